@@ -0,0 +1,82 @@
+use bintrie::{BinTrie, CompressedTrie};
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::hint::black_box;
+
+const ITEMS: u32 = 1 << 20;
+
+/// A random 64-bit key for every item.
+fn key(n: u32) -> u64 {
+    let mut z = u64::from(n).wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ z >> 30).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ z >> 27).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ z >> 31
+}
+
+fn lookup(n: u32, l: u32) -> bool {
+    key(n) >> (63 - l) & 1 == 1
+}
+
+fn build() -> (BinTrie, CompressedTrie) {
+    let mut trie = BinTrie::new_depth(64);
+    let mut compressed = CompressedTrie::new_depth(64);
+    for n in 0..ITEMS {
+        trie.insert(n, |l| lookup(n, l), lookup);
+        compressed.insert(n, |l| lookup(n, l), lookup);
+    }
+    (trie, compressed)
+}
+
+fn get(c: &mut Criterion) {
+    let (trie, compressed) = build();
+    // Criterion only measures time, so the memory is reported alongside it.
+    println!(
+        "memory: BinTrie {} bytes in {} nodes, CompressedTrie {} bytes in {} nodes",
+        trie.stats().heap_bytes,
+        trie.node_count(),
+        compressed.memory_usage(),
+        compressed.node_count()
+    );
+    let mut group = c.benchmark_group("get random 64-bit keys");
+    let mut n = 0u32;
+    group.bench_function("BinTrie", |b| {
+        b.iter(|| {
+            n = n.wrapping_add(0x5851_F42D) % ITEMS;
+            black_box(trie.get(|l| lookup(n, l)))
+        })
+    });
+    let mut n = 0u32;
+    group.bench_function("CompressedTrie", |b| {
+        b.iter(|| {
+            n = n.wrapping_add(0x5851_F42D) % ITEMS;
+            black_box(compressed.get(|l| lookup(n, l)))
+        })
+    });
+    group.finish();
+}
+
+fn insert(c: &mut Criterion) {
+    let mut group = c.benchmark_group("insert random 64-bit keys");
+    group.sample_size(10);
+    group.bench_function("BinTrie", |b| {
+        b.iter(|| {
+            let mut trie = BinTrie::new_depth(64);
+            for n in 0..ITEMS {
+                trie.insert(n, |l| lookup(n, l), lookup);
+            }
+            trie
+        })
+    });
+    group.bench_function("CompressedTrie", |b| {
+        b.iter(|| {
+            let mut trie = CompressedTrie::new_depth(64);
+            for n in 0..ITEMS {
+                trie.insert(n, |l| lookup(n, l), lookup);
+            }
+            trie
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, get, insert);
+criterion_main!(benches);
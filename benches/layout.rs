@@ -0,0 +1,74 @@
+use bintrie::BinTrie;
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use std::hint::black_box;
+
+const ITEMS: u32 = 1 << 20;
+
+fn key(n: u32) -> u32 {
+    n.wrapping_mul(0x9E37_79B9).rotate_left(13) ^ n
+}
+
+fn lookup(n: u32, l: u32) -> bool {
+    key(n) >> l & 1 == 1
+}
+
+/// Builds a trie whose nodes are in insertion order, with keys that arrive in no particular order.
+fn build() -> BinTrie {
+    let mut trie = BinTrie::new_depth(32);
+    for n in 0..ITEMS {
+        trie.insert(n, |l| lookup(n, l), lookup);
+    }
+    trie
+}
+
+fn get(c: &mut Criterion) {
+    let mut insertion = build();
+    // Count the accesses of a sample of the lookups to decide which nodes are hot.
+    insertion.count_accesses();
+    for n in (0..ITEMS).step_by(64) {
+        insertion.get(|l| lookup(n, l));
+    }
+    let reorganized = insertion.reorganize(1 << 12);
+    insertion.stop_counting_accesses();
+    let mut optimized = insertion.clone();
+    optimized.optimize_layout();
+    let mut group = c.benchmark_group("get");
+    let mut n = 0u32;
+    group.bench_function("reorganize", |b| {
+        b.iter(|| {
+            n = n.wrapping_add(0x5851_F42D) % ITEMS;
+            black_box(reorganized.get(|l| lookup(n, l)))
+        })
+    });
+    for (name, trie) in [
+        ("insertion order", &insertion),
+        ("optimize_layout", &optimized),
+    ] {
+        let mut n = 0u32;
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                // Step through the items in an order unrelated to their keys.
+                n = n.wrapping_add(0x5851_F42D) % ITEMS;
+                black_box(trie.get(|l| lookup(n, l)))
+            })
+        });
+    }
+    group.finish();
+}
+
+fn optimize(c: &mut Criterion) {
+    let trie = build();
+    c.bench_function("optimize_layout", |b| {
+        b.iter_batched(
+            || trie.clone(),
+            |mut trie| {
+                trie.optimize_layout();
+                trie
+            },
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+criterion_group!(benches, get, optimize);
+criterion_main!(benches);
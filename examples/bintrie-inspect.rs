@@ -0,0 +1,179 @@
+//! Inspects a serialized trie from the command line.
+//!
+//! ```text
+//! bintrie-inspect <trie> stats
+//! bintrie-inspect <trie> dot [<prefix>]
+//! bintrie-inspect <trie> radius <keys> <key> <radius>
+//! bintrie-inspect <trie> demo <keys>
+//! ```
+//!
+//! The trie is either written by `BinTrie::write_to` or by `BinTrie::write_aligned_to`,
+//! in which case it is borrowed as a `BinTrieRef` first. Both formats are validated
+//! when they are loaded, and a trie that fails to validate is reported along with
+//! the items that could still be reached.
+//!
+//! The prefix of `dot` is written as bits, such as `0110`, starting with bit `0` of
+//! the key. Since the trie does not store keys, `radius` needs a file of keys, which
+//! holds the key of item `n` as the `n`th little-endian `u64`, where bit `l` of the
+//! key is `key >> l & 1`. The query `key` is a hexadecimal `u64` in the same form.
+//!
+//! To try it out, `demo` writes a trie of every key in a file of keys:
+//!
+//! ```text
+//! cargo run --example bintrie-inspect -- demo.trie demo keys.bin
+//! cargo run --example bintrie-inspect -- demo.trie stats
+//! ```
+
+use bintrie::{BinTrie, BinTrieRef, LoadMode};
+use std::convert::TryInto;
+use std::error::Error;
+use std::{env, fs, process};
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<String>>();
+    let args = args.iter().map(String::as_str).collect::<Vec<&str>>();
+    if let Err(error) = run(&args) {
+        eprintln!("error: {}", error);
+        eprintln!(
+            "usage: bintrie-inspect <trie> (stats | dot [<prefix>] | radius <keys> <key> <radius> | demo <keys>)"
+        );
+        process::exit(1);
+    }
+}
+
+fn run(args: &[&str]) -> Result<()> {
+    match *args {
+        [path, "demo", keys] => demo(path, &read_keys(keys)?),
+        [path, "stats"] => {
+            let trie = load(path)?;
+            let stats = trie.stats();
+            println!("depth: {}", trie.depth());
+            println!("items: {}", stats.items);
+            println!("internal nodes: {}", stats.internal_nodes);
+            println!("free nodes: {}", stats.free_nodes);
+            println!("leaves: {}", stats.leaves);
+            println!("terminated items: {}", stats.terminated_items);
+            println!("heap bytes: {}", stats.heap_bytes);
+            println!("max leaf depth: {}", stats.max_leaf_depth);
+            println!("mean leaf depth: {:.2}", stats.mean_leaf_depth);
+            println!("level  nodes  leaves  occupancy");
+            let levels = stats
+                .nodes_per_level
+                .len()
+                .max(stats.leaves_per_level.len());
+            for level in 0..levels {
+                let at = |counts: &[usize]| counts.get(level).copied().unwrap_or(0);
+                println!(
+                    "{:>5}  {:>5}  {:>6}  {:>9.4}",
+                    level,
+                    at(&stats.nodes_per_level),
+                    at(&stats.leaves_per_level),
+                    stats.occupancy(level as u32)
+                );
+            }
+            Ok(())
+        }
+        [path, "dot"] => dot(path, ""),
+        [path, "dot", prefix] => dot(path, prefix),
+        [path, "radius", keys, key, radius] => {
+            let trie = load(path)?;
+            let keys = read_keys(keys)?;
+            let key = u64::from_str_radix(key.trim_start_matches("0x"), 16)?;
+            let radius = radius.parse::<u32>()?;
+            let lookup = |item: u32, l: u32| keys[item as usize] >> l & 1 == 1;
+            let mut found = trie
+                .within_distance(|l| key >> l & 1 == 1, radius, lookup)
+                .collect::<Vec<(u32, u32)>>();
+            found.sort_by_key(|&(item, distance)| (distance, item));
+            for (item, distance) in found {
+                println!("{} {:#018x} {}", item, keys[item as usize], distance);
+            }
+            Ok(())
+        }
+        _ => Err("unknown command".into()),
+    }
+}
+
+fn dot(path: &str, prefix: &str) -> Result<()> {
+    if prefix.len() > 64 || prefix.chars().any(|c| c != '0' && c != '1') {
+        return Err("the prefix must be at most 64 bits of 0 and 1".into());
+    }
+    let trie = load(path)?;
+    let len = prefix.len() as u32;
+    if len > trie.depth() {
+        return Err("the prefix is longer than the depth of the trie".into());
+    }
+    let bits = u64::from_str_radix(prefix, 2).unwrap_or(0);
+    let mut dot = String::new();
+    trie.write_dot_under(&mut dot, bits, len, true)?;
+    print!("{}", dot);
+    Ok(())
+}
+
+/// Loads a trie in either format, reporting a trie that fails to validate.
+fn load(path: &str) -> Result<BinTrie> {
+    let bytes = fs::read(path)?;
+    if bytes.starts_with(b"BTRA") {
+        // The borrowed format needs the bytes aligned for its internal nodes.
+        let mut aligned = vec![0; bytes.len() + 3];
+        let offset = aligned.as_ptr().align_offset(4);
+        let aligned = &mut aligned[offset..offset + bytes.len()];
+        aligned.copy_from_slice(&bytes);
+        let borrowed = match BinTrieRef::from_bytes(aligned) {
+            Ok(borrowed) => borrowed,
+            Err(error) => {
+                let mut trie = BinTrieRef::from_bytes_with(aligned, LoadMode::Lenient)?.to_trie();
+                let report = trie.repair();
+                eprintln!(
+                    "the trie is invalid ({}), so {} reachable items were kept and {} were lost",
+                    error,
+                    trie.len(),
+                    report.lost_items.len()
+                );
+                return Ok(trie);
+            }
+        };
+        eprintln!("loaded a borrowed trie of {} items", borrowed.len());
+        return Ok(borrowed.to_trie());
+    }
+    match BinTrie::read_from(&bytes[..]) {
+        Ok(trie) => Ok(trie),
+        Err(error) => {
+            let mut trie = BinTrie::read_from_with(&bytes[..], LoadMode::Lenient)?;
+            let report = trie.repair();
+            eprintln!(
+                "the trie is invalid ({}), so {} reachable items were kept and {} were lost",
+                error,
+                trie.len(),
+                report.lost_items.len()
+            );
+            Ok(trie)
+        }
+    }
+}
+
+fn read_keys(path: &str) -> Result<Vec<u64>> {
+    let bytes = fs::read(path)?;
+    if bytes.len() % 8 != 0 {
+        return Err("the file of keys must hold whole u64 keys".into());
+    }
+    Ok(bytes
+        .chunks_exact(8)
+        .map(|key| u64::from_le_bytes(key.try_into().unwrap()))
+        .collect())
+}
+
+/// Writes a trie of every key, which can be inspected with the other commands.
+fn demo(path: &str, keys: &[u64]) -> Result<()> {
+    let mut trie = BinTrie::new_depth(64);
+    let lookup = |item: u32, l: u32| keys[item as usize] >> l & 1 == 1;
+    for (item, key) in keys.iter().enumerate() {
+        trie.try_insert(item as u32, |l| key >> l & 1 == 1, lookup)?;
+    }
+    let mut bytes = vec![];
+    trie.write_to(&mut bytes)?;
+    fs::write(path, bytes)?;
+    Ok(())
+}
@@ -0,0 +1,143 @@
+use crate::{BinTrie, Internal, HIGH};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// The shape of a trie and what each layout option would be expected to save.
+///
+/// Returned by `BinTrie::compression_advisor`. Lookup costs are measured in internal
+/// nodes visited, which is what dominates the latency of `get` on large tries.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CompressionAdvice {
+    /// The number of internal nodes in use at each level, starting with the root.
+    pub nodes_per_level: Vec<usize>,
+    /// Internal nodes below the root with a single child and no terminated item,
+    /// which path compression would remove.
+    pub chain_nodes: usize,
+    /// The length of the longest run of chain nodes along one path.
+    pub longest_chain: u32,
+    /// The mean number of internal nodes visited to reach an item.
+    pub mean_lookup_steps: f64,
+    /// The mean number of internal nodes that would be visited with path compression.
+    pub mean_compressed_steps: f64,
+    /// Leaves on the last level, where an item with the same key replaces the leaf
+    /// unless buckets are used.
+    pub full_depth_leaves: usize,
+    /// Items that are already kept in buckets.
+    pub bucketed_items: usize,
+    /// The number of bits for `build_top_table` that is advised, or `0` if the top
+    /// levels are too sparse for a table to pay off.
+    pub top_table_bits: u32,
+}
+
+impl CompressionAdvice {
+    /// The bytes of internal nodes that path compression would save.
+    pub fn compression_savings(&self) -> usize {
+        self.chain_nodes * core::mem::size_of::<Internal>()
+    }
+
+    /// The bytes taken by the advised top table.
+    pub fn top_table_bytes(&self) -> usize {
+        if self.top_table_bits == 0 {
+            0
+        } else {
+            4 << self.top_table_bits
+        }
+    }
+
+    /// The internal nodes visited per lookup that the advised top table would save.
+    ///
+    /// The table replaces the nodes of the top levels with one table access.
+    pub fn top_table_saved_steps(&self) -> u32 {
+        self.top_table_bits.saturating_sub(1)
+    }
+}
+
+impl BinTrie {
+    /// Analyzes the shape of the trie to estimate the savings of each layout option.
+    ///
+    /// Runs of internal nodes with a single child are what path compression removes,
+    /// leaves on the last level are where buckets keep items that would otherwise be
+    /// replaced, and the top levels are advised to be replaced with a table from
+    /// `build_top_table` as long as each of them is at least half full.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let mut trie = BinTrie::new_depth(24);
+    /// let lookup = |n: u32, l: u32| n >> (23 - l) & 1 == 1;
+    /// // Dense in the top levels, but every key ends with a long run of zeros.
+    /// for n in 0..256 {
+    ///     trie.insert(n << 16, |l| lookup(n << 16, l), lookup);
+    /// }
+    /// trie.insert(1, |l| lookup(1, l), lookup);
+    /// let advice = trie.compression_advisor();
+    /// assert_eq!(advice.nodes_per_level[..3], [1, 2, 4]);
+    /// assert_eq!(advice.top_table_bits, 8);
+    /// assert_eq!(advice.longest_chain, 15);
+    /// assert!(advice.mean_compressed_steps < advice.mean_lookup_steps);
+    /// ```
+    pub fn compression_advisor(&self) -> CompressionAdvice {
+        let mut advice = CompressionAdvice::default();
+        if let Some(buckets) = &self.buckets {
+            advice.bucketed_items = buckets.values().map(Vec::len).sum();
+        }
+        let mut steps = 0;
+        let mut compressed_steps = 0;
+        let mut reached = 0;
+        // Each internal node with its level, the length of the run of chain nodes
+        // ending at it, and the chain nodes on its path.
+        let mut stack = vec![(0, 0, 0, 0)];
+        while let Some((index, level, run, chained)) = stack.pop() {
+            let children = match self.internals.get(index as usize) {
+                // Suspect tries may point past the end or deeper than the depth.
+                Some(internal) if level < self.depth => internal.0,
+                _ => continue,
+            };
+            if advice.nodes_per_level.len() <= level as usize {
+                advice.nodes_per_level.resize(level as usize + 1, 0);
+            }
+            advice.nodes_per_level[level as usize] += 1;
+            if self.terminal(index).is_some() {
+                reached += 1;
+                steps += level;
+                compressed_steps += level - chained;
+            }
+            let is_chain = index != 0 && self.terminal(index).is_none() && children.contains(&0);
+            let (run, chained) = if is_chain {
+                advice.chain_nodes += 1;
+                advice.longest_chain = advice.longest_chain.max(run + 1);
+                (run + 1, chained + 1)
+            } else {
+                (0, chained)
+            };
+            for n in children {
+                if n & HIGH != 0 {
+                    let weight = 1 + self.bucket(n & !HIGH).len() as u32;
+                    reached += weight;
+                    steps += weight * (level + 1);
+                    compressed_steps += weight * (level + 1 - chained);
+                    if level + 1 == self.depth {
+                        advice.full_depth_leaves += 1;
+                    }
+                } else if n != 0 {
+                    stack.push((n, level + 1, run, chained));
+                }
+            }
+        }
+        if reached != 0 {
+            advice.mean_lookup_steps = f64::from(steps) / f64::from(reached);
+            advice.mean_compressed_steps = f64::from(compressed_steps) / f64::from(reached);
+        }
+        advice.top_table_bits = advice
+            .nodes_per_level
+            .iter()
+            .enumerate()
+            .take(24.min(self.depth as usize - 1))
+            .take_while(|&(level, &nodes)| nodes * 2 >= 1 << level)
+            .count() as u32;
+        if advice.top_table_bits == 1 {
+            // A table for the root alone saves nothing.
+            advice.top_table_bits = 0;
+        }
+        advice
+    }
+}
@@ -0,0 +1,183 @@
+use crate::explore::CANCEL_INTERVAL;
+use crate::{BatchScratch, BinTrie, Child, Internal};
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// How many lookups `get_batch` keeps in flight at once.
+const LANES: usize = 8;
+
+/// Hints that the internal node will be read soon, so it is fetched into the cache
+/// while other lookups are working.
+#[inline(always)]
+fn prefetch(internal: &Internal) {
+    #[cfg(target_arch = "x86_64")]
+    {
+        use core::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+        // SAFETY: SSE is part of the x86_64 baseline, and a prefetch is only a hint
+        // that never faults, even for an address that can't be read.
+        unsafe { _mm_prefetch::<_MM_HINT_T0>((internal as *const Internal).cast()) };
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    let _ = internal;
+}
+
+impl BinTrie {
+    /// Performs a lookup for every key, like calling `get` for each of them.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the key.
+    ///
+    /// Several lookups are interleaved, each going down one level at a time, and the
+    /// next internal node of every lookup is prefetched. While one lookup waits on
+    /// memory the others make progress, which hides most of the latency of the cache
+    /// misses on large tries. The results are in the order of `keys`.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let mut trie = BinTrie::new_depth(24);
+    /// let lookup = |n: u32, l: u32| n.wrapping_mul(0x9E37_79B9) >> l & 1 == 1;
+    /// for n in (0..10_000).step_by(3) {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// let keys = (0..100).map(|n| move |l| lookup(n, l)).collect::<Vec<_>>();
+    /// let found = trie.get_batch(&keys);
+    /// for (n, key) in keys.iter().enumerate() {
+    ///     assert_eq!(found[n], trie.get(key));
+    /// }
+    /// assert_eq!(found[3], Some(3));
+    /// ```
+    pub fn get_batch<K>(&self, keys: &[K]) -> Vec<Option<u32>>
+    where
+        K: Fn(u32) -> bool,
+    {
+        let mut scratch = BatchScratch::new();
+        self.get_batch_in(keys, &mut scratch);
+        scratch.found
+    }
+
+    /// Performs a lookup for every key like `get_batch`, but gives up once `cancel` is set.
+    ///
+    /// The flag is checked every `64` internal nodes that the lookups pass through, so
+    /// a huge batch can be aborted from another thread. Returns `None` if the batch
+    /// was cancelled before every lookup finished.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// use std::sync::atomic::AtomicBool;
+    /// let mut trie = BinTrie::new_depth(24);
+    /// let lookup = |n: u32, l: u32| n.wrapping_mul(0x9E37_79B9) >> l & 1 == 1;
+    /// for n in 0..10_000 {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// let keys = (0..10_000).map(|n| move |l| lookup(n, l)).collect::<Vec<_>>();
+    /// let found = trie.get_batch_cancellable(&keys, &AtomicBool::new(false));
+    /// assert_eq!(found, Some(trie.get_batch(&keys)));
+    /// assert_eq!(trie.get_batch_cancellable(&keys, &AtomicBool::new(true)), None);
+    /// ```
+    pub fn get_batch_cancellable<K>(
+        &self,
+        keys: &[K],
+        cancel: &AtomicBool,
+    ) -> Option<Vec<Option<u32>>>
+    where
+        K: Fn(u32) -> bool,
+    {
+        let mut scratch = BatchScratch::new();
+        if self.get_batch_with(keys, &mut scratch, Some(cancel)) {
+            Some(scratch.found)
+        } else {
+            None
+        }
+    }
+
+    /// Performs a lookup for every key like `get_batch`, but keeps the lookups in
+    /// flight and the results in `scratch`.
+    ///
+    /// The results are only valid until the scratch is used again.
+    pub fn get_batch_in<'s, K>(
+        &self,
+        keys: &[K],
+        scratch: &'s mut BatchScratch,
+    ) -> &'s [Option<u32>]
+    where
+        K: Fn(u32) -> bool,
+    {
+        self.get_batch_with(keys, scratch, None);
+        &scratch.found
+    }
+
+    /// Performs the lookups of `get_batch_in`, checking `cancel` every `CANCEL_INTERVAL`
+    /// internal nodes.
+    ///
+    /// Returns `false` if the lookups were cancelled before they all finished.
+    fn get_batch_with<K>(
+        &self,
+        keys: &[K],
+        scratch: &mut BatchScratch,
+        cancel: Option<&AtomicBool>,
+    ) -> bool
+    where
+        K: Fn(u32) -> bool,
+    {
+        let found = &mut scratch.found;
+        found.clear();
+        if self.is_suspect() {
+            found.extend(keys.iter().map(|key| self.get_checked(key)));
+            return true;
+        }
+        found.resize(keys.len(), None);
+        // The lookups in flight, as the position of their key, their internal node and its level.
+        let lanes = &mut scratch.lanes;
+        lanes.clear();
+        let mut next = 0;
+        let mut visited = 0usize;
+        loop {
+            while lanes.len() < LANES && next < keys.len() {
+                let mut key = &keys[next];
+                // Skip the top levels if there is a table for them.
+                match &self.top {
+                    Some(top) => match Child::unpack(top.slot(&mut key)) {
+                        Child::Empty => {}
+                        Child::Leaf(m) => found[next] = Some(m),
+                        Child::Internal(m) if top.bits < self.depth => {
+                            prefetch(&self.internals[m as usize]);
+                            lanes.push((next, m as usize, top.bits));
+                        }
+                        Child::Internal(_) => {}
+                    },
+                    None => lanes.push((next, 0, 0)),
+                }
+                next += 1;
+            }
+            if lanes.is_empty() {
+                return true;
+            }
+            let mut lane = 0;
+            while lane < lanes.len() {
+                // Give up on the whole batch if it was cancelled.
+                if visited.is_multiple_of(CANCEL_INTERVAL)
+                    && cancel.is_some_and(|cancel| cancel.load(Ordering::Relaxed))
+                {
+                    return false;
+                }
+                visited += 1;
+                let (position, index, level) = lanes[lane];
+                let side = keys[position](level) as usize;
+                // This unsafe block is only used to allow indexing [u32; 2] by a `1` or `0`.
+                let child = unsafe { *self.internals.get_unchecked(index).0.get_unchecked(side) };
+                match Child::unpack(child) {
+                    // Internal node encountered, so go on from it next round.
+                    Child::Internal(m) if level + 1 < self.depth => {
+                        prefetch(&self.internals[m as usize]);
+                        lanes[lane] = (position, m as usize, level + 1);
+                        lane += 1;
+                        continue;
+                    }
+                    Child::Leaf(m) => found[position] = Some(m),
+                    // Either nothing is here or the key ran out of levels.
+                    Child::Empty | Child::Internal(_) => {}
+                }
+                lanes.swap_remove(lane);
+            }
+        }
+    }
+}
@@ -0,0 +1,278 @@
+use crate::{BinTrie, FastMap, Internal, LoadMode, HIGH};
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+use std::convert::TryFrom;
+use std::io::{self, Read, Write};
+
+/// The first bytes of every trie written by `BinTrie::write_to`.
+const MAGIC: [u8; 4] = *b"BTRI";
+/// The version of the format written by `BinTrie::write_to`.
+const VERSION: u8 = 1;
+
+fn invalid_data<E>(error: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+fn write_varint<W>(writer: &mut W, mut value: u64) -> io::Result<()>
+where
+    W: Write,
+{
+    let mut buffer = [0; 10];
+    let mut len = 0;
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            buffer[len] = byte;
+            len += 1;
+            break;
+        }
+        buffer[len] = byte | 0x80;
+        len += 1;
+    }
+    writer.write_all(&buffer[..len])
+}
+
+fn read_varint<R>(reader: &mut R) -> io::Result<u64>
+where
+    R: Read,
+{
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0];
+        reader.read_exact(&mut byte)?;
+        value |= u64::from(byte[0] & 0x7F) << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(invalid_data("varint is too long"))
+}
+
+fn read_u32<R>(reader: &mut R) -> io::Result<u32>
+where
+    R: Read,
+{
+    let value = read_varint(reader)?;
+    u32::try_from(value).map_err(|_| invalid_data("value does not fit in 32 bits"))
+}
+
+fn write_u32s<W>(writer: &mut W, values: &[u32]) -> io::Result<()>
+where
+    W: Write,
+{
+    write_varint(writer, values.len() as u64)?;
+    for &value in values {
+        write_varint(writer, u64::from(value))?;
+    }
+    Ok(())
+}
+
+fn read_u32s<R>(reader: &mut R) -> io::Result<Vec<u32>>
+where
+    R: Read,
+{
+    // The length isn't trusted to preallocate, since the data might be corrupt.
+    let len = read_varint(reader)?;
+    (0..len).map(|_| read_u32(reader)).collect()
+}
+
+/// Encodes the child `n` of the internal node at `index`.
+///
+/// Empty children are `0`, leaves have the lowest bit set, and internal nodes
+/// are stored as the zigzag encoded difference from `index`, which is small
+/// when children are stored close to their parents.
+fn encode_child(index: u32, n: u32) -> u64 {
+    match n {
+        0 => 0,
+        n if n & HIGH != 0 => u64::from(n & !HIGH) << 1 | 1,
+        n => {
+            let delta = i64::from(n) - i64::from(index);
+            (((delta << 1) ^ (delta >> 63)) as u64) << 1
+        }
+    }
+}
+
+/// Decodes the child of the internal node at `index` encoded by `encode_child`.
+fn decode_child(index: u32, value: u64) -> io::Result<u32> {
+    let n = if value == 0 {
+        Some(0)
+    } else if value & 1 != 0 {
+        u32::try_from(value >> 1)
+            .ok()
+            .filter(|&item| item & HIGH == 0)
+            .map(|item| item | HIGH)
+    } else {
+        let zigzag = value >> 1;
+        let delta = (zigzag >> 1) as i64 ^ -((zigzag & 1) as i64);
+        u32::try_from(i64::from(index) + delta)
+            .ok()
+            .filter(|&n| n & HIGH == 0)
+    };
+    n.ok_or_else(|| invalid_data("child is out of range"))
+}
+
+impl BinTrie {
+    /// Writes the trie in a compact binary format that can be read by `read_from`.
+    ///
+    /// The format starts with a small header holding a version and the depth of the
+    /// trie, followed by the internal nodes, where every child is a varint. Children
+    /// that are internal nodes are stored relative to their parent, which keeps them
+    /// small. Everything needed to rebuild the trie is written, including its metadata.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let mut trie = BinTrie::new_depth(16);
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// for n in 0..1000 {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// trie.set_metadata(b"v1");
+    /// let mut bytes = vec![];
+    /// trie.write_to(&mut bytes).unwrap();
+    /// let read = BinTrie::read_from(&bytes[..]).unwrap();
+    /// assert!(read.structurally_equal(&trie));
+    /// assert_eq!(read.metadata(), b"v1");
+    /// // Sparse tries have empty children beneath the root as well.
+    /// let mut sparse = BinTrie::new_depth(16);
+    /// for &n in &[0b0000, 0b1000, 0b1100] {
+    ///     sparse.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// let mut bytes = vec![];
+    /// sparse.write_to(&mut bytes).unwrap();
+    /// assert!(BinTrie::read_from(&bytes[..]).unwrap().structurally_equal(&sparse));
+    /// assert!(BinTrie::read_from(&bytes[..bytes.len() / 2]).is_err());
+    /// ```
+    pub fn write_to<W>(&self, mut writer: W) -> io::Result<()>
+    where
+        W: Write,
+    {
+        let writer = &mut writer;
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&[VERSION])?;
+        write_varint(writer, u64::from(self.depth))?;
+        write_varint(writer, self.internals.len() as u64)?;
+        for (index, internal) in self.internals.iter().enumerate() {
+            for &n in &internal.0 {
+                write_varint(writer, encode_child(index as u32, n))?;
+            }
+        }
+        write_varint(writer, self.terminals.len() as u64)?;
+        for (&index, &item) in &self.terminals {
+            write_varint(writer, u64::from(index))?;
+            write_varint(writer, u64::from(item))?;
+        }
+        write_u32s(writer, &self.free)?;
+        match &self.buckets {
+            Some(buckets) => {
+                writer.write_all(&[1])?;
+                write_varint(writer, buckets.len() as u64)?;
+                for (&item, bucket) in buckets {
+                    write_varint(writer, u64::from(item))?;
+                    write_u32s(writer, bucket)?;
+                }
+            }
+            None => writer.write_all(&[0])?,
+        }
+        match &self.insertion_order {
+            Some(order) => {
+                writer.write_all(&[1])?;
+                write_u32s(writer, order)?;
+            }
+            None => writer.write_all(&[0])?,
+        }
+        write_varint(writer, self.metadata.len() as u64)?;
+        writer.write_all(&self.metadata)
+    }
+
+    /// Reads a trie written by `write_to`.
+    ///
+    /// The trie is validated after it is read, so corrupt or malicious data results in
+    /// an error of kind `InvalidData` rather than a trie that can't be used safely.
+    pub fn read_from<R>(reader: R) -> io::Result<BinTrie>
+    where
+        R: Read,
+    {
+        Self::read_from_with(reader, LoadMode::Strict)
+    }
+
+    /// Reads a trie written by `write_to`, treating a trie that breaks the invariants
+    /// of a trie according to the `mode`.
+    ///
+    /// Data that can't be decoded at all is always an error.
+    pub fn read_from_with<R>(mut reader: R, mode: LoadMode) -> io::Result<BinTrie>
+    where
+        R: Read,
+    {
+        let reader = &mut reader;
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(invalid_data("not a trie"));
+        }
+        let mut version = [0];
+        reader.read_exact(&mut version)?;
+        if version[0] != VERSION {
+            return Err(invalid_data(format!("unsupported version {}", version[0])));
+        }
+        let depth = read_u32(reader)?;
+        let len = read_varint(reader)?;
+        let mut internals = vec![];
+        for index in 0..len {
+            let index =
+                u32::try_from(index).map_err(|_| invalid_data("too many internal nodes"))?;
+            let left = decode_child(index, read_varint(reader)?)?;
+            let right = decode_child(index, read_varint(reader)?)?;
+            internals.push(Internal([left, right]));
+        }
+        let mut terminals = BTreeMap::new();
+        for _ in 0..read_varint(reader)? {
+            terminals.insert(read_u32(reader)?, read_u32(reader)?);
+        }
+        let free = read_u32s(reader)?;
+        let mut flag = [0];
+        reader.read_exact(&mut flag)?;
+        let buckets = if flag[0] != 0 {
+            let mut buckets = FastMap::new();
+            for _ in 0..read_varint(reader)? {
+                buckets.insert(read_u32(reader)?, read_u32s(reader)?);
+            }
+            Some(buckets)
+        } else {
+            None
+        };
+        reader.read_exact(&mut flag)?;
+        let insertion_order = if flag[0] != 0 {
+            Some(read_u32s(reader)?)
+        } else {
+            None
+        };
+        let mut metadata = vec![];
+        let metadata_len = read_varint(reader)?;
+        reader.take(metadata_len).read_to_end(&mut metadata)?;
+        if metadata.len() as u64 != metadata_len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let trie = BinTrie {
+            internals,
+            depth,
+            terminals,
+            insertion_order,
+            metadata,
+            top: None,
+            counts: None,
+            len: 0,
+            free,
+            buckets,
+            suspect: vec![],
+            reached_by: vec![],
+            generation: 0,
+            accesses: None,
+        };
+        trie.load(mode).map_err(invalid_data)
+    }
+}
@@ -0,0 +1,397 @@
+use crate::explore::{accept_all, ExploreIter};
+use crate::range::RangeIter;
+use crate::validate::{check_nodes, find_suspects, first_reach};
+use crate::{BinTrie, Child, Internal, IntoHeuristic, InvalidTrieError, LoadMode, Nodes};
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::TryInto;
+use core::{mem, slice};
+#[cfg(feature = "std")]
+use std::io::{self, Write};
+
+/// The first bytes of every trie written by `BinTrie::write_aligned_to`.
+const MAGIC: [u8; 4] = *b"BTRA";
+/// The version of the format written by `BinTrie::write_aligned_to`.
+const VERSION: u32 = 2;
+/// The magic, version, depth, number of internal nodes, number of terminated items,
+/// and the length of the metadata.
+const HEADER_LEN: usize = 24;
+/// The header of version 1, which had no metadata.
+const HEADER_LEN_V1: usize = 20;
+
+/// A read-only trie that borrows its internal nodes directly from bytes.
+///
+/// The bytes are written by `BinTrie::write_aligned_to`, and can be read from a
+/// memory-mapped file without copying or deserializing the internal nodes, which
+/// makes opening a multi-gigabyte index nearly free. The bytes are validated once
+/// when the `BinTrieRef` is made, so they may come from an untrusted source.
+///
+/// ```
+/// # #[cfg(feature = "std")]
+/// # {
+/// # use bintrie::{BinTrie, BinTrieRef, FilterHeuristic, Side};
+/// let mut trie = BinTrie::new_depth(8);
+/// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+/// for n in 0..4 {
+///     trie.insert(n, |l| lookup(n, l), lookup);
+/// }
+/// trie.set_metadata(b"key-encoding-v2");
+/// let mut bytes = vec![];
+/// trie.write_aligned_to(&mut bytes).unwrap();
+/// // This would normally be a memory-mapped file, which is aligned to a page.
+/// let borrowed = BinTrieRef::from_bytes(&bytes).unwrap();
+/// assert_eq!(borrowed.len(), 4);
+/// assert_eq!(borrowed.metadata(), b"key-encoding-v2");
+/// assert_eq!(borrowed.get(|l| lookup(2, l)), Some(2));
+/// assert_eq!(borrowed.items().collect::<Vec<u32>>(), trie.items().collect::<Vec<u32>>());
+/// let left = borrowed.explore(FilterHeuristic(|s| s == Side::Left));
+/// assert_eq!(left.collect::<Vec<u32>>(), vec![0]);
+/// assert!(borrowed.to_trie().structurally_equal(&trie));
+/// assert!(BinTrieRef::from_bytes(&bytes[..bytes.len() - 4]).is_err());
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct BinTrieRef<'a> {
+    internals: &'a [Internal],
+    /// Pairs of an internal node and the item terminated at it, sorted by the node.
+    terminals: &'a [[u32; 2]],
+    /// User-defined metadata written with the trie.
+    metadata: &'a [u8],
+    depth: u32,
+    len: usize,
+    /// What a lenient load found wrong with the nodes, if anything.
+    suspects: Option<Arc<Suspects>>,
+}
+
+/// The problems that `LoadMode::Lenient` found in the nodes of a `BinTrieRef`.
+#[derive(Debug)]
+struct Suspects {
+    /// The sorted internal nodes where problems were found.
+    nodes: Vec<u32>,
+    /// The slot that first reaches each internal node.
+    reached_by: Vec<u64>,
+}
+
+impl<'a> BinTrieRef<'a> {
+    /// Borrows a trie from `bytes` written by `BinTrie::write_aligned_to`.
+    ///
+    /// The bytes must be aligned to 4 bytes, or `InvalidTrieError::Misaligned` is
+    /// returned. The format is little-endian, so the header is always invalid on
+    /// big-endian targets. Every internal node is checked before this returns, which
+    /// takes time and memory proportional to the number of internal nodes.
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<Self, InvalidTrieError> {
+        Self::from_bytes_with(bytes, LoadMode::Strict)
+    }
+
+    /// Borrows a trie from `bytes` like `from_bytes`, treating broken nodes according to the `mode`.
+    ///
+    /// With `LoadMode::Lenient`, nodes that break the invariants of a trie are marked
+    /// as suspect the same way as `BinTrie::read_from_with`, and every query checks each
+    /// index. A header that doesn't match the bytes is still an error, since the nodes
+    /// can't be found without it.
+    ///
+    /// ```
+    /// # #[cfg(feature = "std")]
+    /// # {
+    /// # use bintrie::{BinTrie, BinTrieRef, LoadMode};
+    /// let mut trie = BinTrie::new_depth(8);
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// for n in 0..16 {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// let mut bytes = vec![];
+    /// trie.write_aligned_to(&mut bytes).unwrap();
+    /// // Corrupt the depth in the header so that the nodes are too deep.
+    /// bytes[8] = 2;
+    /// assert!(BinTrieRef::from_bytes(&bytes).is_err());
+    /// let borrowed = BinTrieRef::from_bytes_with(&bytes, LoadMode::Lenient).unwrap();
+    /// assert!(borrowed.is_suspect());
+    /// assert!(!borrowed.suspect_nodes().is_empty());
+    /// assert_eq!(borrowed.items().count(), borrowed.len());
+    /// let mut owned = borrowed.to_trie();
+    /// assert!(owned.is_suspect());
+    /// let report = owned.repair();
+    /// assert_eq!(owned.len() + report.lost_items.len(), 16);
+    /// # }
+    /// ```
+    pub fn from_bytes_with(bytes: &'a [u8], mode: LoadMode) -> Result<Self, InvalidTrieError> {
+        if !(bytes.as_ptr() as usize).is_multiple_of(mem::align_of::<Internal>()) {
+            return Err(InvalidTrieError::Misaligned);
+        }
+        if cfg!(target_endian = "big") || bytes.len() < HEADER_LEN || bytes[..4] != MAGIC {
+            return Err(InvalidTrieError::InvalidHeader);
+        }
+        let word = |i: usize| u32::from_le_bytes(bytes[4 * i..4 * i + 4].try_into().unwrap());
+        let (depth, nodes, terminals) = (word(2), word(3) as usize, word(4) as usize);
+        // Version 1 is still read, and it has no metadata.
+        let (header, metadata_len) = match word(1) {
+            1 => (HEADER_LEN_V1, 0),
+            VERSION if bytes.len() >= HEADER_LEN => (HEADER_LEN, word(5) as usize),
+            _ => return Err(InvalidTrieError::InvalidHeader),
+        };
+        // The metadata is padded so that the nodes after it stay aligned.
+        let start = metadata_len
+            .checked_add(3)
+            .map(|len| len & !3)
+            .and_then(|len| len.checked_add(header));
+        let expected = nodes
+            .checked_add(terminals)
+            .and_then(|pairs| pairs.checked_mul(8))
+            .and_then(|len| len.checked_add(start?));
+        let start = match start {
+            Some(start) if expected == Some(bytes.len()) => start,
+            _ => return Err(InvalidTrieError::InvalidHeader),
+        };
+        let metadata = &bytes[header..header + metadata_len];
+        let internals = &bytes[start..start + nodes * 8];
+        let terminals = &bytes[start + nodes * 8..];
+        // Safety: `Internal` is a transparent `[u32; 2]`, which is valid for any bytes.
+        // Both slices are aligned since the header and the padded metadata are a multiple
+        // of 4 bytes long, and their lengths are exactly the number of pairs.
+        let (internals, terminals) = unsafe {
+            (
+                slice::from_raw_parts(internals.as_ptr() as *const Internal, nodes),
+                slice::from_raw_parts(terminals.as_ptr() as *const [u32; 2], terminals.len() / 8),
+            )
+        };
+        let mut trie = Self {
+            internals,
+            terminals,
+            metadata,
+            depth,
+            len: 0,
+            suspects: None,
+        };
+        match trie.check_invariants() {
+            Ok(len) => trie.len = len,
+            Err(error) if mode == LoadMode::Strict => return Err(error),
+            Err(_) => {
+                let (nodes, reached_by) =
+                    find_suspects(internals.len(), depth, |index| internals[index].0);
+                trie.suspects = Some(Arc::new(Suspects { nodes, reached_by }));
+                // Count whatever a checked traversal can reach.
+                trie.len = trie.items().count();
+            }
+        }
+        Ok(trie)
+    }
+
+    /// Checks the nodes and terminated items, returning the number of items.
+    fn check_invariants(&self) -> Result<usize, InvalidTrieError> {
+        let internals = self.internals;
+        let (reached, leaves) =
+            check_nodes(internals.len(), self.depth, |index| internals[index].0)?;
+        let mut previous = 0;
+        for &[index, _] in self.terminals {
+            // The nodes must be sorted so that they can be searched.
+            if index <= previous || !reached.get(index as usize).copied().unwrap_or(false) {
+                return Err(InvalidTrieError::InvalidTerminal(index));
+            }
+            previous = index;
+        }
+        Ok(leaves.len() + self.terminals.len())
+    }
+
+    /// Perform a lookup for a particular item like `BinTrie::get`.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the key.
+    pub fn get<K>(&self, mut key: K) -> Option<u32>
+    where
+        K: FnMut(u32) -> bool,
+    {
+        let mut index = 0;
+        for i in 0..self.depth {
+            match self.child_checked(index, key(i) as usize) {
+                // Empty node encountered.
+                Child::Empty => return None,
+                // Leaf node encountered.
+                Child::Leaf(m) => return Some(m),
+                // Internal node encountered.
+                Child::Internal(m) => index = m,
+            }
+        }
+        None
+    }
+
+    /// Get an iterator over the items in the trie.
+    pub fn items(&self) -> impl Iterator<Item = u32> + 'a {
+        RangeIter::all(self.clone())
+    }
+
+    /// Iterates over the trie while using the `heuristic` to guide iteration, like `BinTrie::explore`.
+    pub fn explore<H>(&self, heuristic: H) -> impl Iterator<Item = u32> + 'a
+    where
+        H: IntoHeuristic,
+        H::Heuristic: 'a,
+    {
+        ExploreIter::new(self.clone(), heuristic.into_heuristic(), vec![], accept_all)
+    }
+
+    /// Get the number of items in the trie.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Check if the trie has no items.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Get the maximum depth of the trie.
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    /// Gets the user-defined metadata that was attached to the trie when it was written.
+    pub fn metadata(&self) -> &'a [u8] {
+        self.metadata
+    }
+
+    /// Checks if the trie was borrowed leniently and broke the invariants of a trie.
+    pub fn is_suspect(&self) -> bool {
+        self.suspects.is_some()
+    }
+
+    /// Gets the sorted internal nodes where a lenient load found problems, like `BinTrie::suspect_nodes`.
+    pub fn suspect_nodes(&self) -> &[u32] {
+        self.suspects
+            .as_ref()
+            .map_or(&[][..], |suspects| &suspects.nodes[..])
+    }
+
+    /// Copies the borrowed trie into an owned `BinTrie` that can be modified.
+    pub fn to_trie(&self) -> BinTrie {
+        let mut trie = BinTrie::new_depth(self.depth);
+        trie.internals = self.internals.to_vec();
+        trie.terminals = self
+            .terminals
+            .iter()
+            .map(|&[index, item]| (index, item))
+            .collect();
+        trie.len = self.len;
+        trie.set_metadata(self.metadata);
+        if let Some(suspects) = &self.suspects {
+            trie.suspect = suspects.nodes.clone();
+            trie.reached_by = suspects.reached_by.clone();
+        }
+        trie
+    }
+}
+
+impl<'a> Nodes for BinTrieRef<'a> {
+    type Index = u32;
+
+    #[inline(always)]
+    fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    #[inline(always)]
+    fn root(&self) -> u32 {
+        0
+    }
+
+    #[inline(always)]
+    fn child(&self, index: u32, position: usize) -> Child {
+        Child::unpack(self.internals[index as usize].0[position])
+    }
+
+    #[inline(always)]
+    unsafe fn child_unchecked(&self, index: u32, position: usize) -> Child {
+        Child::unpack(
+            *self
+                .internals
+                .get_unchecked(index as usize)
+                .0
+                .get_unchecked(position),
+        )
+    }
+
+    #[inline(always)]
+    fn terminal(&self, index: u32) -> Option<u32> {
+        if self.terminals.is_empty() {
+            return None;
+        }
+        let position = self
+            .terminals
+            .binary_search_by_key(&index, |&[index, _]| index)
+            .ok()?;
+        Some(self.terminals[position][1])
+    }
+
+    #[inline(always)]
+    fn child_checked(&self, index: u32, position: usize) -> Child {
+        let index = index as usize;
+        let n = self
+            .internals
+            .get(index)
+            .map_or(0, |internal| internal.0[position]);
+        Child::unpack(match &self.suspects {
+            // Only follow the first slot that reaches each node of a suspect trie.
+            Some(suspects) => first_reach(&suspects.reached_by, index, position, n),
+            None => n,
+        })
+    }
+
+    #[inline(always)]
+    fn bucket_item(&self, _item: u32, _position: usize) -> Option<u32> {
+        // Tries with buckets can't be borrowed.
+        None
+    }
+
+    #[inline(always)]
+    fn is_verified(&self) -> bool {
+        // Borrowed tries are validated when they are made, unless they were loaded leniently.
+        self.suspects.is_none()
+    }
+}
+
+#[cfg(feature = "std")]
+impl BinTrie {
+    /// Writes the trie in an uncompressed format that can be borrowed by `BinTrieRef`.
+    ///
+    /// The internal nodes are written exactly as they are stored, so the output is
+    /// larger than that of `write_to`, but it can be used in place without reading it.
+    /// Only the nodes, terminated items and metadata are written, so the insertion order
+    /// is not kept, and tries that have buckets result in an error of kind `InvalidInput`.
+    pub fn write_aligned_to<W>(&self, mut writer: W) -> io::Result<()>
+    where
+        W: Write,
+    {
+        if self
+            .buckets
+            .as_ref()
+            .is_some_and(|buckets| !buckets.is_empty())
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "tries with buckets can't be borrowed",
+            ));
+        }
+        let mut words = vec![
+            u32::from_le_bytes(MAGIC),
+            VERSION,
+            self.depth,
+            self.internals.len() as u32,
+            self.terminals.len() as u32,
+            self.metadata.len() as u32,
+        ];
+        // The metadata is padded with zeros to keep the nodes aligned.
+        let mut metadata = self.metadata.clone();
+        metadata.resize((metadata.len() + 3) & !3, 0);
+        words.extend(
+            metadata
+                .chunks_exact(4)
+                .map(|chunk| u32::from_le_bytes(chunk.try_into().unwrap())),
+        );
+        words.extend(self.internals.iter().flat_map(|internal| internal.0));
+        words.extend(
+            self.terminals
+                .iter()
+                .flat_map(|(&index, &item)| [index, item]),
+        );
+        let bytes: Vec<u8> = words.iter().flat_map(|word| word.to_le_bytes()).collect();
+        writer.write_all(&bytes)
+    }
+}
@@ -0,0 +1,101 @@
+use crate::{BinTrie, FastMap, HIGH};
+use alloc::vec;
+use alloc::vec::Vec;
+
+impl BinTrie {
+    /// Keeps every item that shares a whole key rather than replacing the existing one.
+    ///
+    /// Without buckets, inserting an item whose key matches an existing item in every
+    /// bit replaces it, which silently loses items when keys are hashes that can
+    /// collide. With buckets, the colliding items are all kept, and `items`, `explore`
+    /// and the range scans yield each one right after the item in its leaf. `get` only finds the first item with the key, while `get_all` finds
+    /// every one of them, and `remove` removes the first one. Since nothing is
+    /// replaced, the `merge` of `insert_merge` is never called.
+    ///
+    /// Items are bucketed with the item stored in the leaf, so when using buckets an
+    /// item must not be inserted under more than one key.
+    ///
+    /// ```
+    /// # use bintrie::{BinTrie, HammingNearestFirst};
+    /// let mut trie = BinTrie::new_depth(2).with_buckets();
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// for n in 0..8 {
+    ///     assert_eq!(trie.insert(n, |l| lookup(n, l), lookup), None);
+    /// }
+    /// assert_eq!(trie.len(), 8);
+    /// assert_eq!(trie.get_all(|l| lookup(1, l)), vec![1, 5]);
+    /// assert_eq!(trie.items().collect::<Vec<u32>>(), vec![0, 4, 2, 6, 1, 5, 3, 7]);
+    /// let explored = trie.explore(HammingNearestFirst::new(0)).collect::<Vec<u32>>();
+    /// assert_eq!(explored, vec![0, 4, 2, 6, 1, 5, 3, 7]);
+    /// let mut near = trie.within_distance(|_| false, 0, lookup).collect::<Vec<_>>();
+    /// near.sort();
+    /// assert_eq!(near, vec![(0, 0), (4, 0)]);
+    /// let from = trie.range_from(|l| lookup(2, l), lookup).collect::<Vec<u32>>();
+    /// assert_eq!(from, vec![2, 6, 1, 5, 3, 7]);
+    /// let frozen = trie.clone().freeze();
+    /// assert_eq!(frozen.items().collect::<Vec<u32>>(), vec![0, 4, 2, 6, 1, 5, 3, 7]);
+    /// assert_eq!(trie.remove(|l| lookup(1, l), lookup), Some(1));
+    /// assert_eq!(trie.get_all(|l| lookup(1, l)), vec![5]);
+    /// ```
+    pub fn with_buckets(mut self) -> Self {
+        if self.buckets.is_none() {
+            self.buckets = Some(FastMap::new());
+        }
+        self
+    }
+
+    /// Perform a lookup for every item with a particular key.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the key.
+    ///
+    /// This only finds more than one item when using `with_buckets`.
+    pub fn get_all<K>(&self, key: K) -> Vec<u32>
+    where
+        K: FnMut(u32) -> bool,
+    {
+        match self.get(key) {
+            Some(item) => core::iter::once(item)
+                .chain(self.bucket(item).iter().copied())
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    /// Gets the other items that share the whole key of the leaf `item`.
+    #[inline(always)]
+    pub(crate) fn bucket(&self, item: u32) -> &[u32] {
+        match &self.buckets {
+            Some(buckets) => buckets.get(&item).map_or(&[], |bucket| &bucket[..]),
+            None => &[],
+        }
+    }
+
+    /// Takes the next item out of the bucket of the leaf `item` so that it can replace the leaf.
+    ///
+    /// Returns the leaf encoding of the next item, or `0` if the bucket is empty.
+    pub(crate) fn take_from_bucket(&mut self, item: u32) -> u32 {
+        let buckets = match &mut self.buckets {
+            Some(buckets) => buckets,
+            None => return 0,
+        };
+        match buckets.remove(&item) {
+            Some(mut bucket) if !bucket.is_empty() => {
+                let next = bucket.remove(0);
+                if !bucket.is_empty() {
+                    buckets.insert(next, bucket);
+                }
+                next | HIGH
+            }
+            _ => 0,
+        }
+    }
+
+    /// Moves the bucket of the leaf `old` to the leaf `new` which replaced it.
+    pub(crate) fn rename_bucket(&mut self, old: u32, new: u32) {
+        if let Some(buckets) = &mut self.buckets {
+            if let Some(bucket) = buckets.remove(&old) {
+                buckets.insert(new, bucket);
+            }
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use crate::{BinTrie, BinTrieError, Child, Internal, HIGH};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Builds a trie from items that arrive in key order.
+///
+/// In key order, the level of every leaf only depends on how many key bits it
+/// shares with the items right before and after it. The builder therefore holds
+/// on to the most recently pushed item until the next key arrives, and then places
+/// it for good, so the trie is built bottom-up in a single pass without ever
+/// descending from the root or moving a leaf. Internal nodes are allocated in
+/// the order they are visited by `items`, which gives lookups good locality.
+///
+/// Only the previous key is remembered, so the builder never needs a lookup
+/// function into previously inserted items. This allows building a trie from a
+/// stream of sorted records, such as the output of an external merge sort,
+/// without holding the keys in memory.
+///
+/// ```
+/// # use bintrie::SortedBuilder;
+/// let key = |n: u32| move |l: u32| n >> (15 - l) & 1 == 1;
+/// let mut builder = SortedBuilder::new(16);
+/// for n in (0..1000).map(|n| n * 37) {
+///     builder.push(n, key(n));
+/// }
+/// let trie = builder.finish();
+/// assert_eq!(trie.get(key(370)), Some(370));
+/// assert_eq!(trie.items().count(), 1000);
+/// ```
+#[derive(Clone, Debug)]
+pub struct SortedBuilder {
+    trie: BinTrie,
+    /// The internal nodes on the path of the pending item, from the root down to
+    /// the level where it splits off from the item before it.
+    path: Vec<u32>,
+    /// The most recently pushed item, which is not placed until the next key is known.
+    pending: Option<u32>,
+    /// The packed key bits of the pending item.
+    pending_key: Vec<u64>,
+    /// The packed key bits of the item being pushed.
+    key: Vec<u64>,
+}
+
+impl SortedBuilder {
+    /// Makes a builder for a trie with a given maximum `depth`.
+    pub fn new(depth: u32) -> Self {
+        let words = (depth as usize).div_ceil(64);
+        Self {
+            trie: BinTrie::new_depth(depth),
+            path: vec![0],
+            pending: None,
+            pending_key: vec![0; words],
+            key: vec![0; words],
+        }
+    }
+
+    /// Inserts the next item, which must not have a smaller key than the previous item.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the key.
+    ///
+    /// Returns `Some` of the previous item if it had the same key and was replaced.
+    pub fn push<K>(&mut self, item: u32, mut key: K) -> Option<u32>
+    where
+        K: FnMut(u32) -> bool,
+    {
+        if item & HIGH != 0 {
+            panic!("{}", BinTrieError::ItemTooLarge(item));
+        }
+        let depth = self.trie.depth;
+        for word in &mut self.key {
+            *word = 0;
+        }
+        for n in 0..depth {
+            if key(n) {
+                self.key[n as usize / 64] |= 1 << (n % 64);
+            }
+        }
+        if let Some(pending) = self.pending {
+            let shared = shared_bits(&self.key, &self.pending_key, depth);
+            if shared == depth {
+                self.pending = Some(item);
+                return Some(pending);
+            }
+            assert!(bit(&self.key, shared), "items must be pushed in key order");
+            self.place(pending, shared);
+            // The new item splits off from the pending one at the node on level `shared`.
+            self.path.truncate(shared as usize + 1);
+        }
+        core::mem::swap(&mut self.key, &mut self.pending_key);
+        self.pending = Some(item);
+        None
+    }
+
+    /// Places the pending item, given how many key bits the next item shares with it.
+    fn place(&mut self, item: u32, shared: u32) {
+        // Make the nodes that the next item shares with the pending one.
+        for level in self.path.len() as u32..=shared {
+            let index = self
+                .trie
+                .try_allocate(Internal::default())
+                .unwrap_or_else(|error| panic!("{}", error));
+            let parent = self.path[level as usize - 1];
+            self.trie.internals[parent as usize].0[bit(&self.pending_key, level - 1) as usize] =
+                index;
+            self.path.push(index);
+        }
+        // The leaf goes in the deepest node that it shares with either neighbor.
+        let level = self.path.len() as u32 - 1;
+        let index = self.path[level as usize];
+        self.trie.internals[index as usize].0[bit(&self.pending_key, level) as usize] =
+            Child::Leaf(item).pack();
+        self.trie.record_insertion(item, None);
+    }
+
+    /// Finishes building and returns the trie.
+    pub fn finish(mut self) -> BinTrie {
+        if let Some(pending) = self.pending.take() {
+            self.place(pending, 0);
+        }
+        self.trie
+    }
+}
+
+impl BinTrie {
+    /// Builds a trie from items that are sorted by key, like a `SortedBuilder`.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the key of an item.
+    ///
+    /// Keys are in order when the first bit where two keys differ is `false` for the
+    /// earlier one. Every leaf and internal node is written once, in a single pass
+    /// over the items, rather than descending from the root for each item. When
+    /// items have the same key, the later one replaces the earlier one, like `insert`.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let lookup = |n: u32, l: u32| n >> (19 - l) & 1 == 1;
+    /// let sorted = (0..5000).map(|n| n * 97);
+    /// let trie = BinTrie::from_sorted(sorted.clone().map(|n| (n, move |l| lookup(n, l))), 20);
+    /// let mut inserted = BinTrie::new_depth(20);
+    /// for n in sorted {
+    ///     inserted.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// assert_eq!(trie.len(), 5000);
+    /// assert!(trie.structurally_equal(&inserted));
+    /// assert_eq!(trie.items().collect::<Vec<_>>(), inserted.items().collect::<Vec<_>>());
+    /// ```
+    pub fn from_sorted<I, K>(items: I, depth: u32) -> BinTrie
+    where
+        I: IntoIterator<Item = (u32, K)>,
+        K: FnMut(u32) -> bool,
+    {
+        let mut builder = SortedBuilder::new(depth);
+        for (item, key) in items {
+            builder.push(item, key);
+        }
+        builder.finish()
+    }
+}
+
+fn bit(key: &[u64], n: u32) -> bool {
+    key[n as usize / 64] >> (n % 64) & 1 == 1
+}
+
+/// Counts the leading key bits that `a` and `b` share, up to `depth`.
+fn shared_bits(a: &[u64], b: &[u64], depth: u32) -> u32 {
+    a.iter()
+        .zip(b)
+        .position(|(a, b)| a != b)
+        .map_or(depth, |word| {
+            word as u32 * 64 + (a[word] ^ b[word]).trailing_zeros()
+        })
+        .min(depth)
+}
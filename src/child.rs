@@ -0,0 +1,88 @@
+use crate::{BinTrie, HIGH};
+
+/// A decoded child of an internal node.
+///
+/// Children are stored packed into a `u32`, where `0` is empty, since the
+/// root can never be a child, and the high bit marks a leaf. `Child` only decodes
+/// that packing so code walking the trie can match on the kind of child instead of
+/// testing bits. Leaves are still stored in the slots of their parent rather than
+/// in a vector of their own, so the item of a leaf is the item itself, and items
+/// can't be larger than `BinTrie::MAX_ITEM`. `LargeTrie` and `BinTrie64` store
+/// larger items.
+///
+/// Tries that don't number their internal nodes with a `u32` use a different `I`
+/// to identify them, so every kind of trie can be walked the same way.
+///
+/// ```
+/// # use bintrie::{BinTrie, Child};
+/// let mut trie = BinTrie::new_depth(4);
+/// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+/// trie.insert(0b0000, |l| lookup(0b0000, l), lookup);
+/// trie.insert(0b0010, |l| lookup(0b0010, l), lookup);
+/// assert_eq!(trie.children(0), Some([Child::Internal(1), Child::Empty]));
+/// assert_eq!(trie.children(1), Some([Child::Leaf(0b0000), Child::Leaf(0b0010)]));
+/// assert_eq!(trie.children(2), None);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Child<I = u32> {
+    /// There is nothing on this side.
+    Empty,
+    /// The index of an internal node.
+    Internal(I),
+    /// An item.
+    Leaf(u32),
+}
+
+impl Child {
+    /// Decodes a packed child.
+    #[inline(always)]
+    pub(crate) fn unpack(n: u32) -> Self {
+        match n {
+            0 => Child::Empty,
+            n if n & HIGH != 0 => Child::Leaf(n & !HIGH),
+            n => Child::Internal(n),
+        }
+    }
+
+    /// Encodes a child to be stored in an internal node.
+    ///
+    /// Panics if the item or index uses the high bit.
+    #[inline(always)]
+    pub(crate) fn pack(self) -> u32 {
+        match self {
+            Child::Empty => 0,
+            Child::Internal(index) => {
+                assert!(index & HIGH == 0 && index != 0);
+                index
+            }
+            Child::Leaf(item) => {
+                assert!(item & HIGH == 0);
+                item | HIGH
+            }
+        }
+    }
+}
+
+impl BinTrie {
+    /// The largest item a trie can hold, since the high bit marks leaves.
+    ///
+    /// ```
+    /// # use bintrie::{BinTrie, BinTrieError};
+    /// let mut trie = BinTrie::new_depth(4);
+    /// let lookup = |_: u32, _: u32| false;
+    /// assert_eq!(trie.try_insert(BinTrie::MAX_ITEM, |_| false, lookup), Ok(None));
+    /// assert_eq!(
+    ///     trie.try_insert(BinTrie::MAX_ITEM + 1, |_| true, lookup),
+    ///     Err(BinTrieError::ItemTooLarge(BinTrie::MAX_ITEM + 1)),
+    /// );
+    /// ```
+    pub const MAX_ITEM: u32 = !HIGH;
+
+    /// The decoded children of the internal node at `index`, where the root is at `0`.
+    ///
+    /// Returns `None` if there is no internal node at `index`.
+    pub fn children(&self, index: u32) -> Option<[Child; 2]> {
+        let internal = self.internals.get(index as usize)?;
+        Some(internal.0.map(Child::unpack))
+    }
+}
@@ -0,0 +1,396 @@
+use crate::{BinTrie, HIGH};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// An internal node of a `CompressedTrie`.
+#[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Node {
+    /// The children on the `false` and `true` sides.
+    children: [u32; 2],
+    /// The level of the key bit this node branches on.
+    level: u32,
+}
+
+/// A path-compressed (PATRICIA) trie that skips the levels where all keys agree.
+///
+/// A `BinTrie` has an internal node for every level between the root and a leaf,
+/// so keys sharing long prefixes form long chains of internal nodes with a single
+/// child. Here every internal node instead stores the level of the bit it branches
+/// on, so the chains collapse and a trie of `n` items never has more than `n`
+/// internal nodes, regardless of the depth of the keys.
+///
+/// The bits of the skipped levels are not looked at by `get`, so like with
+/// `BinTrie::get` a key that was never inserted may find an item. Use
+/// `get_exact` when the key has to be verified.
+///
+/// ```
+/// # use bintrie::{BinTrie, CompressedTrie};
+/// let lookup = |n: u32, l: u32| (n.wrapping_mul(0x9E37_79B9) ^ n << 8) >> (31 - l) & 1 == 1;
+/// let mut compressed = CompressedTrie::new_depth(32);
+/// let mut trie = BinTrie::new_depth(32);
+/// for n in 0..256 {
+///     compressed.insert(n, |l| lookup(n, l), lookup);
+///     trie.insert(n, |l| lookup(n, l), lookup);
+/// }
+/// assert_eq!(compressed.len(), 256);
+/// assert!(compressed.node_count() <= 256);
+/// assert!(compressed.node_count() <= trie.node_count());
+/// for n in 0..256 {
+///     assert_eq!(compressed.get(|l| lookup(n, l)), Some(n));
+/// }
+/// ```
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CompressedTrie {
+    /// The root node is always at index `0` and always branches on level `0`.
+    nodes: Vec<Node>,
+    /// The number of levels in a key.
+    depth: u32,
+    /// The number of items in the trie.
+    len: usize,
+}
+
+/// The serialized form of a `CompressedTrie`, before it has been validated.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct CompressedRepr {
+    nodes: Vec<Node>,
+    depth: u32,
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for CompressedTrie {
+    /// Deserializes a trie, making sure that it is valid so that lookups can't go out of bounds.
+    ///
+    /// ```
+    /// # use bintrie::CompressedTrie;
+    /// let mut trie = CompressedTrie::new_depth(8);
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// for n in 0..16 {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// let json = serde_json::to_string(&trie).unwrap();
+    /// let decoded: CompressedTrie = serde_json::from_str(&json).unwrap();
+    /// assert_eq!(decoded.len(), 16);
+    /// assert_eq!(decoded.items().collect::<Vec<_>>(), trie.items().collect::<Vec<_>>());
+    /// let json = r#"{"nodes":[{"children":[0,0],"level":0}],"depth":8,"len":0}"#;
+    /// assert!(serde_json::from_str::<CompressedTrie>(json).is_ok());
+    /// // The node beneath the root is its own child.
+    /// let json = r#"{"nodes":[{"children":[1,0],"level":0},{"children":[1,0],"level":1}],
+    ///     "depth":8,"len":0}"#;
+    /// assert!(serde_json::from_str::<CompressedTrie>(json).is_err());
+    /// ```
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::Error;
+        let repr = CompressedRepr::deserialize(deserializer)?;
+        let mut trie = CompressedTrie {
+            nodes: repr.nodes,
+            depth: repr.depth,
+            len: 0,
+        };
+        // The number of items is counted rather than trusted.
+        trie.len = trie.check().map_err(D::Error::custom)?;
+        Ok(trie)
+    }
+}
+
+impl Default for CompressedTrie {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CompressedTrie {
+    /// Makes a new trie with a maximum `depth` of `8192`.
+    pub fn new() -> Self {
+        Self::new_depth(8192)
+    }
+
+    /// Makes a new trie with a given maximum `depth`.
+    pub fn new_depth(depth: u32) -> Self {
+        assert!(depth > 0);
+        Self {
+            nodes: vec![Node {
+                children: [0; 2],
+                level: 0,
+            }],
+            depth,
+            len: 0,
+        }
+    }
+
+    /// Makes a compressed copy of the items of a trie.
+    ///
+    /// Every item must have a key of the full depth of the trie, so tries with
+    /// terminated or bucketed items can not be compressed.
+    ///
+    /// `F(item, n)` - A function that must be able to look up the nth bit
+    ///    from a previously inserted item.
+    ///
+    /// ```
+    /// # use bintrie::{BinTrie, CompressedTrie};
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// let mut trie = BinTrie::new_depth(16);
+    /// for n in 0..64 {
+    ///     trie.insert(n << 10, |l| lookup(n << 10, l), lookup);
+    /// }
+    /// let compressed = CompressedTrie::from_trie(&trie, lookup);
+    /// assert_eq!(compressed.items().collect::<Vec<_>>(), trie.items().collect::<Vec<_>>());
+    /// assert!(compressed.node_count() < trie.node_count());
+    /// ```
+    pub fn from_trie<F>(trie: &BinTrie, lookup: F) -> Self
+    where
+        F: Fn(u32, u32) -> bool,
+    {
+        assert!(
+            trie.terminals.is_empty() && trie.buckets.is_none(),
+            "only tries of full depth keys can be compressed"
+        );
+        let mut compressed = Self::new_depth(trie.depth);
+        for item in trie.items() {
+            compressed.insert(item, |l| lookup(item, l), &lookup);
+        }
+        compressed
+    }
+
+    /// Checks that the nodes form a trie, returning the number of items.
+    #[cfg(feature = "serde")]
+    fn check(&self) -> Result<usize, crate::InvalidTrieError> {
+        use crate::InvalidTrieError;
+        if self.depth == 0 {
+            return Err(InvalidTrieError::ZeroDepth);
+        }
+        let root = self.nodes.first().ok_or(InvalidTrieError::MissingRoot)?;
+        if root.level != 0 {
+            return Err(InvalidTrieError::InvalidLevel(0));
+        }
+        let mut reached = vec![false; self.nodes.len()];
+        reached[0] = true;
+        let mut items = 0;
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let node = self.nodes[index];
+            for &child in &node.children {
+                match child {
+                    0 => {}
+                    m if m & HIGH != 0 => items += 1,
+                    m => {
+                        let next = self
+                            .nodes
+                            .get(m as usize)
+                            .ok_or(InvalidTrieError::IndexOutOfRange(m))?;
+                        if reached[m as usize] {
+                            return Err(InvalidTrieError::RepeatedIndex(m));
+                        }
+                        // Levels only increase going down, which also keeps them in the depth.
+                        if next.level <= node.level || next.level >= self.depth {
+                            return Err(InvalidTrieError::InvalidLevel(m));
+                        }
+                        reached[m as usize] = true;
+                        stack.push(m as usize);
+                    }
+                }
+            }
+        }
+        Ok(items)
+    }
+
+    /// The number of levels in a key.
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    /// The number of items in the trie.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Checks if the trie has no items.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The number of internal nodes, including the root.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// The number of bytes used by the internal nodes.
+    pub fn memory_usage(&self) -> usize {
+        self.nodes.capacity() * core::mem::size_of::<Node>()
+    }
+
+    /// Inserts an item, returning the item it replaced if it had the same key.
+    ///
+    /// Unlike `BinTrie::insert`, this needs the whole key of the item that is
+    /// found, since the skipped levels have to be compared to find where the
+    /// keys diverge.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the key.
+    /// `F(item, n)` - A function that must be able to look up the nth bit
+    ///    from a previously inserted item.
+    ///
+    /// ```
+    /// # use bintrie::CompressedTrie;
+    /// let mut trie = CompressedTrie::new_depth(4);
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// assert_eq!(trie.insert(3, |l| lookup(3, l), lookup), None);
+    /// assert_eq!(trie.insert(19, |l| lookup(3, l), lookup), Some(3));
+    /// assert_eq!(trie.len(), 1);
+    /// ```
+    pub fn insert<K, F>(&mut self, item: u32, mut key: K, mut lookup: F) -> Option<u32>
+    where
+        K: FnMut(u32) -> bool,
+        F: FnMut(u32, u32) -> bool,
+    {
+        // Always check that the high bit is not set in the item.
+        assert!(item & HIGH == 0);
+        let leaf = item | HIGH;
+        // Find the item whose key shares the most branching bits with this key.
+        let mut index = 0;
+        let found = loop {
+            let node = self.nodes[index];
+            let position = key(node.level) as usize;
+            match node.children[position] {
+                0 => break Err(position),
+                m if m & HIGH != 0 => break Ok(m & !HIGH),
+                m => index = m as usize,
+            }
+        };
+        let found = match found {
+            // Only the root has an empty side, and none of its items have this first bit.
+            Err(position) => {
+                self.nodes[index].children[position] = leaf;
+                self.len += 1;
+                return None;
+            }
+            Ok(found) => found,
+        };
+        let level = match (0..self.depth).find(|&l| key(l) != lookup(found, l)) {
+            Some(level) => level,
+            None => {
+                // The keys are the same, so the item is replaced.
+                let slot = self.slot_of(found, &mut key);
+                *slot = leaf;
+                return Some(found);
+            }
+        };
+        // Descend again until the next node would branch beneath the divergent level.
+        let mut index = 0;
+        let position = loop {
+            let node = self.nodes[index];
+            let position = key(node.level) as usize;
+            match node.children[position] {
+                m if m != 0 && m & HIGH == 0 && self.nodes[m as usize].level < level => {
+                    index = m as usize
+                }
+                _ => break position,
+            }
+        };
+        let child = self.nodes[index].children[position];
+        let new_index = self.nodes.len() as u32;
+        // Panic if we go too high to fit in our indices.
+        assert!(new_index & HIGH == 0);
+        let side = key(level) as usize;
+        let mut children = [0; 2];
+        children[side] = leaf;
+        children[1 - side] = child;
+        self.nodes.push(Node { children, level });
+        self.nodes[index].children[position] = new_index;
+        self.len += 1;
+        None
+    }
+
+    /// Finds the slot holding the leaf of an item found with `key`.
+    fn slot_of<K>(&mut self, item: u32, mut key: K) -> &mut u32
+    where
+        K: FnMut(u32) -> bool,
+    {
+        let mut index = 0;
+        loop {
+            let node = self.nodes[index];
+            let position = key(node.level) as usize;
+            let child = node.children[position];
+            if child == item | HIGH {
+                return &mut self.nodes[index].children[position];
+            }
+            index = child as usize;
+        }
+    }
+
+    /// Perform a lookup for a particular item.
+    ///
+    /// Only the bits of the levels that nodes branch on are looked at.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the key.
+    pub fn get<K>(&self, mut key: K) -> Option<u32>
+    where
+        K: FnMut(u32) -> bool,
+    {
+        let mut index = 0;
+        loop {
+            let node = &self.nodes[index];
+            match node.children[key(node.level) as usize] {
+                // Empty node encountered.
+                0 => return None,
+                // Leaf node encountered.
+                m if m & HIGH != 0 => return Some(m & !HIGH),
+                // Internal node encountered.
+                m => index = m as usize,
+            }
+        }
+    }
+
+    /// Perform a lookup for a particular item, verifying every bit of its key.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the key.
+    /// `F(item, n)` - A function that must be able to look up the nth bit
+    ///    from a previously inserted item.
+    ///
+    /// ```
+    /// # use bintrie::CompressedTrie;
+    /// let mut trie = CompressedTrie::new_depth(8);
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// trie.insert(0b0000_0001, |l| lookup(1, l), lookup);
+    /// trie.insert(0b0000_0010, |l| lookup(2, l), lookup);
+    /// assert_eq!(trie.get(|l| lookup(0b1000_0001, l)), Some(1));
+    /// assert_eq!(trie.get_exact(|l| lookup(0b1000_0001, l), lookup), None);
+    /// assert_eq!(trie.get_exact(|l| lookup(1, l), lookup), Some(1));
+    /// ```
+    pub fn get_exact<K, F>(&self, mut key: K, mut lookup: F) -> Option<u32>
+    where
+        K: FnMut(u32) -> bool,
+        F: FnMut(u32, u32) -> bool,
+    {
+        let item = self.get(&mut key)?;
+        (0..self.depth)
+            .all(|l| key(l) == lookup(item, l))
+            .then_some(item)
+    }
+
+    /// Get an iterator over the items in the same order as `BinTrie::items`.
+    pub fn items(&self) -> impl Iterator<Item = u32> + '_ {
+        let mut indices = vec![self.nodes[0].children.iter()];
+        core::iter::from_fn(move || loop {
+            let mut current = indices.pop()?;
+            let child = if let Some(&child) = current.next() {
+                indices.push(current);
+                child
+            } else {
+                continue;
+            };
+            match child {
+                // Empty node
+                0 => {}
+                // Leaf node
+                m if m & HIGH != 0 => return Some(m & !HIGH),
+                // Internal node
+                m => indices.push(self.nodes[m as usize].children.iter()),
+            }
+        })
+    }
+}
@@ -0,0 +1,295 @@
+use crate::explore::{accept_all, ExploreIter};
+use crate::{BinTrie, BinTrieError, Child, FilterHeuristic, Internal, IntoHeuristic, Nodes, HIGH};
+use alloc::boxed::Box;
+use alloc::vec;
+use core::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+
+/// A trie that many threads can insert into at once without locking.
+///
+/// The internal nodes live in an arena with a fixed `capacity` that is allocated
+/// up front, so they never move. An insert claims an empty slot or publishes a
+/// new internal node with a single compare-and-swap, and retries from the same
+/// node if another thread got there first. Readers never wait on writers: `get`,
+/// `items` and `explore` see every insert that completed before they reached
+/// the slot it changed, and may or may not see inserts that are in progress.
+///
+/// When building is done, `into_trie` turns it into a regular `BinTrie`.
+///
+/// ```
+/// # use bintrie::{ConcurrentBinTrie, FilterHeuristic, Side};
+/// let trie = ConcurrentBinTrie::with_capacity(16, 1 << 12);
+/// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+/// std::thread::scope(|scope| {
+///     for thread in 0..4 {
+///         let trie = &trie;
+///         scope.spawn(move || {
+///             for n in (thread..1000).step_by(4) {
+///                 trie.insert(n, |l| lookup(n, l), lookup);
+///             }
+///         });
+///     }
+/// });
+/// assert_eq!(trie.len(), 1000);
+/// let mut items: Vec<u32> = trie.items().collect();
+/// items.sort_unstable();
+/// assert_eq!(items, (0..1000).collect::<Vec<u32>>());
+/// // Always going left only reaches the item whose key is all zeros.
+/// let left = trie.explore(FilterHeuristic(|s| s == Side::Left)).next();
+/// assert_eq!(left, Some(0));
+/// let trie = trie.into_trie();
+/// for n in 0..1000 {
+///     assert_eq!(trie.get(|l| lookup(n, l)), Some(n));
+/// }
+/// ```
+#[derive(Debug)]
+pub struct ConcurrentBinTrie {
+    /// The root node is always at index `0`.
+    internals: Box<[[AtomicU32; 2]]>,
+    /// The number of internal nodes handed out so far.
+    used: AtomicUsize,
+    /// The maximum depth to stop at.
+    depth: u32,
+    /// The number of items in the trie.
+    len: AtomicUsize,
+}
+
+impl ConcurrentBinTrie {
+    /// Makes a new trie with a given maximum `depth` and room for `capacity` internal nodes.
+    pub fn with_capacity(depth: u32, capacity: usize) -> Self {
+        assert!(depth > 0);
+        let capacity = capacity.clamp(1, HIGH as usize);
+        Self {
+            internals: (0..capacity)
+                .map(|_| [AtomicU32::new(0), AtomicU32::new(0)])
+                .collect(),
+            used: AtomicUsize::new(1),
+            depth,
+            len: AtomicUsize::new(0),
+        }
+    }
+
+    /// The maximum depth of the trie.
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    /// The number of items in the trie.
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Relaxed)
+    }
+
+    /// Checks if the trie has no items.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The number of internal nodes the arena has room for.
+    pub fn capacity(&self) -> usize {
+        self.internals.len()
+    }
+
+    /// Inserts an item, which may happen at the same time as other inserts.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the key.
+    /// `F(item, n)` - A function that must be able to look up the nth bit
+    ///    from a previously inserted item.
+    ///
+    /// Returns `Some` of a replaced leaf if a leaf was replaced, otherwise None.
+    pub fn insert<K, F>(&self, item: u32, key: K, lookup: F) -> Option<u32>
+    where
+        K: FnMut(u32) -> bool,
+        F: FnMut(u32, u32) -> bool,
+    {
+        self.try_insert(item, key, lookup)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Inserts an item like `insert`, but returns an error rather than panicking.
+    ///
+    /// Fails if the item has the high bit set or if the arena has no room left.
+    pub fn try_insert<K, F>(
+        &self,
+        item: u32,
+        mut key: K,
+        mut lookup: F,
+    ) -> Result<Option<u32>, BinTrieError>
+    where
+        K: FnMut(u32) -> bool,
+        F: FnMut(u32, u32) -> bool,
+    {
+        if item & HIGH != 0 {
+            return Err(BinTrieError::ItemTooLarge(item));
+        }
+        // A node that was allocated for a split that another thread beat us to.
+        let mut spare = None;
+        let mut index = 0;
+        let mut i = 0;
+        while i < self.depth - 1 {
+            let slot = &self.internals[index][key(i) as usize];
+            match slot.load(Ordering::Acquire) {
+                // Empty node encountered.
+                0 => {
+                    if slot
+                        .compare_exchange(0, item | HIGH, Ordering::AcqRel, Ordering::Acquire)
+                        .is_ok()
+                    {
+                        self.len.fetch_add(1, Ordering::Relaxed);
+                        return Ok(None);
+                    }
+                    // Someone else filled the slot, so look at it again.
+                    continue;
+                }
+                // Leaf node encountered.
+                m if m & HIGH != 0 => {
+                    let new_index = match spare.take() {
+                        Some(new_index) => new_index,
+                        None => self.allocate()?,
+                    };
+                    let new_internal = &self.internals[new_index as usize];
+                    new_internal[0].store(0, Ordering::Relaxed);
+                    new_internal[1].store(0, Ordering::Relaxed);
+                    new_internal[lookup(m & !HIGH, i + 1) as usize].store(m, Ordering::Relaxed);
+                    // Publishing the node also publishes the leaf moved into it.
+                    if slot
+                        .compare_exchange(m, new_index, Ordering::AcqRel, Ordering::Acquire)
+                        .is_err()
+                    {
+                        spare = Some(new_index);
+                        continue;
+                    }
+                    index = new_index as usize;
+                }
+                // Internal node encountered.
+                m => index = m as usize,
+            }
+            i += 1;
+        }
+        let slot = &self.internals[index][key(self.depth - 1) as usize];
+        match slot.swap(item | HIGH, Ordering::AcqRel) {
+            0 => {
+                self.len.fetch_add(1, Ordering::Relaxed);
+                Ok(None)
+            }
+            m => Ok(Some(m & !HIGH)),
+        }
+    }
+
+    /// Hands out an unused internal node from the arena.
+    fn allocate(&self) -> Result<u32, BinTrieError> {
+        let index = self.used.fetch_add(1, Ordering::Relaxed);
+        if index >= self.internals.len() {
+            self.used.fetch_sub(1, Ordering::Relaxed);
+            return Err(BinTrieError::CapacityExhausted);
+        }
+        Ok(index as u32)
+    }
+
+    /// Perform a lookup for a particular item.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the key.
+    pub fn get<K>(&self, mut key: K) -> Option<u32>
+    where
+        K: FnMut(u32) -> bool,
+    {
+        let mut index = 0;
+        for i in 0..self.depth {
+            match self.internals[index][key(i) as usize].load(Ordering::Acquire) {
+                // Empty node encountered.
+                0 => return None,
+                // Leaf node encountered.
+                m if m & HIGH != 0 => return Some(m & !HIGH),
+                // Internal node encountered.
+                m => index = m as usize,
+            }
+        }
+        None
+    }
+
+    /// Get an iterator over the items added to the trie.
+    pub fn items(&self) -> impl Iterator<Item = u32> + '_ {
+        self.explore(FilterHeuristic(|_| true))
+    }
+
+    /// Explores the trie using a heuristic, like `BinTrie::explore`.
+    pub fn explore<'a, H>(&'a self, heuristic: H) -> impl Iterator<Item = u32> + 'a
+    where
+        H: IntoHeuristic,
+        H::Heuristic: 'a,
+    {
+        ExploreIter::new(self, heuristic.into_heuristic(), vec![], accept_all)
+    }
+
+    /// Turns this into a regular `BinTrie` once no more threads are inserting.
+    ///
+    /// Nodes that were allocated for splits that lost a race are dropped.
+    pub fn into_trie(self) -> BinTrie {
+        let used = self.used.into_inner().min(self.internals.len());
+        let mut trie = BinTrie::new_depth(self.depth);
+        trie.internals = self.internals[..used]
+            .iter()
+            .map(|[left, right]| {
+                Internal([left.load(Ordering::Relaxed), right.load(Ordering::Relaxed)])
+            })
+            .collect();
+        trie.len = self.len.into_inner();
+        trie.compact();
+        trie
+    }
+}
+
+impl Nodes for &ConcurrentBinTrie {
+    type Index = u32;
+
+    #[inline(always)]
+    fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    #[inline(always)]
+    fn root(&self) -> u32 {
+        0
+    }
+
+    #[inline(always)]
+    fn child(&self, index: u32, position: usize) -> Child {
+        Child::unpack(self.internals[index as usize][position].load(Ordering::Acquire))
+    }
+
+    #[inline(always)]
+    unsafe fn child_unchecked(&self, index: u32, position: usize) -> Child {
+        Child::unpack(
+            self.internals
+                .get_unchecked(index as usize)
+                .get_unchecked(position)
+                .load(Ordering::Acquire),
+        )
+    }
+
+    #[inline(always)]
+    fn terminal(&self, _index: u32) -> Option<u32> {
+        // Items are only ever stored in leaves.
+        None
+    }
+
+    #[inline(always)]
+    fn child_checked(&self, index: u32, position: usize) -> Child {
+        Child::unpack(
+            self.internals
+                .get(index as usize)
+                .map_or(0, |internal| internal[position].load(Ordering::Acquire)),
+        )
+    }
+
+    #[inline(always)]
+    fn bucket_item(&self, _item: u32, _position: usize) -> Option<u32> {
+        // There are no buckets.
+        None
+    }
+
+    #[inline(always)]
+    fn is_verified(&self) -> bool {
+        // Every published internal node is in the arena and above the depth, since
+        // inserts only ever publish nodes that they allocated.
+        true
+    }
+}
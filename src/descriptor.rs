@@ -0,0 +1,125 @@
+use crate::BinTrie;
+use alloc::vec::Vec;
+
+/// Gets bit `n` of a binary descriptor, starting from the most significant bit of the first byte.
+#[inline(always)]
+pub(crate) fn descriptor_bit(descriptor: &[u8], n: u32) -> bool {
+    descriptor[n as usize / 8] >> (7 - n % 8) & 1 == 1
+}
+
+impl BinTrie {
+    /// Builds a trie from a slice of fixed-size binary descriptors.
+    ///
+    /// The depth of the trie is the number of bits in a descriptor and each
+    /// descriptor is inserted with its index in `descriptors` as the item.
+    /// Bits are taken from the most significant bit of the first byte onwards.
+    ///
+    /// Descriptors which are exact duplicates share a single item. Along with
+    /// the trie, a `Vec` is returned that maps the index of every descriptor
+    /// to the item it is stored as, which is the index of the first duplicate.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let descriptors = [[0u8; 32], [0xFF; 32], [0u8; 32]];
+    /// let (trie, items) = BinTrie::from_descriptors(&descriptors);
+    /// assert_eq!(items, vec![0, 1, 0]);
+    /// assert_eq!(trie.items().collect::<Vec<u32>>(), vec![0, 1]);
+    /// ```
+    pub fn from_descriptors<const N: usize>(descriptors: &[[u8; N]]) -> (BinTrie, Vec<u32>) {
+        assert!(N > 0);
+        Self::build_descriptors(descriptors, N)
+    }
+
+    /// Makes an empty trie deep enough for descriptors of `N` bytes.
+    ///
+    /// Descriptors can then be added with `insert_key` and found with `get_key`.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let descriptors = [[0x0Fu8; 128], [0xF0; 128]];
+    /// let mut trie = BinTrie::for_descriptors::<128>();
+    /// assert_eq!(trie.depth(), 1024);
+    /// for (n, descriptor) in descriptors.iter().enumerate() {
+    ///     trie.insert_key(n as u32, descriptor, |item| descriptors[item as usize]);
+    /// }
+    /// assert_eq!(trie.get_key(&[0xF0u8; 128]), Some(1));
+    /// ```
+    pub fn for_descriptors<const N: usize>() -> BinTrie {
+        assert!(N > 0);
+        BinTrie::new_depth(N as u32 * 8)
+    }
+
+    /// Builds a trie from descriptors whose width is only known at runtime.
+    ///
+    /// This works like `from_descriptors`, but every descriptor is a slice of bytes,
+    /// such as a `Vec<u8>`. The depth of the trie is the number of bits in the first
+    /// descriptor, and every descriptor must have the same width.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let descriptors = vec![vec![0u8; 64], vec![0xFF; 64], vec![0u8; 64]];
+    /// let (trie, items) = BinTrie::from_descriptor_slices(&descriptors);
+    /// assert_eq!(trie.depth(), 512);
+    /// assert_eq!(items, vec![0, 1, 0]);
+    /// ```
+    pub fn from_descriptor_slices<D>(descriptors: &[D]) -> (BinTrie, Vec<u32>)
+    where
+        D: AsRef<[u8]>,
+    {
+        let width = descriptors
+            .first()
+            .map_or(0, |descriptor| descriptor.as_ref().len());
+        assert!(width > 0, "descriptors must have at least one byte");
+        assert!(
+            descriptors
+                .iter()
+                .all(|descriptor| descriptor.as_ref().len() == width),
+            "descriptors must all have the same width"
+        );
+        Self::build_descriptors(descriptors, width)
+    }
+
+    /// Builds a trie from descriptors of `width` bytes stored one after another in `bytes`.
+    ///
+    /// This works like `from_descriptor_slices` without needing a slice for every
+    /// descriptor, which suits descriptors loaded from a file in one buffer.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let mut bytes = vec![0u8; 3 * 128];
+    /// bytes[128..256].fill(0xFF);
+    /// let (trie, items) = BinTrie::from_flat_descriptors(&bytes, 128);
+    /// assert_eq!(trie.depth(), 1024);
+    /// assert_eq!(items, vec![0, 1, 0]);
+    /// ```
+    pub fn from_flat_descriptors(bytes: &[u8], width: usize) -> (BinTrie, Vec<u32>) {
+        assert!(width > 0, "descriptors must have at least one byte");
+        assert!(
+            bytes.len().is_multiple_of(width),
+            "the bytes must be a whole number of descriptors"
+        );
+        let descriptors: Vec<&[u8]> = bytes.chunks_exact(width).collect();
+        Self::build_descriptors(&descriptors, width)
+    }
+
+    /// Inserts descriptors of `width` bytes, keeping the first of any duplicates.
+    fn build_descriptors<D>(descriptors: &[D], width: usize) -> (BinTrie, Vec<u32>)
+    where
+        D: AsRef<[u8]>,
+    {
+        let mut trie = BinTrie::new_depth(width as u32 * 8);
+        let lookup = |item: u32, n: u32| descriptor_bit(descriptors[item as usize].as_ref(), n);
+        let items = descriptors
+            .iter()
+            .enumerate()
+            .map(|(index, descriptor)| {
+                let item = index as u32;
+                let key = |n| descriptor_bit(descriptor.as_ref(), n);
+                // Keep the first item when descriptors are duplicates.
+                trie.insert_merge(item, key, lookup, |collision| collision.existing)
+                    .unwrap_or(item)
+            })
+            .collect();
+        (trie, items)
+    }
+}
@@ -0,0 +1,469 @@
+use crate::{BinTrie, CompiledHeuristic, Heuristic, QueryScratch, Side, HIGH};
+use alloc::vec::Vec;
+use core::ops::Range;
+
+/// A distance between keys that can be bounded from a prefix of a key.
+///
+/// This lets the nearest neighbor searches work with metrics other than the
+/// Hamming distance, such as weighted bit costs or the length of the shared prefix.
+///
+/// ```
+/// # use bintrie::{BinTrie, PathDistance, Side, SpillPolicy};
+/// # use std::ops::Range;
+/// /// The Hamming distance to zero where bit `n` costs `n + 1`.
+/// struct Weighted<F>(F);
+///
+/// impl<F: FnMut(u32, u32) -> bool> PathDistance for Weighted<F> {
+///     fn branch(&mut self, level: u32, side: Side, distance: u32) -> u32 {
+///         distance + if side == Side::Right { level + 1 } else { 0 }
+///     }
+///
+///     fn leaf(&mut self, item: u32, remaining: Range<u32>, distance: u32) -> u32 {
+///         distance + remaining.filter(|&l| (self.0)(item, l)).map(|l| l + 1).sum::<u32>()
+///     }
+/// }
+///
+/// let mut trie = BinTrie::new_depth(4);
+/// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+/// for n in 0..16 {
+///     trie.insert(n, |l| lookup(n, l), lookup);
+/// }
+/// let nearest =
+///     trie.within_adaptive_by(Weighted(lookup), 4, 16, usize::MAX, SpillPolicy::DropWorst);
+/// assert_eq!(nearest, vec![(0, 0), (1, 1), (2, 2), (3, 3), (4, 3)]);
+/// ```
+pub trait PathDistance {
+    /// Gets a lower bound on the distance of every key that takes `side` at the branch at `level`.
+    ///
+    /// `distance` is the lower bound for the keys that reach the branch, and the
+    /// returned bound must not be smaller.
+    fn branch(&mut self, level: u32, side: Side, distance: u32) -> u32;
+
+    /// Gets the exact distance of the key of `item`.
+    ///
+    /// The item was found after the branches that led to the lower bound `distance`,
+    /// and the bits in `remaining` are those of its key that were not on the way.
+    fn leaf(&mut self, item: u32, remaining: Range<u32>, distance: u32) -> u32;
+}
+
+/// The Hamming distance to the key `target`, which is the number of differing bits.
+///
+/// `F(item, n)` - A function that must be able to look up the nth bit
+///    from a previously inserted item.
+pub struct HammingDistance<K, F> {
+    target: K,
+    lookup: F,
+}
+
+impl<K, F> HammingDistance<K, F>
+where
+    K: FnMut(u32) -> bool,
+    F: FnMut(u32, u32) -> bool,
+{
+    /// Measures the distance to `target`, which provides the `n`th bit of the key.
+    pub fn new(target: K, lookup: F) -> Self {
+        Self { target, lookup }
+    }
+}
+
+impl<K, F> PathDistance for HammingDistance<K, F>
+where
+    K: FnMut(u32) -> bool,
+    F: FnMut(u32, u32) -> bool,
+{
+    #[inline(always)]
+    fn branch(&mut self, level: u32, side: Side, distance: u32) -> u32 {
+        distance + (bool::from(side) != (self.target)(level)) as u32
+    }
+
+    #[inline(always)]
+    fn leaf(&mut self, item: u32, remaining: Range<u32>, distance: u32) -> u32 {
+        let Self { target, lookup } = self;
+        distance + remaining.filter(|&l| lookup(item, l) != target(l)).count() as u32
+    }
+}
+
+/// What a best-first search does when its frontier of waiting nodes is full.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SpillPolicy {
+    /// Forget the farthest waiting node, which loses any items beneath it.
+    DropWorst,
+    /// Search beneath the new node depth-first right away instead of keeping it
+    /// waiting, which still finds every item but visits nodes out of order.
+    DepthFirst,
+}
+
+/// The nodes and items waiting to be visited, bucketed by their distance.
+struct Frontier<'s> {
+    /// Internal nodes and their depths.
+    nodes: &'s mut [Vec<(u32, u32)>],
+    /// Items whose distance is known.
+    items: &'s mut [Vec<u32>],
+    /// The number of nodes waiting in all buckets.
+    waiting: usize,
+    max_nodes: usize,
+    policy: SpillPolicy,
+}
+
+impl Frontier<'_> {
+    fn push_item(&mut self, item: u32, distance: u32) {
+        self.items[distance as usize].push(item);
+    }
+
+    /// Makes the internal node at `index` wait, unless the frontier is full.
+    ///
+    /// Returns `false` if the node must be searched right away instead.
+    fn push_node(&mut self, index: u32, level: u32, distance: u32) -> bool {
+        if self.waiting >= self.max_nodes {
+            match self.policy {
+                SpillPolicy::DropWorst => {
+                    let worst = self.nodes.iter().rposition(|bucket| !bucket.is_empty());
+                    match worst {
+                        Some(worst) if worst > distance as usize => {
+                            self.nodes[worst].pop();
+                            self.waiting -= 1;
+                        }
+                        // The new node is the worst, so it is the one that is dropped.
+                        _ => return true,
+                    }
+                }
+                SpillPolicy::DepthFirst => return false,
+            }
+        }
+        self.nodes[distance as usize].push((index, level));
+        self.waiting += 1;
+        true
+    }
+}
+
+impl BinTrie {
+    /// Iterates over every item within Hamming distance `radius` of `query`.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the query key.
+    /// `F(item, n)` - A function that must be able to look up the nth bit
+    ///    from a previously inserted item.
+    ///
+    /// The distance budget is compiled into a table per level, so subtrees are pruned
+    /// as soon as they differ too much from the query. Leaves can be found above the
+    /// last level, so `lookup` is used to check the rest of their keys. Each item is
+    /// yielded with its exact distance, in the order they are found.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let mut trie = BinTrie::new_depth(8);
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// for &n in &[0b0000_0000, 0b0000_0011, 0b0000_0111, 0b1111_1111] {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// let mut found = trie
+    ///     .within_distance(|l| lookup(0b0000_0001, l), 2, lookup)
+    ///     .collect::<Vec<_>>();
+    /// found.sort();
+    /// assert_eq!(found, vec![(0b0000_0000, 1), (0b0000_0011, 1), (0b0000_0111, 2)]);
+    /// ```
+    pub fn within_distance<'a, K, F>(
+        &'a self,
+        mut query: K,
+        radius: u32,
+        mut lookup: F,
+    ) -> impl Iterator<Item = (u32, u32)> + 'a
+    where
+        K: FnMut(u32) -> bool + 'a,
+        F: FnMut(u32, u32) -> bool + 'a,
+    {
+        let depth = self.depth;
+        let heuristic = CompiledHeuristic::hamming(&mut query, depth, radius);
+        self.explore(heuristic).filter_map(move |item| {
+            let distance = (0..depth).filter(|&l| lookup(item, l) != query(l)).count() as u32;
+            Some((item, distance)).filter(|_| distance <= radius)
+        })
+    }
+
+    /// Iterates over every item within Hamming distance `radius` of `query` like
+    /// `within_distance`, but keeps the traversal stack in `scratch`.
+    pub fn within_distance_in<'a, K, F>(
+        &'a self,
+        mut query: K,
+        radius: u32,
+        mut lookup: F,
+        scratch: &'a mut QueryScratch<CompiledHeuristic>,
+    ) -> impl Iterator<Item = (u32, u32)> + 'a
+    where
+        K: FnMut(u32) -> bool + 'a,
+        F: FnMut(u32, u32) -> bool + 'a,
+    {
+        let depth = self.depth;
+        let heuristic = CompiledHeuristic::hamming(&mut query, depth, radius);
+        self.explore_with(heuristic, scratch)
+            .filter_map(move |item| {
+                let distance = (0..depth).filter(|&l| lookup(item, l) != query(l)).count() as u32;
+                Some((item, distance)).filter(|_| distance <= radius)
+            })
+    }
+
+    /// Finds the items nearest to `target` by widening the search radius until enough are found.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the target key.
+    /// `F(item, n)` - A function that must be able to look up the nth bit
+    ///    from a previously inserted item.
+    ///
+    /// The Hamming radius starts at `0` and grows one step at a time until at least
+    /// `desired_results` items have been found or `max_radius` is reached. Nodes that
+    /// are too far away for the current radius are kept for the next radius, so
+    /// widening continues where the previous radius left off instead of restarting.
+    ///
+    /// Returns every item within the final radius along with its exact distance,
+    /// nearest first. The `lookup` is used to find the exact distance of items.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let mut trie = BinTrie::new_depth(8);
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// for &n in &[0b0000_0000, 0b0000_0011, 0b0000_0111, 0b1111_1111] {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// let target = |l| lookup(0b0000_0001, l);
+    /// let mut nearest = trie.within_adaptive(target, 1, 8, lookup);
+    /// nearest.sort();
+    /// assert_eq!(nearest, vec![(0b0000_0000, 1), (0b0000_0011, 1)]);
+    /// assert_eq!(trie.within_adaptive(target, 3, 1, lookup).len(), 2);
+    /// assert_eq!(trie.within_adaptive(target, 3, 8, lookup).len(), 3);
+    /// ```
+    pub fn within_adaptive<K, F>(
+        &self,
+        target: K,
+        desired_results: usize,
+        max_radius: u32,
+        lookup: F,
+    ) -> Vec<(u32, u32)>
+    where
+        K: FnMut(u32) -> bool,
+        F: FnMut(u32, u32) -> bool,
+    {
+        self.within_adaptive_bounded(
+            target,
+            desired_results,
+            max_radius,
+            usize::MAX,
+            SpillPolicy::DropWorst,
+            lookup,
+        )
+    }
+
+    /// Finds the items nearest to `target` like `within_adaptive`, but keeps the
+    /// frontier and the results in `scratch`.
+    ///
+    /// The results are only valid until the scratch is used again.
+    pub fn within_adaptive_in<'s, K, F, H>(
+        &self,
+        target: K,
+        desired_results: usize,
+        max_radius: u32,
+        lookup: F,
+        scratch: &'s mut QueryScratch<H>,
+    ) -> &'s [(u32, u32)]
+    where
+        K: FnMut(u32) -> bool,
+        F: FnMut(u32, u32) -> bool,
+        H: Heuristic,
+    {
+        self.within_adaptive_bounded_in(
+            target,
+            desired_results,
+            max_radius,
+            usize::MAX,
+            SpillPolicy::DropWorst,
+            lookup,
+            scratch,
+        )
+    }
+
+    /// Finds the items nearest to `target` like `within_adaptive`, but with a bounded frontier.
+    ///
+    /// At most `max_nodes` internal nodes wait to be visited at any time, so the memory
+    /// a query uses stays bounded even on adversarial tries. When another node would
+    /// have to wait, the `policy` decides what happens to it.
+    ///
+    /// ```
+    /// # use bintrie::{BinTrie, SpillPolicy};
+    /// let mut trie = BinTrie::new_depth(12);
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// for n in 0..4096 {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// let target = |l| lookup(0, l);
+    /// let exact = trie.within_adaptive(target, 100, 3, lookup);
+    /// let depth_first = trie.within_adaptive_bounded(target, 100, 3, 4, SpillPolicy::DepthFirst, lookup);
+    /// assert_eq!(depth_first.len(), exact.len());
+    /// let dropped = trie.within_adaptive_bounded(target, 100, 3, 4, SpillPolicy::DropWorst, lookup);
+    /// assert!(dropped.len() < exact.len());
+    /// ```
+    pub fn within_adaptive_bounded<K, F>(
+        &self,
+        target: K,
+        desired_results: usize,
+        max_radius: u32,
+        max_nodes: usize,
+        policy: SpillPolicy,
+        lookup: F,
+    ) -> Vec<(u32, u32)>
+    where
+        K: FnMut(u32) -> bool,
+        F: FnMut(u32, u32) -> bool,
+    {
+        self.within_adaptive_by(
+            HammingDistance::new(target, lookup),
+            desired_results,
+            max_radius,
+            max_nodes,
+            policy,
+        )
+    }
+
+    /// Finds the items nearest to `target` like `within_adaptive_bounded`, but keeps
+    /// the frontier and the results in `scratch`.
+    ///
+    /// The results are only valid until the scratch is used again.
+    #[allow(clippy::too_many_arguments)]
+    pub fn within_adaptive_bounded_in<'s, K, F, H>(
+        &self,
+        target: K,
+        desired_results: usize,
+        max_radius: u32,
+        max_nodes: usize,
+        policy: SpillPolicy,
+        lookup: F,
+        scratch: &'s mut QueryScratch<H>,
+    ) -> &'s [(u32, u32)]
+    where
+        K: FnMut(u32) -> bool,
+        F: FnMut(u32, u32) -> bool,
+        H: Heuristic,
+    {
+        self.within_adaptive_by_in(
+            HammingDistance::new(target, lookup),
+            desired_results,
+            max_radius,
+            max_nodes,
+            policy,
+            scratch,
+        )
+    }
+
+    /// Finds the items nearest to a key like `within_adaptive_bounded`, but with any `metric`.
+    ///
+    /// The radius grows one step at a time, so distances should be small integers.
+    pub fn within_adaptive_by<D>(
+        &self,
+        metric: D,
+        desired_results: usize,
+        max_radius: u32,
+        max_nodes: usize,
+        policy: SpillPolicy,
+    ) -> Vec<(u32, u32)>
+    where
+        D: PathDistance,
+    {
+        let mut scratch = QueryScratch::<CompiledHeuristic>::new();
+        self.within_adaptive_by_in(
+            metric,
+            desired_results,
+            max_radius,
+            max_nodes,
+            policy,
+            &mut scratch,
+        );
+        scratch.results
+    }
+
+    /// Finds the items nearest to a key like `within_adaptive_by`, but keeps the
+    /// frontier and the results in `scratch`.
+    pub fn within_adaptive_by_in<'s, D, H>(
+        &self,
+        mut metric: D,
+        desired_results: usize,
+        max_radius: u32,
+        max_nodes: usize,
+        policy: SpillPolicy,
+        scratch: &'s mut QueryScratch<H>,
+    ) -> &'s [(u32, u32)]
+    where
+        D: PathDistance,
+        H: Heuristic,
+    {
+        let QueryScratch {
+            nodes,
+            items,
+            stack,
+            results,
+            ..
+        } = scratch;
+        results.clear();
+        // Every bucket up to the largest radius is emptied, keeping what it allocated.
+        let buckets = max_radius as usize + 1;
+        nodes.truncate(buckets);
+        nodes.iter_mut().for_each(Vec::clear);
+        nodes.resize_with(buckets, Vec::new);
+        items.truncate(buckets);
+        items.iter_mut().for_each(Vec::clear);
+        items.resize_with(buckets, Vec::new);
+        let mut frontier = Frontier {
+            nodes,
+            items,
+            waiting: 1,
+            max_nodes,
+            policy,
+        };
+        // The root always waits, even if nothing else may.
+        frontier.nodes[0].push((0, 0));
+        // Nodes which must be searched depth-first right away.
+        stack.clear();
+        for radius in 0..=max_radius {
+            while let Some((index, level)) = frontier.nodes[radius as usize].pop() {
+                frontier.waiting -= 1;
+                stack.push((index, level, radius));
+                while let Some((index, level, distance)) = stack.pop() {
+                    // Only follow the first slot that reaches each node of a suspect trie, and stop at the depth.
+                    if level >= self.depth {
+                        continue;
+                    }
+                    let children = self.children_checked(index as usize);
+                    for (&side, &n) in Side::BOTH.iter().zip(children.iter()) {
+                        if n == 0 {
+                            continue;
+                        }
+                        let distance = metric.branch(level, side, distance);
+                        if distance > max_radius {
+                            continue;
+                        }
+                        if n & HIGH != 0 {
+                            // Find the exact distance of the leaf using the rest of its key.
+                            let distance = metric.leaf(n & !HIGH, level + 1..self.depth, distance);
+                            let item = n & !HIGH;
+                            if distance <= max_radius {
+                                frontier.push_item(item, distance);
+                            }
+                        } else {
+                            if let Some(item) = self.terminal(n) {
+                                frontier.push_item(item, distance);
+                            }
+                            // Nodes at the current radius are searched right away as well.
+                            if distance == radius || !frontier.push_node(n, level + 1, distance) {
+                                stack.push((n, level + 1, distance));
+                            }
+                        }
+                    }
+                }
+            }
+            results.extend(
+                frontier.items[radius as usize]
+                    .drain(..)
+                    .map(|item| (item, radius)),
+            );
+            if results.len() >= desired_results {
+                break;
+            }
+        }
+        results
+    }
+}
@@ -0,0 +1,155 @@
+use crate::relocate::prefix_bit;
+use crate::{BinTrie, HIGH};
+use alloc::string::String;
+use alloc::vec;
+use core::fmt::{self, Write};
+
+impl BinTrie {
+    /// Renders the trie as a Graphviz DOT graph, like `write_dot`.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let mut trie = BinTrie::new_depth(4);
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// for n in &[0b0000, 0b0100, 0b0111] {
+    ///     trie.insert(*n, |l| lookup(*n, l), lookup);
+    /// }
+    /// let dot = trie.to_dot(true);
+    /// assert!(dot.starts_with("digraph bintrie {"));
+    /// assert!(dot.contains("n0 -> n1 [label=\"0\"];"));
+    /// assert!(dot.contains("l0_1 [shape=box, label=\"7\"];"));
+    /// assert!(dot.contains("n0 -> l0_1 [label=\"1\"];"));
+    /// assert!(dot.contains("e1_1 [shape=point];"));
+    /// assert!(dot.contains("n2 [label=\"#2\\nlevel 2\"];"));
+    /// assert!(trie.to_dot(false).contains("n0 -> n1;"));
+    /// ```
+    pub fn to_dot(&self, branch_bits: bool) -> String {
+        let mut dot = String::new();
+        self.write_dot(&mut dot, branch_bits)
+            .expect("writing to a String never fails");
+        dot
+    }
+
+    /// Writes the structure of the trie as a Graphviz DOT graph for debugging.
+    ///
+    /// Internal nodes are circles labeled with their index and level, along with
+    /// any item whose key ends there. Leaves are boxes labeled with their item and
+    /// the rest of its bucket, and empty slots are points. With `branch_bits`, every
+    /// edge is labeled with the key bit that leads down it.
+    ///
+    /// Render it with `dot -Tsvg trie.dot -o trie.svg`.
+    pub fn write_dot<W>(&self, out: &mut W, branch_bits: bool) -> fmt::Result
+    where
+        W: Write,
+    {
+        self.write_dot_under(out, 0, 0, branch_bits)
+    }
+
+    /// Writes the subtree that holds the keys starting with the `len` bit `prefix`,
+    /// like `write_dot`.
+    ///
+    /// The prefix is the same as in `items_under_prefix`. When a leaf is found above
+    /// the end of the prefix, only that leaf is written, since the rest of its key is
+    /// not known. The graph is empty when no key can have the prefix.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let mut trie = BinTrie::new_depth(4);
+    /// let lookup = |n: u32, l: u32| n >> (3 - l) & 1 == 1;
+    /// for &n in &[0b0001, 0b0011, 0b1000] {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// let mut dot = String::new();
+    /// trie.write_dot_under(&mut dot, 0b00, 2, false).unwrap();
+    /// assert!(dot.contains("n2 -> l2_0;"));
+    /// assert!(!dot.contains("n0"));
+    /// dot.clear();
+    /// trie.write_dot_under(&mut dot, 0b10, 2, false).unwrap();
+    /// assert!(dot.contains("l0_1 [shape=box, label=\"8\"];"));
+    /// dot.clear();
+    /// trie.write_dot_under(&mut dot, 0b01, 2, false).unwrap();
+    /// assert!(!dot.contains("->"));
+    /// ```
+    pub fn write_dot_under<W>(
+        &self,
+        out: &mut W,
+        prefix: u64,
+        len: u32,
+        branch_bits: bool,
+    ) -> fmt::Result
+    where
+        W: Write,
+    {
+        assert!(len <= 64 && len <= self.depth);
+        writeln!(out, "digraph bintrie {{")?;
+        writeln!(out, "    node [shape=circle];")?;
+        // Descend to the internal node with the prefix.
+        let mut stack = vec![(0u32, 0u32)];
+        for i in 0..len {
+            let index = match stack.pop() {
+                Some((index, _)) => index,
+                None => break,
+            };
+            let position = prefix_bit(prefix, len, i) as usize;
+            match self.child_checked(index as usize, position) {
+                0 => {}
+                n if n & HIGH != 0 => self.write_leaf(out, index, position, n & !HIGH)?,
+                n => stack.push((n, i + 1)),
+            }
+        }
+        while let Some((index, level)) = stack.pop() {
+            // Only follow the first slot that reaches each node of a suspect trie, and stop at the depth.
+            if level >= self.depth {
+                continue;
+            }
+            let children = self.children_checked(index as usize);
+            write!(out, "    n{} [label=\"#{}\\nlevel {}", index, index, level)?;
+            if let Some(item) = self.terminal(index) {
+                write!(out, "\\nends {}", item)?;
+            }
+            writeln!(out, "\"];")?;
+            for (position, &n) in children.iter().enumerate() {
+                // Empty slots and leaves are named after the slot, internal nodes after their index.
+                let (kind, id, slot) = match n {
+                    0 => {
+                        writeln!(out, "    e{}_{} [shape=point];", index, position)?;
+                        ('e', index, Some(position))
+                    }
+                    n if n & HIGH != 0 => {
+                        self.write_leaf(out, index, position, n & !HIGH)?;
+                        ('l', index, Some(position))
+                    }
+                    n => {
+                        stack.push((n, level + 1));
+                        ('n', n, None)
+                    }
+                };
+                write!(out, "    n{} -> {}{}", index, kind, id)?;
+                if let Some(slot) = slot {
+                    write!(out, "_{}", slot)?;
+                }
+                if branch_bits {
+                    write!(out, " [label=\"{}\"]", position)?;
+                }
+                writeln!(out, ";")?;
+            }
+        }
+        writeln!(out, "}}")
+    }
+
+    /// Writes the leaf at `position` of the internal node at `index`, with its bucket.
+    fn write_leaf<W>(&self, out: &mut W, index: u32, position: usize, item: u32) -> fmt::Result
+    where
+        W: Write,
+    {
+        write!(
+            out,
+            "    l{}_{} [shape=box, label=\"{}",
+            index, position, item
+        )?;
+        for other in self.bucket(item) {
+            write!(out, ", {}", other)?;
+        }
+        writeln!(out, "\"];")
+    }
+}
@@ -0,0 +1,102 @@
+use crate::into_items::Taking;
+use crate::{BinTrie, Internal};
+
+/// The iterator returned by `BinTrie::drain`, which empties the trie.
+///
+/// Items are yielded in the same order as `BinTrie::items`. Once it is dropped,
+/// the trie is empty, even if not every item was yielded.
+#[derive(Debug)]
+pub struct Drain<'a> {
+    trie: &'a mut BinTrie,
+    taking: Taking,
+}
+
+impl Iterator for Drain<'_> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // The trie is cleared once the drain is dropped, so nothing has to be released.
+        self.taking.next(self.trie, |_| {})
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.taking.size_hint()
+    }
+}
+
+impl ExactSizeIterator for Drain<'_> {}
+
+impl Drop for Drain<'_> {
+    fn drop(&mut self) {
+        self.trie.clear();
+    }
+}
+
+impl BinTrie {
+    /// Removes every item, keeping the depth, metadata and allocated memory.
+    ///
+    /// Tracking the insertion order and bucketing stay enabled.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let mut trie = BinTrie::new_depth(8);
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// for n in 0..32 {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// trie.clear();
+    /// assert!(trie.is_empty());
+    /// assert_eq!(trie.get(|l| lookup(3, l)), None);
+    /// ```
+    pub fn clear(&mut self) {
+        // The table of the top levels and the subtree counts would become stale.
+        self.top = None;
+        self.counts = None;
+        self.internals.clear();
+        self.internals.push(Internal::default());
+        self.terminals.clear();
+        if let Some(order) = &mut self.insertion_order {
+            order.clear();
+        }
+        if let Some(buckets) = &mut self.buckets {
+            buckets.clear();
+        }
+        self.free.clear();
+        self.generation += 1;
+        self.suspect.clear();
+        self.reached_by.clear();
+        if self.accesses.is_some() {
+            self.count_accesses();
+        }
+        self.len = 0;
+    }
+
+    /// Iterates over the items while emptying the trie, so it can be reused.
+    ///
+    /// This yields the same items in the same order as `items`. The trie keeps its
+    /// allocated memory, so refilling it after a drain does not reallocate.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let mut trie = BinTrie::new_depth(8);
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// for n in 0..32 {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// let items = trie.items().collect::<Vec<u32>>();
+    /// assert_eq!(trie.drain().collect::<Vec<u32>>(), items);
+    /// assert!(trie.is_empty());
+    /// trie.insert(3, |l| lookup(3, l), lookup);
+    /// assert_eq!(trie.items().collect::<Vec<u32>>(), vec![3]);
+    /// ```
+    pub fn drain(&mut self) -> Drain<'_> {
+        // The table of the top levels, the subtree counts and recorded paths would become stale.
+        self.top = None;
+        self.counts = None;
+        self.generation += 1;
+        Drain {
+            taking: Taking::new(self),
+            trie: self,
+        }
+    }
+}
@@ -0,0 +1,218 @@
+use crate::{BinTrie, HIGH};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// A place in a trie for a key, which is either occupied or vacant.
+///
+/// This is returned by `BinTrie::entry`.
+pub enum Entry<'a, K, F> {
+    Occupied(OccupiedEntry<'a>),
+    Vacant(VacantEntry<'a, K, F>),
+}
+
+/// A place in a trie where an item with the key is stored.
+pub struct OccupiedEntry<'a> {
+    trie: &'a mut BinTrie,
+    /// The internal node and position of every slot from the root to the item.
+    path: Vec<(usize, usize)>,
+}
+
+/// A place in a trie where an item with the key could be stored.
+pub struct VacantEntry<'a, K, F> {
+    trie: &'a mut BinTrie,
+    /// The internal node where insertion resumes.
+    index: usize,
+    /// The depth of the internal node at `index`.
+    level: u32,
+    key: K,
+    lookup: F,
+}
+
+impl BinTrie {
+    /// Finds the place in the trie for a key, so it can be inspected or changed without descending again.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the key.
+    /// `F(item, n)` - A function that must be able to look up the nth bit
+    ///    from a previously inserted item.
+    ///
+    /// The `lookup` is used to make sure that an item found along the key really has
+    /// the whole key, and is kept by a vacant entry for inserting later.
+    ///
+    /// ```
+    /// # use bintrie::{BinTrie, Entry};
+    /// let mut trie = BinTrie::new_depth(8);
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// assert_eq!(trie.entry(|l| lookup(5, l), lookup).or_insert_with(|| 5), 5);
+    /// assert_eq!(trie.entry(|l| lookup(5, l), lookup).or_insert(7), 5);
+    /// match trie.entry(|l| lookup(6, l), lookup) {
+    ///     Entry::Occupied(_) => unreachable!(),
+    ///     Entry::Vacant(entry) => entry.insert(6),
+    /// }
+    /// if let Entry::Occupied(entry) = trie.entry(|l| lookup(5, l), lookup) {
+    ///     assert_eq!(entry.remove(), 5);
+    /// }
+    /// assert_eq!(trie.items().collect::<Vec<u32>>(), vec![6]);
+    /// ```
+    pub fn entry<K, F>(&mut self, mut key: K, mut lookup: F) -> Entry<'_, K, F>
+    where
+        K: FnMut(u32) -> bool,
+        F: FnMut(u32, u32) -> bool,
+    {
+        self.assert_trusted();
+        let mut path = vec![];
+        let mut index = 0;
+        for level in 0..self.depth {
+            let position = key(level) as usize;
+            path.push((index, position));
+            match self.internals[index].0[position] {
+                // Internal node encountered.
+                m if m != 0 && m & HIGH == 0 => index = m as usize,
+                // Make sure a leaf actually has the key.
+                m if m != 0 && (level + 1..self.depth).all(|l| lookup(m & !HIGH, l) == key(l)) => {
+                    return Entry::Occupied(OccupiedEntry { trie: self, path });
+                }
+                // Either nothing is here or a leaf with another key is.
+                _ => {
+                    return Entry::Vacant(VacantEntry {
+                        trie: self,
+                        index,
+                        level,
+                        key,
+                        lookup,
+                    })
+                }
+            }
+        }
+        unreachable!("the last level always holds a leaf or nothing")
+    }
+
+    /// Gets the item with a key, inserting the item from `make` if there is none.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the key.
+    /// `F(item, n)` - A function that must be able to look up the nth bit
+    ///    from a previously inserted item.
+    ///
+    /// The trie is only descended once, and `make` is only called if no item has the key.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let mut trie = BinTrie::new_depth(8);
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// assert_eq!(trie.get_or_insert_with(|l| lookup(3, l), lookup, || 3), 3);
+    /// assert_eq!(trie.get_or_insert_with(|l| lookup(3, l), lookup, || unreachable!()), 3);
+    /// ```
+    pub fn get_or_insert_with<K, F, G>(&mut self, key: K, lookup: F, make: G) -> u32
+    where
+        K: FnMut(u32) -> bool,
+        F: FnMut(u32, u32) -> bool,
+        G: FnOnce() -> u32,
+    {
+        self.entry(key, lookup).or_insert_with(make)
+    }
+}
+
+impl<'a, K, F> Entry<'a, K, F>
+where
+    K: FnMut(u32) -> bool,
+    F: FnMut(u32, u32) -> bool,
+{
+    /// Inserts `item` if the entry is vacant, and returns the item stored in the entry.
+    pub fn or_insert(self, item: u32) -> u32 {
+        self.or_insert_with(|| item)
+    }
+
+    /// Inserts the item from `make` if the entry is vacant, and returns the item stored in the entry.
+    pub fn or_insert_with<M>(self, make: M) -> u32
+    where
+        M: FnOnce() -> u32,
+    {
+        match self {
+            Entry::Occupied(entry) => entry.get(),
+            Entry::Vacant(entry) => {
+                let item = make();
+                entry.insert(item);
+                item
+            }
+        }
+    }
+
+    /// Replaces the item in the entry with the item from `modify` if the entry is occupied.
+    pub fn and_modify<M>(self, modify: M) -> Self
+    where
+        M: FnOnce(u32) -> u32,
+    {
+        match self {
+            Entry::Occupied(mut entry) => {
+                let item = modify(entry.get());
+                entry.insert(item);
+                Entry::Occupied(entry)
+            }
+            vacant => vacant,
+        }
+    }
+}
+
+impl<'a> OccupiedEntry<'a> {
+    /// The slot holding the item.
+    fn slot(&self) -> (usize, usize) {
+        *self.path.last().expect("the path always ends at the item")
+    }
+
+    /// Gets the item stored in the entry.
+    pub fn get(&self) -> u32 {
+        let (index, position) = self.slot();
+        self.trie.internals[index].0[position] & !HIGH
+    }
+
+    /// Replaces the item stored in the entry and returns the old one.
+    pub fn insert(&mut self, item: u32) -> u32 {
+        // Always check that the high bit is not set in the item.
+        assert!(item & HIGH == 0);
+        // The table of the top levels and the subtree counts would become stale.
+        self.trie.top = None;
+        self.trie.counts = None;
+        let (index, position) = self.slot();
+        let old =
+            core::mem::replace(&mut self.trie.internals[index].0[position], item | HIGH) & !HIGH;
+        self.trie.rename_bucket(old, item);
+        self.trie.record_insertion(item, Some(old));
+        old
+    }
+
+    /// Removes the item stored in the entry and returns it, like `BinTrie::remove`.
+    pub fn remove(mut self) -> u32 {
+        // The table of the top levels and the subtree counts would become stale.
+        self.trie.top = None;
+        self.trie.counts = None;
+        let (index, position) = self.path.pop().expect("the path always ends at the item");
+        let item = self.trie.internals[index].0[position] & !HIGH;
+        self.trie.forget_insertion(item);
+        // Another item in the bucket of the leaf takes its place if there is one.
+        let next = self.trie.take_from_bucket(item);
+        self.trie.internals[index].0[position] = next;
+        if next == 0 {
+            self.trie.collapse(index, &self.path);
+        }
+        item
+    }
+}
+
+impl<'a, K, F> VacantEntry<'a, K, F>
+where
+    K: FnMut(u32) -> bool,
+    F: FnMut(u32, u32) -> bool,
+{
+    /// Inserts `item` into the entry.
+    pub fn insert(self, item: u32) {
+        self.trie
+            .insert_from(
+                (self.index, self.level),
+                item,
+                self.key,
+                self.lookup,
+                |_, _| {},
+                |_| item,
+            )
+            .unwrap_or_else(|error| panic!("{}", error));
+    }
+}
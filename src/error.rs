@@ -0,0 +1,139 @@
+use crate::BinTrie;
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::fmt;
+
+/// Returned by `BinTrie::with_depth` when items collide under the new depth.
+#[derive(Clone, Debug)]
+pub struct DepthChangeError {
+    /// Pairs of `(kept, dropped)` items which share every key bit under the new depth.
+    pub collisions: Vec<(u32, u32)>,
+    /// The rebuilt trie, which keeps the first item of each collision.
+    pub trie: Box<BinTrie>,
+}
+
+impl fmt::Display for DepthChangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} items collided when changing the trie depth to {}",
+            self.collisions.len(),
+            self.trie.depth
+        )
+    }
+}
+
+impl core::error::Error for DepthChangeError {}
+
+/// Returned when an item can not be inserted into a trie.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BinTrieError {
+    /// The item is larger than `BinTrie::MAX_ITEM`, since the most significant bit is reserved.
+    ItemTooLarge(u32),
+    /// There is no index left for another internal node.
+    CapacityExhausted,
+    /// The key has more bits than the depth of the trie.
+    DepthExhausted,
+    /// The trie was loaded leniently and must be validated or repaired before it is modified.
+    SuspectTrie,
+}
+
+impl fmt::Display for BinTrieError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BinTrieError::ItemTooLarge(item) => {
+                write!(f, "item {:#x} has the most significant bit set", item)
+            }
+            BinTrieError::CapacityExhausted => {
+                write!(f, "the trie has no room for more internal nodes")
+            }
+            BinTrieError::DepthExhausted => write!(f, "the key is deeper than the trie"),
+            BinTrieError::SuspectTrie => {
+                write!(
+                    f,
+                    "the trie is suspect and must be validated or repaired first"
+                )
+            }
+        }
+    }
+}
+
+impl core::error::Error for BinTrieError {}
+
+/// Returned when a trie from outside of the program breaks the invariants of a trie.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum InvalidTrieError {
+    /// The depth of the trie is `0`.
+    ZeroDepth,
+    /// There are no internal nodes, so there is no root.
+    MissingRoot,
+    /// A child points past the end of the internal nodes.
+    IndexOutOfRange(u32),
+    /// More than one child points to the same internal node, which may form a cycle.
+    RepeatedIndex(u32),
+    /// An internal node is deeper than the depth of the trie.
+    TooDeep(u32),
+    /// A terminated item belongs to an internal node that is not in the trie.
+    InvalidTerminal(u32),
+    /// An internal node that is waiting to be reused is still in use.
+    InvalidFreeNode(u32),
+    /// A bucket belongs to an item that is not a leaf.
+    InvalidBucket(u32),
+    /// The insertion order does not have every item.
+    InvalidInsertionOrder,
+    /// A leaf refers to an entry that does not exist.
+    InvalidEntry(u32),
+    /// An internal node of a `CompressedTrie` does not branch on a level between
+    /// that of its parent and the depth.
+    InvalidLevel(u32),
+    /// The bytes of a borrowed trie don't start with a supported header or have the wrong length.
+    InvalidHeader,
+    /// The bytes of a borrowed trie are not aligned for the internal nodes.
+    Misaligned,
+}
+
+impl fmt::Display for InvalidTrieError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidTrieError::ZeroDepth => write!(f, "the trie has a depth of 0"),
+            InvalidTrieError::MissingRoot => write!(f, "the trie has no root"),
+            InvalidTrieError::IndexOutOfRange(index) => {
+                write!(f, "internal node {} is out of range", index)
+            }
+            InvalidTrieError::RepeatedIndex(index) => {
+                write!(f, "internal node {} is a child more than once", index)
+            }
+            InvalidTrieError::TooDeep(index) => {
+                write!(f, "internal node {} is deeper than the trie", index)
+            }
+            InvalidTrieError::InvalidTerminal(index) => {
+                write!(
+                    f,
+                    "internal node {} has a terminated item but is not in the trie",
+                    index
+                )
+            }
+            InvalidTrieError::InvalidFreeNode(index) => {
+                write!(f, "internal node {} is free but still in use", index)
+            }
+            InvalidTrieError::InvalidBucket(item) => {
+                write!(f, "item {} has a bucket but is not a leaf", item)
+            }
+            InvalidTrieError::InvalidInsertionOrder => {
+                write!(f, "the insertion order does not match the items")
+            }
+            InvalidTrieError::InvalidEntry(entry) => write!(f, "entry {} does not exist", entry),
+            InvalidTrieError::InvalidLevel(index) => {
+                write!(
+                    f,
+                    "internal node {} branches on a level out of order",
+                    index
+                )
+            }
+            InvalidTrieError::InvalidHeader => write!(f, "the trie has an invalid header"),
+            InvalidTrieError::Misaligned => write!(f, "the trie is not aligned to 4 bytes"),
+        }
+    }
+}
+
+impl core::error::Error for InvalidTrieError {}
@@ -0,0 +1,112 @@
+//! Utilities for measuring the quality of approximate nearest neighbor searches.
+//!
+//! Tuning a heuristic usually means trading how many internal nodes are probed
+//! against how many of the true nearest neighbors are found. `evaluate` runs a
+//! heuristic over a set of queries with known ground truth and reports both.
+
+use crate::{BinTrie, Heuristic, IntoHeuristic, Side};
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::cell::Cell;
+
+/// The result of `evaluate`.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Evaluation {
+    /// The mean fraction of the `k` true nearest neighbors that were found.
+    pub recall: f64,
+    /// The mean number of internal nodes entered per query.
+    pub mean_probes: f64,
+}
+
+/// Wraps a heuristic to count the internal nodes that are entered.
+#[derive(Clone)]
+struct Probed<H> {
+    heuristic: H,
+    probes: Rc<Cell<usize>>,
+}
+
+impl<H> Heuristic for Probed<H>
+where
+    H: Heuristic,
+{
+    type Iter = H::Iter;
+
+    #[inline(always)]
+    fn enter(&mut self, side: Side, level: u32) {
+        self.probes.set(self.probes.get() + 1);
+        self.heuristic.enter(side, level);
+    }
+
+    #[inline(always)]
+    fn iter(&self, level: u32) -> Self::Iter {
+        self.heuristic.iter(level)
+    }
+
+    #[inline(always)]
+    fn begin(&mut self) {
+        self.heuristic.begin();
+    }
+}
+
+/// Computes recall@`k` and the mean probe count of a heuristic over a query set.
+///
+/// For every query, `heuristic` creates the heuristic to explore with and at most
+/// `budget` items are taken from the exploration. `ground_truth` contains the true
+/// nearest neighbors of each query, nearest first, and the first `k` of them are
+/// checked for in the explored items.
+///
+/// ```
+/// # use bintrie::{BinTrie, CompiledHeuristic, eval::evaluate};
+/// let mut trie = BinTrie::new_depth(4);
+/// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+/// for n in 0..16 {
+///     trie.insert(n, |l| lookup(n, l), lookup);
+/// }
+/// let queries = [0b0000, 0b1111];
+/// let ground_truth = [vec![0b0000, 0b0001], vec![0b1111, 0b0111]];
+/// let evaluation = evaluate(&trie, &queries, &ground_truth, 2, 16, |&q| {
+///     CompiledHeuristic::hamming(move |l| lookup(q, l), 4, 1)
+/// });
+/// assert_eq!(evaluation.recall, 1.0);
+/// ```
+pub fn evaluate<Q, H, M>(
+    trie: &BinTrie,
+    queries: &[Q],
+    ground_truth: &[Vec<u32>],
+    k: usize,
+    budget: usize,
+    mut heuristic: M,
+) -> Evaluation
+where
+    M: FnMut(&Q) -> H,
+    H: IntoHeuristic,
+{
+    assert_eq!(queries.len(), ground_truth.len());
+    if queries.is_empty() {
+        return Evaluation::default();
+    }
+    let mut recall = 0.0;
+    let mut probes = 0;
+    for (query, truth) in queries.iter().zip(ground_truth) {
+        let counter = Rc::new(Cell::new(0));
+        let found = trie
+            .explore(Probed {
+                heuristic: heuristic(query).into_heuristic(),
+                probes: counter.clone(),
+            })
+            .take(budget)
+            .collect::<Vec<u32>>();
+        let truth = &truth[..k.min(truth.len())];
+        if truth.is_empty() {
+            recall += 1.0;
+        } else {
+            let hits = truth.iter().filter(|item| found.contains(item)).count();
+            recall += hits as f64 / truth.len() as f64;
+        }
+        probes += counter.get();
+    }
+    Evaluation {
+        recall: recall / queries.len() as f64,
+        mean_probes: probes as f64 / queries.len() as f64,
+    }
+}
@@ -0,0 +1,508 @@
+use crate::{BinTrie, Child, FastSet, Heuristic, Nodes, Side};
+use alloc::vec::Vec;
+use core::borrow::BorrowMut;
+use core::hash::Hash;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// How many internal nodes are entered between checks of the cancellation flag.
+pub(crate) const CANCEL_INTERVAL: usize = 64;
+
+/// A single level of the stack used while exploring.
+///
+/// This is only exposed so that the stack can be provided by the caller
+/// with `BinTrie::explore_in`.
+pub struct ExploreFrame<H, I = u32>
+where
+    H: Heuristic,
+{
+    index: I,
+    heuristic: H,
+    iter: H::Iter,
+}
+
+impl<H, I> ExploreFrame<H, I>
+where
+    H: Heuristic,
+{
+    /// Makes the frame of the internal node at `index`, which is on `level`.
+    pub(crate) fn new(index: I, heuristic: H, level: u32) -> Self {
+        let iter = heuristic.iter(level);
+        Self {
+            index,
+            heuristic,
+            iter,
+        }
+    }
+}
+
+/// Where an item was found, for the internal nodes identified by `I`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) enum Location<I> {
+    /// The leaf on a side of an internal node.
+    Leaf(I, Side),
+    /// The item terminated at an internal node.
+    Terminal(I),
+}
+
+/// Decides which leaves an exploration yields.
+pub(crate) struct Leaves<P, I> {
+    filter: P,
+    pub(crate) rejected: usize,
+    /// The locations of every leaf yielded so far, if deduplicating.
+    seen: Option<FastSet<Location<I>>>,
+    /// The location of the most recently yielded leaf.
+    pub(crate) location: Option<Location<I>>,
+    /// The leaf whose bucket is being yielded, the position of the next item in the
+    /// bucket and the location of the leaf.
+    bucket: Option<(u32, usize, Location<I>)>,
+}
+
+impl<P, I> Leaves<P, I>
+where
+    P: FnMut(u32) -> bool,
+    I: Copy + Ord + Hash,
+{
+    /// Checks if the leaf at `location` was already reached, which only happens when deduplicating.
+    #[inline(always)]
+    fn repeated(&mut self, location: Location<I>) -> bool {
+        match &mut self.seen {
+            Some(seen) => !seen.insert(location),
+            None => false,
+        }
+    }
+
+    /// Checks if the leaf `item` at `location` should be yielded.
+    #[inline(always)]
+    fn accept(&mut self, item: u32, location: Location<I>) -> bool {
+        if (self.filter)(item) {
+            self.location = Some(location);
+            true
+        } else {
+            self.rejected += 1;
+            false
+        }
+    }
+
+    /// Gets the next accepted item from the bucket of the most recently reached leaf.
+    #[inline(always)]
+    fn next_in_bucket<T>(&mut self, trie: &T) -> Option<u32>
+    where
+        T: Nodes,
+    {
+        while let Some((leaf, position, location)) = self.bucket {
+            match trie.bucket_item(leaf, position) {
+                Some(item) => {
+                    self.bucket = Some((leaf, position + 1, location));
+                    if self.accept(item, location) {
+                        return Some(item);
+                    }
+                }
+                None => self.bucket = None,
+            }
+        }
+        None
+    }
+}
+
+/// Accepts every leaf.
+pub(crate) fn accept_all(_: u32) -> bool {
+    true
+}
+
+/// Wraps a heuristic to explore a subtree whose root is `levels` levels down.
+///
+/// Explorations count levels from the node they start at, so this passes the
+/// levels of the whole trie on to the heuristic.
+#[derive(Clone)]
+pub(crate) struct Shifted<H> {
+    pub(crate) heuristic: H,
+    pub(crate) levels: u32,
+}
+
+impl<H> Heuristic for Shifted<H>
+where
+    H: Heuristic,
+{
+    type Iter = H::Iter;
+
+    #[inline(always)]
+    fn enter(&mut self, side: Side, level: u32) {
+        self.heuristic.enter(side, level + self.levels);
+    }
+
+    #[inline(always)]
+    fn iter(&self, level: u32) -> Self::Iter {
+        self.heuristic.iter(level + self.levels)
+    }
+
+    #[inline(always)]
+    fn begin(&mut self) {
+        // The exploration already began above the subtree, so this continues it.
+    }
+}
+
+/// Is told about every step of an exploration.
+///
+/// Levels are those of the branch the step is taken at, counted from the node the
+/// exploration started at. The unit type ignores every step.
+pub(crate) trait Observer {
+    /// The heuristic chose `side` at the node on top of the stack.
+    #[inline(always)]
+    fn choose(&mut self, _side: Side) {}
+
+    /// The internal node on `side` of the branch at `level` was entered.
+    #[inline(always)]
+    fn enter(&mut self, _level: u32, _side: Side) {}
+
+    /// The heuristic has nothing left to choose at the node on `level`, whose
+    /// children are given by `children`.
+    #[inline(always)]
+    fn exhaust<I, C>(&mut self, _level: u32, _children: C)
+    where
+        C: FnOnce() -> [Child<I>; 2],
+    {
+    }
+
+    /// The leaf `item` on `side` of the branch at `level` was yielded.
+    #[inline(always)]
+    fn leaf(&mut self, _item: u32, _level: u32, _side: Side) {}
+
+    /// The `item` terminated at the internal node on `level` was yielded.
+    #[inline(always)]
+    fn terminal(&mut self, _item: u32, _level: u32) {}
+}
+
+impl Observer for () {}
+
+/// Explores the trie, keeping its stack in `S`, which may be owned or borrowed.
+///
+/// Leaves are only yielded if they are accepted by the `leaves`.
+///
+/// At most `limit` internal nodes are entered. When the limit is reached the
+/// iterator returns `None`, but remembers the node it was about to enter so
+/// that it can resume if the limit is raised.
+///
+/// If there is a `cancel` flag, it is checked every `CANCEL_INTERVAL` internal
+/// nodes, and once it is set the exploration stops for good.
+///
+/// Every step taken is reported to the `observer`.
+pub(crate) struct ExploreIter<'c, T, H, S, P, O = ()>
+where
+    T: Nodes,
+    H: Heuristic,
+{
+    trie: T,
+    indices: S,
+    pub(crate) leaves: Leaves<P, T::Index>,
+    pub(crate) entered: usize,
+    pub(crate) limit: usize,
+    /// An internal node that was not entered because the limit was reached, along
+    /// with the level of its parent.
+    pub(crate) suspended: Option<(T::Index, Side, H, u32)>,
+    cancel: Option<&'c AtomicBool>,
+    /// Whether the trie is not verified, so every index must be checked.
+    checked: bool,
+    pub(crate) observer: O,
+}
+
+impl<'c, T, H, S, P> ExploreIter<'c, T, H, S, P>
+where
+    T: Nodes,
+    H: Heuristic,
+    S: BorrowMut<Vec<ExploreFrame<H, T::Index>>>,
+    P: FnMut(u32) -> bool,
+{
+    pub(crate) fn new(trie: T, heuristic: H, indices: S, filter: P) -> Self {
+        let root = trie.root();
+        Self::new_at(trie, root, heuristic, indices, filter)
+    }
+
+    /// Explores only beneath the internal node at `index`, which is treated as level `0`.
+    ///
+    /// The item terminated at `index` itself is not yielded, just like that of the root.
+    pub(crate) fn new_at(
+        trie: T,
+        index: T::Index,
+        mut heuristic: H,
+        mut indices: S,
+        filter: P,
+    ) -> Self {
+        let checked = !trie.is_verified();
+        heuristic.begin();
+        indices.borrow_mut().clear();
+        indices
+            .borrow_mut()
+            .push(ExploreFrame::new(index, heuristic, 0));
+        Self {
+            trie,
+            indices,
+            leaves: Leaves {
+                filter,
+                rejected: 0,
+                seen: None,
+                location: None,
+                bucket: None,
+            },
+            entered: 0,
+            limit: usize::MAX,
+            suspended: None,
+            cancel: None,
+            checked,
+            observer: (),
+        }
+    }
+
+    /// Reports every step taken to `observer`.
+    pub(crate) fn observe<O>(self, observer: O) -> ExploreIter<'c, T, H, S, P, O>
+    where
+        O: Observer,
+    {
+        ExploreIter {
+            trie: self.trie,
+            indices: self.indices,
+            leaves: self.leaves,
+            entered: self.entered,
+            limit: self.limit,
+            suspended: self.suspended,
+            cancel: self.cancel,
+            checked: self.checked,
+            observer,
+        }
+    }
+}
+
+impl<'c, T, H, S, P, O> ExploreIter<'c, T, H, S, P, O>
+where
+    T: Nodes,
+    H: Heuristic,
+    S: BorrowMut<Vec<ExploreFrame<H, T::Index>>>,
+    P: FnMut(u32) -> bool,
+{
+    /// Gets the heuristic of the node that the most recently yielded leaf was found in.
+    pub(crate) fn current_heuristic(&self) -> Option<&H> {
+        self.indices.borrow().last().map(|frame| &frame.heuristic)
+    }
+
+    /// Gets the path to the most recently yielded item, as the sides taken from the root.
+    pub(crate) fn path(&self) -> Vec<Side> {
+        let frames = self.indices.borrow();
+        let mut path: Vec<Side> = frames
+            .windows(2)
+            .map(|pair| {
+                let (parent, child) = (pair[0].index, pair[1].index);
+                Side::from(self.trie.child_checked(parent, 1) == Child::Internal(child))
+            })
+            .collect();
+        // A leaf is one more side away from the node it was found in.
+        if let Some(Location::Leaf(_, side)) = self.leaves.location {
+            path.push(side);
+        }
+        path
+    }
+
+    /// Skips any leaf that was already yielded, in case the heuristic reaches it twice.
+    pub(crate) fn deduplicate(mut self) -> Self {
+        self.leaves.seen = Some(FastSet::new());
+        self
+    }
+
+    /// Stops the exploration once `cancel` is set.
+    pub(crate) fn cancel_on(mut self, cancel: &'c AtomicBool) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+}
+
+impl<'c, T, H, S, P, O> Iterator for ExploreIter<'c, T, H, S, P, O>
+where
+    T: Nodes,
+    H: Heuristic,
+    S: BorrowMut<Vec<ExploreFrame<H, T::Index>>>,
+    P: FnMut(u32) -> bool,
+    O: Observer,
+{
+    type Item = u32;
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        let indices = self.indices.borrow_mut();
+        if let Some(item) = self.leaves.next_in_bucket(&self.trie) {
+            if let Some(Location::Leaf(_, side)) = self.leaves.location {
+                self.observer.leaf(item, indices.len() as u32 - 1, side);
+            }
+            return Some(item);
+        }
+        loop {
+            let (n, choice, mut next_heuristic, level) =
+                if let Some(suspended) = self.suspended.take() {
+                    suspended
+                } else {
+                    // Get the current frame. If there is none, then we return `None`.
+                    let mut frame = indices.pop()?;
+                    // Clone the heuristic before we put it back so we can
+                    // use it when descending further.
+                    let next_heuristic = frame.heuristic.clone();
+                    let index = frame.index;
+                    // Get the next item in the array or continue the loop if its empty.
+                    let (choice, n) = if let Some(choice) = frame.iter.next() {
+                        self.observer.choose(choice);
+                        let n = if self.checked {
+                            self.trie.child_checked(index, choice.index())
+                        } else {
+                            unsafe { self.trie.child_unchecked(index, choice.index()) }
+                        };
+                        // Push the state back.
+                        indices.push(frame);
+                        (choice, n)
+                    } else {
+                        let (trie, checked) = (&self.trie, self.checked);
+                        self.observer.exhaust(indices.len() as u32, || {
+                            [0, 1].map(|position| {
+                                if checked {
+                                    trie.child_checked(index, position)
+                                } else {
+                                    unsafe { trie.child_unchecked(index, position) }
+                                }
+                            })
+                        });
+                        continue;
+                    };
+                    // Check what kind of node it is.
+                    match n {
+                        // Empty node
+                        Child::Empty => continue,
+                        // Leaf node
+                        Child::Leaf(item) => {
+                            let location = Location::Leaf(index, choice);
+                            if self.leaves.repeated(location) {
+                                continue;
+                            }
+                            // The rest of the bucket comes right after the leaf.
+                            self.leaves.bucket = Some((item, 0, location));
+                            let level = indices.len() as u32 - 1;
+                            if self.leaves.accept(item, location) {
+                                self.observer.leaf(item, level, choice);
+                                return Some(item);
+                            }
+                            if let Some(item) = self.leaves.next_in_bucket(&self.trie) {
+                                self.observer.leaf(item, level, choice);
+                                return Some(item);
+                            }
+                            continue;
+                        }
+                        // Internal node that is too deep, which may be a cycle in a suspect trie.
+                        Child::Internal(_)
+                            if self.checked && indices.len() >= self.trie.depth() as usize =>
+                        {
+                            continue
+                        }
+                        // Internal node, whose parent is the frame on top of the stack.
+                        Child::Internal(n) => (n, choice, next_heuristic, indices.len() as u32 - 1),
+                    }
+                };
+            // Give up on the whole exploration if it was cancelled.
+            if self.entered.is_multiple_of(CANCEL_INTERVAL)
+                && self
+                    .cancel
+                    .is_some_and(|cancel| cancel.load(Ordering::Relaxed))
+            {
+                indices.clear();
+                return None;
+            }
+            // Stop before entering the internal node if the limit was reached.
+            if self.entered >= self.limit {
+                self.suspended = Some((n, choice, next_heuristic, level));
+                return None;
+            }
+            self.entered += 1;
+            next_heuristic.enter(choice, level);
+            self.observer.enter(level, choice);
+            indices.push(ExploreFrame::new(n, next_heuristic, level + 1));
+            if let Some(item) = self.trie.terminal(n) {
+                let location = Location::Terminal(n);
+                if !self.leaves.repeated(location) && self.leaves.accept(item, location) {
+                    self.observer.terminal(item, level + 1);
+                    return Some(item);
+                }
+            }
+        }
+    }
+}
+
+/// An exploration which owns its stack.
+pub(crate) type OwnedExplore<'a, H, P, O = ()> =
+    ExploreIter<'a, &'a BinTrie, H, Vec<ExploreFrame<H>>, P, O>;
+
+/// The iterator returned by `BinTrie::explore_filtered`.
+pub struct FilteredExplore<'a, H, P>(pub(crate) OwnedExplore<'a, H, P>)
+where
+    H: Heuristic;
+
+impl<'a, H, P> FilteredExplore<'a, H, P>
+where
+    H: Heuristic,
+{
+    /// The number of leaves rejected by the filter so far.
+    pub fn rejected(&self) -> usize {
+        self.0.leaves.rejected
+    }
+
+    /// Stops the exploration once `cancel` is set, like `BinTrie::explore_cancellable`.
+    pub fn cancel_on(self, cancel: &'a AtomicBool) -> Self
+    where
+        P: FnMut(u32) -> bool,
+    {
+        Self(self.0.cancel_on(cancel))
+    }
+}
+
+impl<'a, H, P> Iterator for FilteredExplore<'a, H, P>
+where
+    H: Heuristic,
+    P: FnMut(u32) -> bool,
+{
+    type Item = u32;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+}
+
+/// The iterator returned by `BinTrie::explore_budgeted`.
+pub struct BudgetedExplore<'a, H>(pub(crate) OwnedExplore<'a, H, fn(u32) -> bool>)
+where
+    H: Heuristic;
+
+impl<'a, H> BudgetedExplore<'a, H>
+where
+    H: Heuristic,
+{
+    /// The number of internal nodes entered so far.
+    pub fn visited(&self) -> usize {
+        self.0.entered
+    }
+
+    /// Checks if the exploration stopped because the budget ran out rather than
+    /// because there was nothing left to explore.
+    pub fn is_exhausted(&self) -> bool {
+        self.0.suspended.is_some()
+    }
+
+    /// Allows `nodes` more internal nodes to be entered, so an exhausted exploration
+    /// continues where it stopped.
+    pub fn extend_budget(&mut self, nodes: usize) {
+        self.0.limit = self.0.limit.saturating_add(nodes);
+    }
+}
+
+impl<'a, H> Iterator for BudgetedExplore<'a, H>
+where
+    H: Heuristic,
+{
+    type Item = u32;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+}
@@ -0,0 +1,241 @@
+use crate::explore::Shifted;
+use crate::{BinTrie, Heuristic, IntoHeuristic};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// A set of independent tries, each holding the items whose keys start with the same `bits`.
+///
+/// Every shard is a `BinTrie` over the rest of the key, so the shards can be built
+/// at the same time on separate threads without any synchronization, which is done
+/// by `par_from_items` with the `rayon` feature. Lookups, iteration and exploration
+/// work across all of the shards as though they were one trie, and `into_trie`
+/// joins them into one.
+///
+/// ```
+/// # use bintrie::{BinTrie, TrieForest};
+/// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+/// let forest = TrieForest::from_items(3, 16, 0..1000, lookup);
+/// assert_eq!(forest.len(), 1000);
+/// assert_eq!(forest.get(|l| lookup(123, l)), Some(123));
+/// let mut trie = BinTrie::new_depth(16);
+/// for n in 0..1000 {
+///     trie.insert(n, |l| lookup(n, l), lookup);
+/// }
+/// assert_eq!(forest.items().collect::<Vec<_>>(), trie.items().collect::<Vec<_>>());
+/// assert!(forest.into_trie().structurally_equal(&trie));
+/// ```
+#[derive(Clone, Debug)]
+pub struct TrieForest {
+    /// The shards in the order of the key bits they are chosen by.
+    shards: Vec<BinTrie>,
+    /// The number of key bits that choose a shard.
+    bits: u32,
+}
+
+impl TrieForest {
+    /// Makes `2^bits` empty shards for keys with a maximum `depth`.
+    pub fn new_depth(bits: u32, depth: u32) -> Self {
+        assert!(bits <= 16, "a forest can have at most 2^16 shards");
+        assert!(bits < depth);
+        Self {
+            shards: (0..1 << bits)
+                .map(|_| BinTrie::new_depth(depth - bits))
+                .collect(),
+            bits,
+        }
+    }
+
+    /// Builds a forest from the items, one shard after another.
+    ///
+    /// `F(item, n)` - A function that must be able to look up the nth bit
+    ///    from an item.
+    pub fn from_items<I, F>(bits: u32, depth: u32, items: I, lookup: F) -> Self
+    where
+        I: IntoIterator<Item = u32>,
+        F: Fn(u32, u32) -> bool,
+    {
+        let mut forest = Self::new_depth(bits, depth);
+        for (shard, items) in forest.partition(items, &lookup).into_iter().enumerate() {
+            forest.fill(shard, items, &lookup);
+        }
+        forest
+    }
+
+    /// Builds a forest from the items, filling every shard on a separate rayon task.
+    ///
+    /// `F(item, n)` - A function that must be able to look up the nth bit
+    ///    from an item.
+    ///
+    /// ```
+    /// # use bintrie::TrieForest;
+    /// let lookup = |n: u32, l: u32| n.wrapping_mul(0x9E37_79B9) >> l & 1 == 1;
+    /// let forest = TrieForest::par_from_items(4, 32, 0..10_000, lookup);
+    /// let sequential = TrieForest::from_items(4, 32, 0..10_000, lookup);
+    /// assert_eq!(forest.items().collect::<Vec<_>>(), sequential.items().collect::<Vec<_>>());
+    /// ```
+    #[cfg(feature = "rayon")]
+    pub fn par_from_items<I, F>(bits: u32, depth: u32, items: I, lookup: F) -> Self
+    where
+        I: IntoIterator<Item = u32>,
+        F: Fn(u32, u32) -> bool + Sync,
+    {
+        use rayon::prelude::*;
+        let mut forest = Self::new_depth(bits, depth);
+        let partitions = forest.partition(items, &lookup);
+        forest
+            .shards
+            .par_iter_mut()
+            .zip(partitions)
+            .for_each(|(shard, items)| {
+                for item in items {
+                    shard.insert(item, |l| lookup(item, l + bits), |n, l| lookup(n, l + bits));
+                }
+            });
+        forest
+    }
+
+    /// Splits the items by the shard they belong in.
+    fn partition<I, F>(&self, items: I, lookup: &F) -> Vec<Vec<u32>>
+    where
+        I: IntoIterator<Item = u32>,
+        F: Fn(u32, u32) -> bool,
+    {
+        let mut partitions = vec![vec![]; self.shards.len()];
+        for item in items {
+            partitions[self.shard(|l| lookup(item, l))].push(item);
+        }
+        partitions
+    }
+
+    /// Inserts the items of a shard.
+    fn fill<F>(&mut self, shard: usize, items: Vec<u32>, lookup: &F)
+    where
+        F: Fn(u32, u32) -> bool,
+    {
+        let bits = self.bits;
+        for item in items {
+            self.shards[shard].insert(item, |l| lookup(item, l + bits), |n, l| lookup(n, l + bits));
+        }
+    }
+
+    /// The shard that a key belongs in.
+    fn shard<K>(&self, mut key: K) -> usize
+    where
+        K: FnMut(u32) -> bool,
+    {
+        (0..self.bits).fold(0, |shard, l| shard << 1 | key(l) as usize)
+    }
+
+    /// The number of key bits that choose a shard.
+    pub fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    /// The shards, in the order of the key bits that choose them.
+    pub fn shards(&self) -> &[BinTrie] {
+        &self.shards
+    }
+
+    /// The number of items in all of the shards.
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(BinTrie::len).sum()
+    }
+
+    /// Checks if there are no items in any shard.
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(BinTrie::is_empty)
+    }
+
+    /// Inserts an item into its shard.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the key.
+    /// `F(item, n)` - A function that must be able to look up the nth bit
+    ///    from a previously inserted item.
+    ///
+    /// Returns `Some` of a replaced leaf if a leaf was replaced, otherwise None.
+    pub fn insert<K, F>(&mut self, item: u32, mut key: K, mut lookup: F) -> Option<u32>
+    where
+        K: FnMut(u32) -> bool,
+        F: FnMut(u32, u32) -> bool,
+    {
+        let bits = self.bits;
+        let shard = self.shard(&mut key);
+        self.shards[shard].insert(item, |l| key(l + bits), |n, l| lookup(n, l + bits))
+    }
+
+    /// Perform a lookup for a particular item.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the key.
+    pub fn get<K>(&self, mut key: K) -> Option<u32>
+    where
+        K: FnMut(u32) -> bool,
+    {
+        let bits = self.bits;
+        self.shards[self.shard(&mut key)].get(|l| key(l + bits))
+    }
+
+    /// Get an iterator over the items in the same order as `BinTrie::items`.
+    pub fn items(&self) -> impl Iterator<Item = u32> + '_ {
+        self.shards.iter().flat_map(BinTrie::items)
+    }
+
+    /// Explores all of the shards using a heuristic, like `BinTrie::explore`.
+    ///
+    /// The heuristic chooses the shards as though their key bits were levels of a
+    /// trie, and then explores each chosen shard in turn.
+    ///
+    /// ```
+    /// # use bintrie::{TrieForest, HammingRadius};
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// let forest = TrieForest::from_items(2, 4, 0..16, lookup);
+    /// let mut found = forest.explore(HammingRadius::new(0b0101, 1)).collect::<Vec<u32>>();
+    /// found.sort();
+    /// assert_eq!(found, vec![0b0001, 0b0100, 0b0101, 0b0111, 0b1101]);
+    /// ```
+    pub fn explore<'a, H>(&'a self, heuristic: H) -> impl Iterator<Item = u32> + 'a
+    where
+        H: IntoHeuristic,
+        H::Heuristic: 'a,
+    {
+        let mut chosen = vec![];
+        let mut heuristic = heuristic.into_heuristic();
+        heuristic.begin();
+        self.choose(heuristic, 0, 0, &mut chosen);
+        let bits = self.bits;
+        chosen.into_iter().flat_map(move |(shard, heuristic)| {
+            self.shards[shard].explore(Shifted {
+                heuristic,
+                levels: bits,
+            })
+        })
+    }
+
+    /// Finds the non-empty shards in the order a heuristic chooses them.
+    fn choose<H>(&self, heuristic: H, level: u32, shard: usize, chosen: &mut Vec<(usize, H)>)
+    where
+        H: Heuristic,
+    {
+        if level == self.bits {
+            if !self.shards[shard].is_empty() {
+                chosen.push((shard, heuristic));
+            }
+            return;
+        }
+        for side in heuristic.iter(level) {
+            let mut heuristic = heuristic.clone();
+            heuristic.enter(side, level);
+            self.choose(heuristic, level + 1, shard << 1 | side.index(), chosen);
+        }
+    }
+
+    /// Joins the shards into a single trie with the whole key.
+    pub fn into_trie(self) -> BinTrie {
+        let mut level = self.shards;
+        while level.len() > 1 {
+            let mut shards = level.into_iter();
+            level = core::iter::from_fn(|| Some(BinTrie::join(shards.next()?, shards.next()?)))
+                .collect();
+        }
+        level.pop().unwrap()
+    }
+}
@@ -0,0 +1,73 @@
+use crate::explore::{accept_all, ExploreIter};
+use crate::range::RangeIter;
+use crate::{BinTrie, IntoHeuristic};
+use alloc::sync::Arc;
+use alloc::vec;
+use core::ops::Deref;
+
+/// A read-only trie that can be shared between threads and tasks.
+///
+/// Cloning a `FrozenTrie` is cheap since it only clones an `Arc`. Every method
+/// of `BinTrie` that only reads the trie is available through `Deref`, but `items`
+/// and `explore` are replaced with versions that hold their own reference to the
+/// trie. The iterators they return are `Send + 'static`, so they can be boxed and
+/// moved across threads or into task queues.
+///
+/// ```
+/// # use bintrie::{BinTrie, FilterHeuristic, Side};
+/// let mut trie = BinTrie::new();
+/// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+/// for n in 0..4 {
+///     trie.insert(n, |l| lookup(n, l), lookup);
+/// }
+/// let trie = trie.freeze();
+/// let items: Box<dyn Iterator<Item = u32> + Send> = Box::new(trie.items());
+/// let explore = trie.explore(FilterHeuristic(|s| s == Side::Left));
+/// let handle = std::thread::spawn(move || (items.collect::<Vec<u32>>(), explore.collect::<Vec<u32>>()));
+/// assert_eq!(handle.join().unwrap(), (vec![0, 2, 1, 3], vec![0]));
+/// ```
+#[derive(Clone, Debug)]
+pub struct FrozenTrie(Arc<BinTrie>);
+
+impl FrozenTrie {
+    /// Get an iterator over the items in the trie which owns a reference to the trie.
+    pub fn items(&self) -> impl Iterator<Item = u32> + Send + 'static {
+        RangeIter::all(self.0.clone())
+    }
+
+    /// Iterates over the trie like `BinTrie::explore` with an iterator that owns a reference to the trie.
+    pub fn explore<H>(&self, heuristic: H) -> impl Iterator<Item = u32> + Send + 'static
+    where
+        H: IntoHeuristic,
+        H::Heuristic: Send + 'static,
+        <H::Heuristic as crate::Heuristic>::Iter: Send + 'static,
+    {
+        ExploreIter::new(
+            self.0.clone(),
+            heuristic.into_heuristic(),
+            vec![],
+            accept_all as fn(u32) -> bool,
+        )
+    }
+}
+
+impl Deref for FrozenTrie {
+    type Target = BinTrie;
+
+    fn deref(&self) -> &BinTrie {
+        &self.0
+    }
+}
+
+impl From<BinTrie> for FrozenTrie {
+    fn from(trie: BinTrie) -> Self {
+        FrozenTrie(Arc::new(trie))
+    }
+}
+
+impl BinTrie {
+    /// Freezes the trie into a `FrozenTrie` that can be cheaply shared between threads.
+    pub fn freeze(self) -> FrozenTrie {
+        self.into()
+    }
+}
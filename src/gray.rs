@@ -0,0 +1,108 @@
+/// Turns integers into key bits in reflected Gray code.
+///
+/// Numbers are keyed by the Gray code of their `width` least significant bits,
+/// most significant bit first. The trie is therefore ordered like the Gray codes
+/// rather than like the numbers, so `items` yields `0, 1, 3, 2, 7, 6, 4, 5` for the
+/// numbers below `8` with a `width` of `3`.
+///
+/// In Gray code, two consecutive numbers differ in exactly one key bit, which is
+/// the bit that a binary increment would carry into. Consecutive numbers are thus
+/// always within a Hamming distance of `1`, so `within_distance` with a radius of `1`
+/// finds the numbers on either side of a query. How long a prefix they share still
+/// depends on where that bit is, just like in binary.
+///
+/// ```
+/// # use bintrie::{BinTrie, GrayKey};
+/// let gray = GrayKey::new(16);
+/// let mut trie = BinTrie::new_depth(16);
+/// let lookup = |n: u32, l: u32| gray.bit(u64::from(n), l);
+/// for n in (0..1000).map(|n| n * 7) {
+///     trie.insert(n, |l| lookup(n, l), lookup);
+/// }
+/// assert_eq!(trie.get(|l| gray.bit(693, l)), Some(693));
+/// // The path to an item can be turned back into the number.
+/// let path = (0..16).map(|l| gray.bit(693, l));
+/// assert_eq!(gray.decode_path(path), 693);
+/// // The trie is in Gray code order, and consecutive numbers are one key bit apart.
+/// let gray = GrayKey::new(3);
+/// let mut trie = BinTrie::new_depth(3);
+/// let lookup = |n: u32, l: u32| gray.bit(u64::from(n), l);
+/// for n in 0..8 {
+///     trie.insert(n, |l| lookup(n, l), lookup);
+/// }
+/// assert_eq!(trie.items().collect::<Vec<u32>>(), vec![0, 1, 3, 2, 7, 6, 4, 5]);
+/// let near = trie.within_distance(|l| gray.bit(5, l), 1, lookup);
+/// assert!(near.filter(|&(n, _)| n == 4 || n == 6).count() == 2);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct GrayKey {
+    width: u32,
+}
+
+impl GrayKey {
+    /// Makes an adaptor for numbers that are `width` bits wide, which may be at most `64`.
+    pub fn new(width: u32) -> Self {
+        assert!(width > 0 && width <= 64);
+        Self { width }
+    }
+
+    /// The number of key bits for each number, which is the depth the trie should have.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Encodes `value` in reflected Gray code.
+    ///
+    /// ```
+    /// # use bintrie::GrayKey;
+    /// assert_eq!(
+    ///     (0..8).map(GrayKey::encode).collect::<Vec<u64>>(),
+    ///     vec![0b000, 0b001, 0b011, 0b010, 0b110, 0b111, 0b101, 0b100],
+    /// );
+    /// ```
+    pub fn encode(value: u64) -> u64 {
+        value ^ value >> 1
+    }
+
+    /// Decodes `gray` from reflected Gray code, which undoes `encode`.
+    pub fn decode(gray: u64) -> u64 {
+        let mut value = gray;
+        let mut shift = 1;
+        while shift < 64 {
+            value ^= value >> shift;
+            shift <<= 1;
+        }
+        value
+    }
+
+    /// Gets the `n`th key bit for `value`.
+    ///
+    /// Bits past the `width` are `false`, so the trie may be deeper than the numbers are wide.
+    ///
+    /// ```
+    /// # use bintrie::GrayKey;
+    /// let gray = GrayKey::new(3);
+    /// let bits = |n| (0..4).map(|l| gray.bit(n, l)).collect::<Vec<bool>>();
+    /// assert_eq!(bits(2), vec![false, true, true, false]);
+    /// ```
+    pub fn bit(&self, value: u64, n: u32) -> bool {
+        n < self.width && Self::encode(value) >> (self.width - 1 - n) & 1 == 1
+    }
+
+    /// Reconstructs a number from the key bits along its path, such as those from `bit`.
+    ///
+    /// If the path is shorter than `width`, the missing bits are taken to be zero.
+    pub fn decode_path<I>(&self, path: I) -> u64
+    where
+        I: IntoIterator<Item = bool>,
+    {
+        let gray = path
+            .into_iter()
+            .take(self.width as usize)
+            .enumerate()
+            .fold(0, |gray, (n, bit)| {
+                gray | u64::from(bit) << (self.width - 1 - n as u32)
+            });
+        Self::decode(gray)
+    }
+}
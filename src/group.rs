@@ -0,0 +1,75 @@
+use crate::{BinTrie, HIGH};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Finds the child slots that hold all of the items of each `k` bit prefix.
+pub(crate) struct GroupIter<'a, F> {
+    trie: &'a BinTrie,
+    k: u32,
+    lookup: F,
+    /// Each node along with its depth, its prefix, and the next position to visit.
+    indices: Vec<(usize, u32, u64, usize)>,
+}
+
+impl<'a, F> GroupIter<'a, F>
+where
+    F: FnMut(u32, u32) -> bool,
+{
+    pub(crate) fn new(trie: &'a BinTrie, k: u32, lookup: F) -> Self {
+        Self {
+            trie,
+            k,
+            lookup,
+            indices: vec![(0, 0, 0, 0)],
+        }
+    }
+}
+
+impl<'a, F> Iterator for GroupIter<'a, F>
+where
+    F: FnMut(u32, u32) -> bool,
+{
+    /// The prefix and the slot that everything with that prefix is under.
+    ///
+    /// If the slot is `None` then everything in the trie shares the prefix.
+    type Item = (u64, Option<&'a u32>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.k == 0 {
+            // There is only one empty prefix, which everything shares.
+            return if self.indices.pop().is_some() && self.trie.items().next().is_some() {
+                Some((0, None))
+            } else {
+                None
+            };
+        }
+        loop {
+            let (index, depth, prefix, position) = self.indices.pop()?;
+            if position == 2 {
+                continue;
+            }
+            self.indices.push((index, depth, prefix, position + 1));
+            let slot = &self.trie.internals[index].0[position];
+            let prefix = prefix << 1 | position as u64;
+            match self.trie.slot_checked(slot) {
+                // Empty node
+                0 => {}
+                // Leaf node
+                n if n & HIGH != 0 => {
+                    // Complete the prefix using the rest of the item's key.
+                    let prefix = (depth + 1..self.k).fold(prefix, |prefix, l| {
+                        prefix << 1 | (self.lookup)(n & !HIGH, l) as u64
+                    });
+                    return Some((prefix, Some(slot)));
+                }
+                // Internal node
+                n => {
+                    if depth + 1 == self.k {
+                        return Some((prefix, Some(slot)));
+                    }
+                    self.indices.push((n as usize, depth + 1, prefix, 0));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use alloc::vec::Vec;
+use core::hash::{BuildHasher, Hash, Hasher};
+#[cfg(feature = "std")]
+use std::collections::hash_map::RandomState;
+
+/// Turns any hashable value into key bits using a pluggable hasher.
+///
+/// The hasher is chosen with `S`, so a DoS resistant hasher such as the default
+/// `RandomState` can be used for untrusted inputs, or a faster one for trusted
+/// inputs. Every 64 bits of the key come from hashing the value along with the
+/// seed and the index of those 64 bits, so keys can be as deep as needed. Two
+/// adaptors with different seeds produce unrelated keys, which is useful for
+/// multi-probe schemes with several tries.
+///
+/// ```
+/// # use bintrie::{BinTrie, HashedKey};
+/// use std::collections::hash_map::DefaultHasher;
+/// use std::hash::BuildHasherDefault;
+///
+/// let hashed = HashedKey::with_hasher(BuildHasherDefault::<DefaultHasher>::default()).with_seed(7);
+/// let words = ["apple", "banana", "cherry"];
+/// let mut trie = BinTrie::new_depth(64);
+/// let lookup = |n: u32, l: u32| hashed.bit(words[n as usize], l);
+/// for (n, word) in words.iter().enumerate() {
+///     let bits = hashed.bits(word, 64);
+///     trie.insert(n as u32, |l| bits.bit(l), lookup);
+/// }
+/// assert_eq!(trie.get(|l| hashed.bit("banana", l)), Some(1));
+/// ```
+#[derive(Clone, Debug)]
+#[cfg(feature = "std")]
+pub struct HashedKey<S = RandomState> {
+    build: S,
+    seed: u64,
+}
+
+/// Turns any hashable value into key bits using a pluggable hasher.
+///
+/// Without `std` there is no default hasher, so one must be given with `with_hasher`.
+#[derive(Clone, Debug)]
+#[cfg(not(feature = "std"))]
+pub struct HashedKey<S> {
+    build: S,
+    seed: u64,
+}
+
+#[cfg(feature = "std")]
+impl HashedKey<RandomState> {
+    /// Makes an adaptor with a randomly keyed `RandomState` hasher.
+    pub fn new() -> Self {
+        Self::with_hasher(RandomState::new())
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for HashedKey<RandomState> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> HashedKey<S>
+where
+    S: BuildHasher,
+{
+    /// Makes an adaptor that hashes with hashers built by `build`.
+    pub fn with_hasher(build: S) -> Self {
+        Self { build, seed: 0 }
+    }
+
+    /// Sets the seed that is hashed along with every value.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Hashes the `word`th 64 bits of the key for `value`.
+    fn word<T>(&self, value: &T, word: u32) -> u64
+    where
+        T: Hash + ?Sized,
+    {
+        let mut hasher = self.build.build_hasher();
+        self.seed.hash(&mut hasher);
+        word.hash(&mut hasher);
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Gets the `n`th bit of the key for `value`.
+    ///
+    /// This hashes the value every time it is called, so prefer `bits`
+    /// when many bits of the same key are needed.
+    pub fn bit<T>(&self, value: &T, n: u32) -> bool
+    where
+        T: Hash + ?Sized,
+    {
+        self.word(value, n / 64) >> (n % 64) & 1 == 1
+    }
+
+    /// Computes the first `depth` bits of the key for `value` all at once.
+    pub fn bits<T>(&self, value: &T, depth: u32) -> HashedBits
+    where
+        T: Hash + ?Sized,
+    {
+        HashedBits {
+            words: (0..depth.div_ceil(64))
+                .map(|word| self.word(value, word))
+                .collect(),
+        }
+    }
+}
+
+/// The precomputed bits of a key from `HashedKey::bits`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HashedBits {
+    words: Vec<u64>,
+}
+
+impl HashedBits {
+    /// Gets the `n`th bit of the key.
+    #[inline(always)]
+    pub fn bit(&self, n: u32) -> bool {
+        self.words[n as usize / 64] >> (n % 64) & 1 == 1
+    }
+}
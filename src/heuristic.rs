@@ -1,3 +1,10 @@
+pub mod testing;
+
+use crate::Side;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
 /// The `Heuristic` chooses which side to explore next.
 ///
 /// This is not useful for finding perfect nearest neighbors because
@@ -10,14 +17,235 @@
 ///
 /// This is cloned right before entering a `side`, so it is expected that
 /// `enter` updates the state of the `Heuristic`.
+///
+/// Both methods are passed the `level` of the node being explored, where the root
+/// is at level `0`, so a heuristic does not need to count the sides it entered.
 pub trait Heuristic: Clone {
-    type Iter: Iterator<Item = bool>;
+    type Iter: Iterator<Item = Side>;
+
+    /// This is passed the `side` taken from the node at `level`.
+    fn enter(&mut self, side: Side, level: u32);
+
+    /// Returns an iterator over the sides of the node at `level` to explore in the
+    /// order they should be explored.
+    fn iter(&self, level: u32) -> Self::Iter;
+
+    /// Prepares the heuristic for a new exploration, before the root is explored.
+    ///
+    /// Heuristics that share state between the clones made while exploring reset
+    /// it here, so that the same heuristic can be used for more than one exploration.
+    /// Heuristics that wrap others must pass this on to them.
+    #[inline(always)]
+    fn begin(&mut self) {}
+
+    /// Plans the heuristic for a trie of the given `depth` before exploring with it.
+    ///
+    /// A heuristic whose choices only depend on the level can be flattened into a
+    /// single `CompiledHeuristic` table, so that a composition of such heuristics
+    /// costs one lookup per node instead of a call into every heuristic it is made of.
+    /// Heuristics that can't be planned are kept as they are, which is the default.
+    ///
+    /// ```
+    /// # use bintrie::{BinTrie, CompiledHeuristic, Heuristic, MaskedHeuristic, Optimized};
+    /// let mut trie = BinTrie::new_depth(4);
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// for n in 0..16 {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// // Keys whose bits at levels 0 and 1, least significant first, are `0` then `1`,
+    /// // within a distance of 1 of `0b1110`.
+    /// let prefix = MaskedHeuristic::new(|l| l == 1, |l| l < 2);
+    /// let radius = CompiledHeuristic::hamming(|l| lookup(0b1110, l), 4, 1);
+    /// let planned = prefix.and(radius).max_depth(4).optimize(trie.depth());
+    /// assert!(matches!(planned, Optimized::Table(_)));
+    /// let mut found = trie.explore(planned).collect::<Vec<u32>>();
+    /// found.sort();
+    /// assert_eq!(found, vec![0b0110, 0b1010, 0b1110]);
+    /// ```
+    fn optimize(self, depth: u32) -> Optimized<Self>
+    where
+        Self: Sized,
+    {
+        let _ = depth;
+        Optimized::Dynamic(self)
+    }
+
+    /// Only explores the sides that both this heuristic and `other` would explore.
+    ///
+    /// Sides are explored in the order of this heuristic, and each at most once.
+    ///
+    /// ```
+    /// # use bintrie::{BinTrie, HammingRadius, Heuristic, MaskedHeuristic};
+    /// let mut trie = BinTrie::new_depth(4);
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// for n in 0..16 {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// // Keys whose bits at levels 0 and 1, least significant first, are `0` then `1`,
+    /// // within a distance of 1 of `0b1110`.
+    /// let prefix = MaskedHeuristic::new(|l| l == 1, |l| l < 2);
+    /// let heuristic = prefix.and(HammingRadius::new(0b1110, 1));
+    /// let mut found = trie.explore(heuristic).collect::<Vec<u32>>();
+    /// found.sort();
+    /// assert_eq!(found, vec![0b0110, 0b1010, 0b1110]);
+    /// ```
+    fn and<H>(self, other: H) -> And<Self, H>
+    where
+        Self: Sized,
+        H: Heuristic,
+    {
+        And(self, other)
+    }
+
+    /// Explores the sides that either this heuristic or `other` would explore.
+    ///
+    /// Sides are explored in the order of this heuristic followed by any others
+    /// from `other`, and each at most once. Once a heuristic declines a side, it
+    /// has no say beneath that side.
+    ///
+    /// ```
+    /// # use bintrie::{BinTrie, HammingRadius, Heuristic};
+    /// let mut trie = BinTrie::new_depth(4);
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// for n in 0..16 {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// let heuristic = HammingRadius::new(0b0000, 0).or(HammingRadius::new(0b1111, 0));
+    /// assert_eq!(trie.explore(heuristic).collect::<Vec<u32>>(), vec![0b0000, 0b1111]);
+    /// ```
+    fn or<H>(self, other: H) -> Or<Self, H>
+    where
+        Self: Sized,
+        H: Heuristic,
+    {
+        Or(Some(self), Some(other))
+    }
+
+    /// Explores like this heuristic, but never enters internal nodes below `levels` levels.
+    ///
+    /// The leaves and terminated items of the nodes on the last allowed level are
+    /// still found.
+    ///
+    /// ```
+    /// # use bintrie::{BinTrie, HammingNearestFirst, Heuristic};
+    /// let mut trie = BinTrie::new_depth(4);
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// trie.insert(0b0001, |l| lookup(0b0001, l), lookup);
+    /// trie.insert(0b0011, |l| lookup(0b0011, l), lookup);
+    /// trie.insert(0b0000, |l| lookup(0b0000, l), lookup);
+    /// let shallow = HammingNearestFirst::new(0).max_depth(1);
+    /// assert_eq!(trie.explore(shallow).collect::<Vec<u32>>(), vec![0b0000]);
+    /// ```
+    fn max_depth(self, levels: u32) -> MaxDepth<Self>
+    where
+        Self: Sized,
+    {
+        MaxDepth {
+            heuristic: self,
+            levels,
+        }
+    }
+
+    /// Explores like this heuristic, but stops once `nodes` internal nodes were entered.
+    ///
+    /// The count is shared between every clone made while exploring, including those
+    /// on other threads, so it limits the exploration as a whole. It starts over with
+    /// every exploration, so the heuristic can be reused. Once the limit is reached,
+    /// no more sides are explored at all, including those with leaves.
+    ///
+    /// ```
+    /// # use bintrie::{BinTrie, HammingNearestFirst, Heuristic};
+    /// let mut trie = BinTrie::new_depth(8);
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// for n in 0..256 {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// // The path to the first leaves enters 7 internal nodes, and then the limit
+    /// // is reached when entering the next.
+    /// let limited = HammingNearestFirst::new(0).limit_nodes(8);
+    /// assert_eq!(trie.explore(limited.clone()).collect::<Vec<u32>>(), vec![0, 128]);
+    /// assert_eq!(trie.explore(limited.clone()).collect::<Vec<u32>>(), vec![0, 128]);
+    /// // The count can be shared between threads.
+    /// let frozen = trie.freeze();
+    /// assert_eq!(frozen.explore(limited).collect::<Vec<u32>>(), vec![0, 128]);
+    /// ```
+    fn limit_nodes(self, nodes: usize) -> LimitNodes<Self>
+    where
+        Self: Sized,
+    {
+        LimitNodes {
+            heuristic: self,
+            entered: Arc::new(AtomicUsize::new(0)),
+            nodes,
+        }
+    }
+}
+
+/// A heuristic after it was planned by `Heuristic::optimize`.
+#[derive(Clone, Debug)]
+pub enum Optimized<H> {
+    /// The heuristic was flattened into a table of branch costs.
+    Table(CompiledHeuristic),
+    /// The heuristic could not be planned and is used as it is.
+    Dynamic(H),
+}
+
+impl<H> Heuristic for Optimized<H>
+where
+    H: Heuristic,
+{
+    type Iter = OptimizedIter<H::Iter>;
+
+    #[inline(always)]
+    fn enter(&mut self, side: Side, level: u32) {
+        match self {
+            Optimized::Table(table) => table.enter(side, level),
+            Optimized::Dynamic(heuristic) => heuristic.enter(side, level),
+        }
+    }
+
+    #[inline(always)]
+    fn iter(&self, level: u32) -> Self::Iter {
+        match self {
+            Optimized::Table(table) => OptimizedIter::Table(table.iter(level)),
+            Optimized::Dynamic(heuristic) => OptimizedIter::Dynamic(heuristic.iter(level)),
+        }
+    }
 
-    /// This is passed the `side`.
-    fn enter(&mut self, side: bool);
+    #[inline(always)]
+    fn begin(&mut self) {
+        if let Optimized::Dynamic(heuristic) = self {
+            heuristic.begin();
+        }
+    }
 
-    /// Must return an iterator which returns values below `16`, otherwise panics.
-    fn iter(&self) -> Self::Iter;
+    fn optimize(self, _: u32) -> Optimized<Self> {
+        match self {
+            Optimized::Table(table) => Optimized::Table(table),
+            dynamic => Optimized::Dynamic(dynamic),
+        }
+    }
+}
+
+#[doc(hidden)]
+pub enum OptimizedIter<I> {
+    Table(<CompiledHeuristic as Heuristic>::Iter),
+    Dynamic(I),
+}
+
+impl<I> Iterator for OptimizedIter<I>
+where
+    I: Iterator<Item = Side>,
+{
+    type Item = Side;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            OptimizedIter::Table(iter) => iter.next(),
+            OptimizedIter::Dynamic(iter) => iter.next(),
+        }
+    }
 }
 
 pub trait IntoHeuristic {
@@ -40,7 +268,7 @@ where
 
 /// Chooses whether to enter a path or not.
 ///
-/// Wrap a type with the bound `F: FnMut(bool) -> bool + Clone` and
+/// Wrap a type with the bound `F: FnMut(Side) -> bool + Clone` and
 /// this will implement `Heuristic`. The function will be cloned
 /// internally so that from the function's point of view it is being called
 /// in the order it descends in. It is passed the side that is being entered
@@ -52,20 +280,20 @@ pub struct FilterHeuristic<F>(pub F);
 
 impl<F> Heuristic for FilterHeuristic<F>
 where
-    F: FnMut(bool) -> bool + Clone,
+    F: FnMut(Side) -> bool + Clone,
 {
     type Iter = FilterHeuristicIter<F>;
 
     #[inline(always)]
-    fn enter(&mut self, side: bool) {
+    fn enter(&mut self, side: Side, _: u32) {
         self.0(side);
     }
 
     #[inline(always)]
-    fn iter(&self) -> Self::Iter {
+    fn iter(&self, _: u32) -> Self::Iter {
         FilterHeuristicIter {
             f: self.0.clone(),
-            iter: [false, true].iter(),
+            iter: Side::BOTH.iter(),
         }
     }
 }
@@ -73,14 +301,14 @@ where
 #[doc(hidden)]
 pub struct FilterHeuristicIter<F> {
     f: F,
-    iter: std::slice::Iter<'static, bool>,
+    iter: core::slice::Iter<'static, Side>,
 }
 
 impl<F> Iterator for FilterHeuristicIter<F>
 where
-    F: FnMut(bool) -> bool + Clone,
+    F: FnMut(Side) -> bool + Clone,
 {
-    type Item = bool;
+    type Item = Side;
 
     #[inline(always)]
     fn next(&mut self) -> Option<Self::Item> {
@@ -91,7 +319,7 @@ where
 
 /// Chooses paths to search down.
 ///
-/// Wrap a type with the bound `F: FnMut(bool) -> bool + Clone` and
+/// Wrap a type with the bound `F: FnMut(Side) -> Side + Clone` and
 /// this will implement `Heuristic`. The second argument has to be the first
 /// choice. The function will be cloned internally so that from the function's
 /// point of view it is being called in the order it descends in. It is passed
@@ -105,25 +333,552 @@ where
 /// differed between two matches (more significant bits differing would throw
 /// it out).
 #[derive(Clone)]
-pub struct SearchHeuristic<F>(pub F, pub bool);
+pub struct SearchHeuristic<F>(pub F, pub Side);
 
 impl<F> Heuristic for SearchHeuristic<F>
 where
-    F: FnMut(bool) -> bool + Clone,
+    F: FnMut(Side) -> Side + Clone,
 {
-    type Iter = std::iter::Cloned<std::slice::Iter<'static, bool>>;
+    type Iter = core::iter::Cloned<core::slice::Iter<'static, Side>>;
 
     #[inline(always)]
-    fn enter(&mut self, side: bool) {
+    fn enter(&mut self, side: Side, _: u32) {
         self.1 = self.0(side);
     }
 
     #[inline(always)]
-    fn iter(&self) -> Self::Iter {
-        if self.1 {
-            [true, false].iter().cloned()
+    fn iter(&self, _: u32) -> Self::Iter {
+        match self.1 {
+            Side::Left => [Side::Left, Side::Right].iter().cloned(),
+            Side::Right => [Side::Right, Side::Left].iter().cloned(),
+        }
+    }
+}
+
+/// A heuristic driven entirely by a precomputed table of branch costs.
+///
+/// Every level of the trie has a cost for entering each side. A side is only
+/// explored if its cost fits in the remaining budget, and the cheaper side is
+/// always explored first. Because every decision is a table lookup and a
+/// subtraction, this avoids evaluating closures at every node.
+///
+/// The table is shared between clones, so cloning while descending is cheap.
+///
+/// ```
+/// # use bintrie::{BinTrie, CompiledHeuristic};
+/// let mut trie = BinTrie::new_depth(4);
+/// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+/// for n in 0..16 {
+///     trie.insert(n, |l| lookup(n, l), lookup);
+/// }
+/// let heuristic = CompiledHeuristic::hamming(|l| lookup(0b0101, l), 4, 1);
+/// let mut found = trie.explore(heuristic).collect::<Vec<u32>>();
+/// found.sort();
+/// assert_eq!(found, vec![0b0001, 0b0100, 0b0101, 0b0111, 0b1101]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct CompiledHeuristic {
+    costs: Arc<[[u32; 2]]>,
+    budget: u32,
+}
+
+impl CompiledHeuristic {
+    /// Creates a heuristic from the cost of each side at every level and a total `budget`.
+    ///
+    /// Levels beyond the end of `costs` are never explored, and neither is a side
+    /// with a cost of `u32::MAX`.
+    pub fn new(costs: Vec<[u32; 2]>, budget: u32) -> Self {
+        Self {
+            costs: costs.into(),
+            budget,
+        }
+    }
+
+    /// Compiles a search for keys within Hamming distance `radius` of `key`.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the query key.
+    ///
+    /// The first `depth` bits of the key are used to build the table.
+    pub fn hamming<K>(mut key: K, depth: u32, radius: u32) -> Self
+    where
+        K: FnMut(u32) -> bool,
+    {
+        Self::new(
+            (0..depth)
+                .map(|i| if key(i) { [1, 0] } else { [0, 1] })
+                .collect(),
+            radius,
+        )
+    }
+
+    /// The budget remaining at the current level.
+    pub fn budget(&self) -> u32 {
+        self.budget
+    }
+
+    /// Merges two tables into one that only explores what both of them would explore.
+    ///
+    /// This is only possible when at least one of the tables has no budget left, so
+    /// that it acts as a constraint that forbids every side with a cost. Sides it
+    /// forbids are forbidden in the merged table, which otherwise keeps the costs
+    /// and budget of the other table. Returns `None` if both tables have a budget.
+    pub fn intersect(&self, other: &Self) -> Option<Self> {
+        let (constraint, table) = if self.budget == 0 {
+            (self, other)
+        } else if other.budget == 0 {
+            (other, self)
         } else {
-            [false, true].iter().cloned()
+            return None;
+        };
+        let costs = constraint
+            .costs
+            .iter()
+            .zip(table.costs.iter())
+            .map(|(allowed, costs)| {
+                let mut costs = *costs;
+                for (cost, &allowed) in costs.iter_mut().zip(allowed) {
+                    if allowed != 0 {
+                        *cost = u32::MAX;
+                    }
+                }
+                costs
+            })
+            .collect();
+        Some(Self::new(costs, table.budget))
+    }
+}
+
+impl Heuristic for CompiledHeuristic {
+    type Iter = core::iter::Take<core::array::IntoIter<Side, 2>>;
+
+    #[inline(always)]
+    fn enter(&mut self, side: Side, level: u32) {
+        self.budget -= self.costs[level as usize][side.index()];
+    }
+
+    #[inline(always)]
+    fn iter(&self, level: u32) -> Self::Iter {
+        let (first, second) = match self.costs.get(level as usize) {
+            Some(&[left, right]) if left <= right => ((Side::Left, left), (Side::Right, right)),
+            Some(&[left, right]) => ((Side::Right, right), (Side::Left, left)),
+            None => return IntoIterator::into_iter([Side::Left, Side::Right]).take(0),
+        };
+        let allowed = [first, second]
+            .iter()
+            .filter(|&&(_, cost)| cost <= self.budget && cost != u32::MAX)
+            .count();
+        IntoIterator::into_iter([first.0, second.0]).take(allowed)
+    }
+
+    fn optimize(self, _: u32) -> Optimized<Self> {
+        Optimized::Table(self)
+    }
+}
+
+/// Only explores paths that match a key on the bits selected by a mask.
+///
+/// `K(n)` - A function that provides the `n`th bit for the key.
+/// `M(n)` - A function that returns `false` if the `n`th bit of the key
+///    is a don't-care bit, in which case both sides are explored.
+///
+/// Sides that do not match a bit that is cared about are pruned.
+#[derive(Clone)]
+pub struct MaskedHeuristic<K, M> {
+    key: K,
+    mask: M,
+}
+
+impl<K, M> MaskedHeuristic<K, M>
+where
+    K: Fn(u32) -> bool + Clone,
+    M: Fn(u32) -> bool + Clone,
+{
+    /// Makes a heuristic that matches `key` on the bits selected by `mask`.
+    pub fn new(key: K, mask: M) -> Self {
+        Self { key, mask }
+    }
+}
+
+impl<K, M> Heuristic for MaskedHeuristic<K, M>
+where
+    K: Fn(u32) -> bool + Clone,
+    M: Fn(u32) -> bool + Clone,
+{
+    type Iter = core::iter::Take<core::array::IntoIter<Side, 2>>;
+
+    #[inline(always)]
+    fn enter(&mut self, _: Side, _: u32) {}
+
+    #[inline(always)]
+    fn iter(&self, level: u32) -> Self::Iter {
+        let side = Side::from((self.key)(level));
+        let sides = if (self.mask)(level) { 1 } else { 2 };
+        IntoIterator::into_iter([side, side.opposite()]).take(sides)
+    }
+
+    fn optimize(self, depth: u32) -> Optimized<Self> {
+        // Every choice depends only on the level, so the whole heuristic fits in a table.
+        let costs = (0..depth)
+            .map(|level| {
+                let mut costs = [0, 0];
+                if (self.mask)(level) {
+                    costs[Side::from((self.key)(level)).opposite().index()] = u32::MAX;
+                }
+                costs
+            })
+            .collect();
+        Optimized::Table(CompiledHeuristic::new(costs, 0))
+    }
+}
+
+/// Only explores keys within a Hamming distance of `radius` of an integer `query`.
+///
+/// Bits are numbered from the least significant bit up like `KeyBits` for integers,
+/// and bits past the end of the query are `0`. The side matching the query is always
+/// explored first, and every mismatch spends one from the remaining radius.
+///
+/// ```
+/// # use bintrie::{BinTrie, HammingRadius, Heuristic};
+/// let mut trie = BinTrie::new_depth(4);
+/// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+/// for n in 0..16 {
+///     trie.insert(n, |l| lookup(n, l), lookup);
+/// }
+/// let mut found = trie.explore(HammingRadius::new(0b0101, 1)).collect::<Vec<u32>>();
+/// found.sort();
+/// assert_eq!(found, vec![0b0001, 0b0100, 0b0101, 0b0111, 0b1101]);
+/// // It can also be planned into a table.
+/// let planned = HammingRadius::new(0b0101, 1).optimize(trie.depth());
+/// let mut planned = trie.explore(planned).collect::<Vec<u32>>();
+/// planned.sort();
+/// assert_eq!(planned, found);
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct HammingRadius {
+    query: u128,
+    radius: u32,
+}
+
+impl HammingRadius {
+    /// Searches for keys within `radius` bits of `query`.
+    pub fn new(query: u128, radius: u32) -> Self {
+        Self { query, radius }
+    }
+
+    /// The number of mismatched bits that may still be entered.
+    pub fn remaining(&self) -> u32 {
+        self.radius
+    }
+}
+
+impl Heuristic for HammingRadius {
+    type Iter = core::iter::Take<core::array::IntoIter<Side, 2>>;
+
+    #[inline(always)]
+    fn enter(&mut self, side: Side, level: u32) {
+        if side != query_side(self.query, level) {
+            self.radius -= 1;
+        }
+    }
+
+    #[inline(always)]
+    fn iter(&self, level: u32) -> Self::Iter {
+        let side = query_side(self.query, level);
+        let sides = if self.radius == 0 { 1 } else { 2 };
+        IntoIterator::into_iter([side, side.opposite()]).take(sides)
+    }
+
+    fn optimize(self, depth: u32) -> Optimized<Self> {
+        let query = self.query;
+        let table =
+            CompiledHeuristic::hamming(|l| bool::from(query_side(query, l)), depth, self.radius);
+        Optimized::Table(table)
+    }
+}
+
+/// Explores every key, always taking the side that matches an integer `query` first.
+///
+/// Bits are numbered like `HammingRadius`. The first items found are near the
+/// query, but since a mismatch high in the trie is explored before one lower down,
+/// the items are only approximately ordered by distance. Combine it with `take`
+/// for an approximate nearest neighbor search, or use `BinTrie::knn` for exact results.
+///
+/// ```
+/// # use bintrie::{BinTrie, HammingNearestFirst};
+/// let mut trie = BinTrie::new_depth(4);
+/// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+/// for n in 0..16 {
+///     trie.insert(n, |l| lookup(n, l), lookup);
+/// }
+/// let found = trie.explore(HammingNearestFirst::new(0b0101)).collect::<Vec<u32>>();
+/// assert_eq!(found.len(), 16);
+/// assert_eq!(found[..2], [0b0101, 0b1101]);
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct HammingNearestFirst {
+    query: u128,
+}
+
+impl HammingNearestFirst {
+    /// Searches outwards from `query`.
+    pub fn new(query: u128) -> Self {
+        Self { query }
+    }
+}
+
+impl Heuristic for HammingNearestFirst {
+    type Iter = core::array::IntoIter<Side, 2>;
+
+    #[inline(always)]
+    fn enter(&mut self, _: Side, _: u32) {}
+
+    #[inline(always)]
+    fn iter(&self, level: u32) -> Self::Iter {
+        let side = query_side(self.query, level);
+        IntoIterator::into_iter([side, side.opposite()])
+    }
+}
+
+/// Gets the side of bit `level` of an integer query.
+#[inline(always)]
+fn query_side(query: u128, level: u32) -> Side {
+    Side::from(query.checked_shr(level).unwrap_or(0) & 1 == 1)
+}
+
+/// Scores each branch so that exploration can go best-first across the whole trie.
+///
+/// This is used by `BinTrie::explore_scored`, which always continues with the
+/// branch that has the lowest total score of the sides entered to reach it, no
+/// matter where in the trie that branch is. Like `Heuristic`, this is cloned right
+/// before entering a side, and `enter` updates its state.
+pub trait ScoredHeuristic: Clone {
+    /// Scores entering `side` of the node at `level`, or returns `None` if it shouldn't
+    /// be explored.
+    ///
+    /// Lower scores are explored first.
+    fn score(&self, side: Side, level: u32) -> Option<u32>;
+
+    /// This is passed the `side` that is entered from the node at `level`.
+    fn enter(&mut self, side: Side, level: u32);
+}
+
+/// The sides chosen by a combination of heuristics, each at most once.
+#[inline(always)]
+fn chosen<I>(sides: I, allowed: &mut [bool; 2]) -> core::iter::Take<core::array::IntoIter<Side, 2>>
+where
+    I: Iterator<Item = Side>,
+{
+    let mut chosen = [Side::Left; 2];
+    let mut len = 0;
+    for side in sides {
+        if core::mem::take(&mut allowed[side.index()]) {
+            chosen[len] = side;
+            len += 1;
+        }
+    }
+    IntoIterator::into_iter(chosen).take(len)
+}
+
+/// The sides a heuristic would explore at `level`.
+#[inline(always)]
+fn explored<H>(heuristic: &H, level: u32) -> [bool; 2]
+where
+    H: Heuristic,
+{
+    let mut explored = [false; 2];
+    for side in heuristic.iter(level) {
+        explored[side.index()] = true;
+    }
+    explored
+}
+
+/// Created by `Heuristic::and`.
+#[derive(Clone, Debug)]
+pub struct And<A, B>(A, B);
+
+impl<A, B> Heuristic for And<A, B>
+where
+    A: Heuristic,
+    B: Heuristic,
+{
+    type Iter = core::iter::Take<core::array::IntoIter<Side, 2>>;
+
+    #[inline(always)]
+    fn enter(&mut self, side: Side, level: u32) {
+        self.0.enter(side, level);
+        self.1.enter(side, level);
+    }
+
+    #[inline(always)]
+    fn iter(&self, level: u32) -> Self::Iter {
+        chosen(self.0.iter(level), &mut explored(&self.1, level))
+    }
+
+    #[inline(always)]
+    fn begin(&mut self) {
+        self.0.begin();
+        self.1.begin();
+    }
+
+    fn optimize(self, depth: u32) -> Optimized<Self> {
+        let tables = (
+            self.0.clone().optimize(depth),
+            self.1.clone().optimize(depth),
+        );
+        if let (Optimized::Table(a), Optimized::Table(b)) = tables {
+            if let Some(table) = a.intersect(&b) {
+                return Optimized::Table(table);
+            }
+        }
+        Optimized::Dynamic(self)
+    }
+}
+
+/// Created by `Heuristic::or`.
+///
+/// A heuristic is dropped once it declines a side that was entered.
+#[derive(Clone, Debug)]
+pub struct Or<A, B>(Option<A>, Option<B>);
+
+impl<A, B> Heuristic for Or<A, B>
+where
+    A: Heuristic,
+    B: Heuristic,
+{
+    type Iter = core::iter::Take<core::array::IntoIter<Side, 2>>;
+
+    #[inline(always)]
+    fn enter(&mut self, side: Side, level: u32) {
+        if let Some(a) = &mut self.0 {
+            if explored(a, level)[side.index()] {
+                a.enter(side, level);
+            } else {
+                self.0 = None;
+            }
+        }
+        if let Some(b) = &mut self.1 {
+            if explored(b, level)[side.index()] {
+                b.enter(side, level);
+            } else {
+                self.1 = None;
+            }
+        }
+    }
+
+    #[inline(always)]
+    fn iter(&self, level: u32) -> Self::Iter {
+        let a = self.0.iter().flat_map(|a| a.iter(level));
+        let b = self.1.iter().flat_map(|b| b.iter(level));
+        chosen(a.chain(b), &mut [true; 2])
+    }
+
+    #[inline(always)]
+    fn begin(&mut self) {
+        self.0.iter_mut().for_each(Heuristic::begin);
+        self.1.iter_mut().for_each(Heuristic::begin);
+    }
+}
+
+/// Created by `Heuristic::max_depth`.
+#[derive(Clone, Debug)]
+pub struct MaxDepth<H> {
+    heuristic: H,
+    levels: u32,
+}
+
+impl<H> Heuristic for MaxDepth<H>
+where
+    H: Heuristic,
+{
+    type Iter = core::iter::Take<H::Iter>;
+
+    #[inline(always)]
+    fn enter(&mut self, side: Side, level: u32) {
+        self.heuristic.enter(side, level);
+    }
+
+    #[inline(always)]
+    fn iter(&self, level: u32) -> Self::Iter {
+        let sides = if level < self.levels { usize::MAX } else { 0 };
+        self.heuristic.iter(level).take(sides)
+    }
+
+    #[inline(always)]
+    fn begin(&mut self) {
+        self.heuristic.begin();
+    }
+
+    fn optimize(self, depth: u32) -> Optimized<Self> {
+        match self.heuristic.clone().optimize(depth) {
+            Optimized::Table(mut table) => {
+                // Levels beyond the end of the table are never explored.
+                let levels = (self.levels as usize).min(table.costs.len());
+                table.costs = table.costs[..levels].into();
+                Optimized::Table(table)
+            }
+            Optimized::Dynamic(_) => Optimized::Dynamic(self),
+        }
+    }
+}
+
+/// Created by `Heuristic::limit_nodes`.
+#[derive(Clone, Debug)]
+pub struct LimitNodes<H> {
+    heuristic: H,
+    /// The internal nodes entered by every clone since the exploration began.
+    entered: Arc<AtomicUsize>,
+    nodes: usize,
+}
+
+impl<H> Heuristic for LimitNodes<H>
+where
+    H: Heuristic,
+{
+    type Iter = LimitNodesIter<H::Iter>;
+
+    #[inline(always)]
+    fn enter(&mut self, side: Side, level: u32) {
+        self.entered.fetch_add(1, Ordering::Relaxed);
+        self.heuristic.enter(side, level);
+    }
+
+    #[inline(always)]
+    fn iter(&self, level: u32) -> Self::Iter {
+        LimitNodesIter {
+            iter: self.heuristic.iter(level),
+            entered: self.entered.clone(),
+            nodes: self.nodes,
+        }
+    }
+
+    #[inline(always)]
+    fn begin(&mut self) {
+        // Clones from earlier explorations keep counting on their own.
+        self.entered = Arc::new(AtomicUsize::new(0));
+        self.heuristic.begin();
+    }
+}
+
+#[doc(hidden)]
+pub struct LimitNodesIter<I> {
+    iter: I,
+    entered: Arc<AtomicUsize>,
+    nodes: usize,
+}
+
+impl<I> Iterator for LimitNodesIter<I>
+where
+    I: Iterator<Item = Side>,
+{
+    type Item = Side;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Side> {
+        // The limit is checked for every side, since other nodes may have been entered
+        // since this node was.
+        if self.entered.load(Ordering::Relaxed) >= self.nodes {
+            return None;
         }
+        self.iter.next()
     }
 }
@@ -0,0 +1,110 @@
+//! Helpers for testing `Heuristic` implementations on small, hand-written tries.
+//!
+//! Paths are written as strings of `0` and `1`, where the `n`th character is
+//! the `n`th bit of the key and `0` is `Side::Left`.
+//!
+//! ```
+//! # use bintrie::{FilterHeuristic, Side};
+//! use bintrie::heuristic::testing::{assert_visits, scripted_trie};
+//! let trie = scripted_trie(&[("000", 0), ("001", 1), ("011", 3), ("110", 6)]);
+//! assert_visits(
+//!     &trie,
+//!     FilterHeuristic(|s| s == Side::Left),
+//!     &["0", "00", "000=0"],
+//! );
+//! assert_visits(
+//!     &trie,
+//!     FilterHeuristic(|_| true),
+//!     &["0", "00", "000=0", "001=1", "01=3", "1=6"],
+//! );
+//! ```
+
+use crate::{BinTrie, IntoHeuristic, Side, VisitEvent};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Builds a trie holding each item at the key written as its path.
+///
+/// Every path must have the same length, which becomes the depth of the trie.
+pub fn scripted_trie(entries: &[(&str, u32)]) -> BinTrie {
+    let keys: Vec<(u32, Vec<bool>)> = entries
+        .iter()
+        .map(|&(path, item)| (item, parse_path(path)))
+        .collect();
+    let depth = keys.first().map_or(1, |(_, key)| key.len() as u32);
+    assert!(
+        keys.iter().all(|(_, key)| key.len() as u32 == depth),
+        "every path must have the same length"
+    );
+    let lookup = |item: u32, n: u32| {
+        keys.iter()
+            .find(|&&(other, _)| other == item)
+            .expect("the item was inserted from the table")
+            .1[n as usize]
+    };
+    let mut trie = BinTrie::new_depth(depth);
+    for (item, key) in &keys {
+        trie.insert(*item, |n| key[n as usize], lookup);
+    }
+    trie
+}
+
+/// Gets every node the `heuristic` visits while exploring, in the order they are visited.
+///
+/// An internal node that is entered is written as its path. A leaf that is yielded
+/// is written as its path followed by `=` and the item, and so is an item whose key
+/// ends at an internal node. Note that a leaf is stored as soon as its path no longer
+/// overlaps any other key, so its path may be shorter than its key.
+pub fn visits<H>(trie: &BinTrie, heuristic: H) -> Vec<String>
+where
+    H: IntoHeuristic,
+{
+    let mut path = String::new();
+    let mut visits = vec![];
+    for event in trie.explore_trace(heuristic) {
+        match event {
+            VisitEvent::Enter { depth, side } => {
+                path.truncate(depth as usize);
+                path.push(side_char(side));
+                visits.push(path.clone());
+            }
+            VisitEvent::Leaf { item, depth, side } => {
+                path.truncate(depth as usize);
+                visits.push(format!("{}{}={}", path, side_char(side), item));
+            }
+            VisitEvent::Terminal { item, depth } => {
+                path.truncate(depth as usize);
+                visits.push(format!("{}={}", path, item));
+            }
+            VisitEvent::Prune { .. } => {}
+        }
+    }
+    visits
+}
+
+/// Asserts that the `heuristic` visits exactly the `expected` nodes in order, as written by `visits`.
+pub fn assert_visits<H>(trie: &BinTrie, heuristic: H, expected: &[&str])
+where
+    H: IntoHeuristic,
+{
+    assert_eq!(visits(trie, heuristic), expected);
+}
+
+fn parse_path(path: &str) -> Vec<bool> {
+    path.chars()
+        .map(|c| match c {
+            '0' => false,
+            '1' => true,
+            _ => panic!("paths may only contain 0 and 1, but found {:?}", c),
+        })
+        .collect()
+}
+
+fn side_char(side: Side) -> char {
+    match side {
+        Side::Left => '0',
+        Side::Right => '1',
+    }
+}
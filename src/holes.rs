@@ -0,0 +1,183 @@
+use crate::{BinTrie, HIGH};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+/// Work waiting to be done by `HolesIter`, in the order it will be done.
+enum Pending {
+    /// An internal node along with its depth and the prefix leading to it.
+    Node(usize, u32, u64),
+    /// A leaf in a slot at some depth along with the prefix leading to the slot.
+    Leaf(u32, u32, u64),
+    /// Prefixes of the final level which are all unoccupied.
+    Holes(Range<u128>),
+}
+
+/// Finds the prefixes of `level` bits that no key uses.
+pub(crate) struct HolesIter<'a, F> {
+    trie: &'a BinTrie,
+    level: u32,
+    lookup: F,
+    pending: Vec<Pending>,
+}
+
+/// The prefixes of `level` bits that start with the `depth` bits of `prefix`.
+fn span(level: u32, depth: u32, prefix: u64) -> Range<u128> {
+    let shift = level - depth;
+    (prefix as u128) << shift..(prefix as u128 + 1) << shift
+}
+
+impl<'a, F> Iterator for HolesIter<'a, F>
+where
+    F: FnMut(u32, u32) -> bool,
+{
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        loop {
+            match self.pending.pop()? {
+                Pending::Node(index, depth, prefix) => {
+                    // Push in reverse so the left side comes out first.
+                    for position in (0..2).rev() {
+                        let prefix = prefix << 1 | position as u64;
+                        match self.trie.child_checked(index, position) {
+                            0 => self.pending.push(Pending::Holes(span(
+                                self.level,
+                                depth + 1,
+                                prefix,
+                            ))),
+                            // The whole prefix is used by whatever is in the slot.
+                            _ if depth + 1 == self.level => {}
+                            n if n & HIGH != 0 => {
+                                self.pending
+                                    .push(Pending::Leaf(n & !HIGH, depth + 1, prefix))
+                            }
+                            n => self
+                                .pending
+                                .push(Pending::Node(n as usize, depth + 1, prefix)),
+                        }
+                    }
+                }
+                Pending::Leaf(item, depth, prefix) => {
+                    // Only the prefix of the key of the leaf is used beneath its slot.
+                    let span = span(self.level, depth, prefix);
+                    let used = (depth..self.level).fold(prefix, |prefix, l| {
+                        prefix << 1 | (self.lookup)(item, l) as u64
+                    }) as u128;
+                    self.pending.push(Pending::Holes(used + 1..span.end));
+                    self.pending.push(Pending::Holes(span.start..used));
+                }
+                Pending::Holes(mut holes) => {
+                    if let Some(hole) = holes.next() {
+                        self.pending.push(Pending::Holes(holes));
+                        return Some(hole as u64);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl BinTrie {
+    /// Iterates over the prefixes of `level` bits that are not used by any key.
+    ///
+    /// `F(item, n)` - A function that must be able to look up the nth bit
+    ///    from a previously inserted item.
+    ///
+    /// The prefixes are numbered like in `group_by_prefix` and come out in ascending
+    /// order. They are found lazily, so large empty regions of the key space don't need
+    /// to be collected at once. Terminated keys shorter than `level` bits do not use any
+    /// prefix. `level` can be at most `64`.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let mut trie = BinTrie::new_depth(4);
+    /// let lookup = |n: u32, l: u32| n >> (3 - l) & 1 == 1;
+    /// for &n in &[0b0001, 0b0011, 0b1000, 0b1001] {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// assert_eq!(trie.unoccupied_prefixes(2, lookup).collect::<Vec<_>>(), vec![0b01, 0b11]);
+    /// assert_eq!(
+    ///     trie.unoccupied_prefixes(3, lookup).collect::<Vec<_>>(),
+    ///     vec![0b010, 0b011, 0b101, 0b110, 0b111],
+    /// );
+    /// ```
+    pub fn unoccupied_prefixes<'a, F>(
+        &'a self,
+        level: u32,
+        lookup: F,
+    ) -> impl Iterator<Item = u64> + 'a
+    where
+        F: FnMut(u32, u32) -> bool + 'a,
+    {
+        self.holes_under(0, 0, level, lookup)
+    }
+
+    /// Iterates over the prefixes of `level` bits that start with `prefix` and are not used by any key.
+    ///
+    /// `F(item, n)` - A function that must be able to look up the nth bit
+    ///    from a previously inserted item.
+    ///
+    /// `prefix` holds the first `prefix_len` bits of the prefixes, with the first bit
+    /// being the most significant, and must not be longer than `level`. Otherwise
+    /// this is the same as `unoccupied_prefixes`.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let mut trie = BinTrie::new_depth(4);
+    /// let lookup = |n: u32, l: u32| n >> (3 - l) & 1 == 1;
+    /// for &n in &[0b0001, 0b0011, 0b1000, 0b1001] {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// assert_eq!(trie.holes_under(0b1, 1, 3, lookup).collect::<Vec<_>>(), vec![0b101, 0b110, 0b111]);
+    /// assert_eq!(trie.holes_under(0b000, 3, 4, lookup).collect::<Vec<_>>(), vec![0b0000]);
+    /// assert_eq!(trie.holes_under(0b11, 2, 2, lookup).collect::<Vec<_>>(), vec![0b11]);
+    /// ```
+    pub fn holes_under<'a, F>(
+        &'a self,
+        prefix: u64,
+        prefix_len: u32,
+        level: u32,
+        mut lookup: F,
+    ) -> impl Iterator<Item = u64> + 'a
+    where
+        F: FnMut(u32, u32) -> bool + 'a,
+    {
+        assert!(level <= 64 && level <= self.depth && prefix_len <= level);
+        let bit = |l: u32| prefix >> (prefix_len - 1 - l) & 1;
+        let everything = span(level, prefix_len, prefix);
+        let mut pending = if level == 0 {
+            // There is only one empty prefix, which is used by any key.
+            Pending::Holes(0..self.is_empty() as u128)
+        } else {
+            Pending::Node(0, 0, 0)
+        };
+        // Follow the prefix down to the slot that everything under it is in.
+        for depth in 0..prefix_len {
+            let index = match pending {
+                Pending::Node(index, _, _) => index,
+                _ => break,
+            };
+            pending = match self.child_checked(index, bit(depth) as usize) {
+                0 => Pending::Holes(everything.clone()),
+                _ if depth + 1 == level => Pending::Holes(0..0),
+                n if n & HIGH != 0 => {
+                    // The leaf only uses the prefix if the rest of its key matches it.
+                    let item = n & !HIGH;
+                    if (depth + 1..prefix_len).all(|l| lookup(item, l) as u64 == bit(l)) {
+                        Pending::Leaf(item, prefix_len, prefix)
+                    } else {
+                        Pending::Holes(everything.clone())
+                    }
+                }
+                n => Pending::Node(n as usize, depth + 1, prefix >> (prefix_len - 1 - depth)),
+            };
+        }
+        HolesIter {
+            trie: self,
+            level,
+            lookup,
+            pending: vec![pending],
+        }
+    }
+}
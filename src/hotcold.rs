@@ -0,0 +1,322 @@
+use crate::explore::{accept_all, ExploreIter};
+use crate::{BinTrie, Child, FastMap, Internal, IntoHeuristic, Nodes};
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+/// How many lookups passed through each internal node, indexed by the node.
+///
+/// The counters are atomic so that lookups can count through a shared reference.
+#[derive(Default)]
+pub(crate) struct AccessCounts(Vec<AtomicU32>);
+
+impl AccessCounts {
+    fn new(len: usize) -> Self {
+        Self((0..len).map(|_| AtomicU32::new(0)).collect())
+    }
+
+    /// Counts a lookup passing through the internal node at `index`.
+    ///
+    /// Nodes that were made after counting started are not counted.
+    #[inline(always)]
+    fn hit(&self, index: usize) {
+        if let Some(count) = self.0.get(index) {
+            count.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn get(&self, index: usize) -> u32 {
+        self.0
+            .get(index)
+            .map_or(0, |count| count.load(Ordering::Relaxed))
+    }
+
+    /// Moves the counts along with the nodes, so that the node at `order[i]` ends up at `i`.
+    pub(crate) fn permute(&mut self, order: &[u32]) {
+        self.0 = order
+            .iter()
+            .map(|&old| AtomicU32::new(self.get(old as usize)))
+            .collect();
+    }
+}
+
+impl Clone for AccessCounts {
+    fn clone(&self) -> Self {
+        Self(
+            self.0
+                .iter()
+                .map(|count| AtomicU32::new(count.load(Ordering::Relaxed)))
+                .collect(),
+        )
+    }
+}
+
+impl core::fmt::Debug for AccessCounts {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_list()
+            .entries(self.0.iter().map(|count| count.load(Ordering::Relaxed)))
+            .finish()
+    }
+}
+
+impl BinTrie {
+    /// Starts counting how many lookups pass through each internal node.
+    ///
+    /// Once counting, every `get` adds one to each internal node on its path, which
+    /// `reorganize` uses to decide which nodes are hot. Counting again restarts every
+    /// count from zero. Nodes made by later insertions are not counted.
+    pub fn count_accesses(&mut self) {
+        self.accesses = Some(AccessCounts::new(self.internals.len()));
+    }
+
+    /// Stops counting accesses and forgets the counts.
+    pub fn stop_counting_accesses(&mut self) {
+        self.accesses = None;
+    }
+
+    /// Performs a lookup like `get`, counting every internal node on the way.
+    pub(crate) fn get_counted<K>(&self, accesses: &AccessCounts, mut key: K) -> Option<u32>
+    where
+        K: FnMut(u32) -> bool,
+    {
+        let mut index = 0;
+        for i in 0..self.depth {
+            accesses.hit(index);
+            match Child::unpack(self.child_checked(index, key(i) as usize)) {
+                // Empty node encountered.
+                Child::Empty => return None,
+                // Leaf node encountered.
+                Child::Leaf(m) => return Some(m),
+                // Internal node encountered.
+                Child::Internal(m) => index = m as usize,
+            }
+        }
+        None
+    }
+
+    /// Copies the trie into a layout where the `hot_nodes` most accessed internal
+    /// nodes are packed into a small dense array, and the rest live in a separate pool.
+    ///
+    /// The accesses are those counted since `count_accesses`. The hot nodes are
+    /// stored from the most to the least accessed, so the nodes that nearly every
+    /// lookup passes through share a few cache lines. The cold nodes are stored
+    /// in depth-first order, which keeps each cold subtree contiguous. The root is
+    /// always hot, and nodes with the same count are taken from the top levels first,
+    /// so without any counted accesses the hot array holds the top of the trie.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let mut trie = BinTrie::new_depth(16);
+    /// let lookup = |n: u32, l: u32| n.wrapping_mul(0x9E37_79B9) >> l & 1 == 1;
+    /// for n in 0..1000 {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// trie.count_accesses();
+    /// // Most lookups go to the first few items.
+    /// for _ in 0..100 {
+    ///     for n in 0..4 {
+    ///         trie.get(|l| lookup(n, l));
+    ///     }
+    /// }
+    /// let hot_cold = trie.reorganize(32);
+    /// assert_eq!(hot_cold.hot_nodes(), 32);
+    /// assert_eq!(hot_cold.hot_nodes() + hot_cold.cold_nodes(), trie.node_count());
+    /// assert_eq!(hot_cold.items().collect::<Vec<u32>>(), trie.items().collect::<Vec<u32>>());
+    /// for n in 0..1000 {
+    ///     assert_eq!(hot_cold.get(|l| lookup(n, l)), Some(n));
+    /// }
+    /// ```
+    pub fn reorganize(&self, hot_nodes: usize) -> HotColdTrie {
+        self.assert_trusted();
+        // Every internal node in breadth-first order, which ranks nodes with the same count.
+        let mut nodes = vec![];
+        let mut queue = VecDeque::new();
+        queue.push_back(0);
+        while let Some(index) = queue.pop_front() {
+            nodes.push(index);
+            queue.extend(self.internal_children(index));
+        }
+        let count = |index: u32| {
+            self.accesses
+                .as_ref()
+                .map_or(0, |accesses| accesses.get(index as usize))
+        };
+        // The root is always first, since every lookup starts there.
+        nodes[1..].sort_by_key(|&index| core::cmp::Reverse(count(index)));
+        let hot_len = hot_nodes.clamp(1, nodes.len());
+        let mut new_index = vec![u32::MAX; self.internals.len()];
+        for (new, &old) in nodes[..hot_len].iter().enumerate() {
+            new_index[old as usize] = new as u32;
+        }
+        // Depth-first through whatever was left cold.
+        let mut cold = vec![];
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            if new_index[index as usize] == u32::MAX {
+                new_index[index as usize] = (hot_len + cold.len()) as u32;
+                cold.push(index);
+            }
+            stack.extend(self.internal_children(index).rev());
+        }
+        let moved = |old: u32| {
+            Internal(
+                self.internals[old as usize]
+                    .0
+                    .map(|n| match Child::unpack(n) {
+                        Child::Internal(m) => new_index[m as usize],
+                        Child::Empty | Child::Leaf(_) => n,
+                    }),
+            )
+        };
+        HotColdTrie {
+            hot: nodes[..hot_len].iter().map(|&old| moved(old)).collect(),
+            cold: cold.iter().map(|&old| moved(old)).collect(),
+            depth: self.depth,
+            terminals: self
+                .terminals
+                .iter()
+                .map(|(&index, &item)| (new_index[index as usize], item))
+                .collect(),
+            buckets: self.buckets.clone(),
+            len: self.len,
+        }
+    }
+}
+
+/// A read-only copy of a trie with its hot internal nodes apart from the cold ones,
+/// as made by `BinTrie::reorganize`.
+///
+/// Node indices run through the hot array and then on into the cold pool, so
+/// telling them apart is a single comparison.
+#[derive(Clone, Debug)]
+pub struct HotColdTrie {
+    /// The most accessed internal nodes, starting with the root.
+    hot: Vec<Internal>,
+    /// Every other internal node, in depth-first order.
+    cold: Vec<Internal>,
+    depth: u32,
+    terminals: BTreeMap<u32, u32>,
+    buckets: Option<FastMap<u32, Vec<u32>>>,
+    len: usize,
+}
+
+impl HotColdTrie {
+    /// Gets the internal node at `index`, wherever it is stored.
+    #[inline(always)]
+    fn node(&self, index: usize) -> &Internal {
+        match self.hot.get(index) {
+            Some(node) => node,
+            None => &self.cold[index - self.hot.len()],
+        }
+    }
+
+    /// Perform a lookup for a particular item, like `BinTrie::get`.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the key.
+    pub fn get<K>(&self, mut key: K) -> Option<u32>
+    where
+        K: FnMut(u32) -> bool,
+    {
+        let mut index = 0;
+        for i in 0..self.depth {
+            match Child::unpack(self.node(index).0[key(i) as usize]) {
+                // Empty node encountered.
+                Child::Empty => return None,
+                // Leaf node encountered.
+                Child::Leaf(m) => return Some(m),
+                // Internal node encountered.
+                Child::Internal(m) => index = m as usize,
+            }
+        }
+        None
+    }
+
+    /// Get an iterator over the items in the trie, in the same order as `BinTrie::items`.
+    pub fn items(&self) -> impl Iterator<Item = u32> + '_ {
+        self.explore(crate::FilterHeuristic(|_| true))
+    }
+
+    /// Iterates over the trie while using the `heuristic` to guide iteration, like `BinTrie::explore`.
+    pub fn explore<'a, H>(&'a self, heuristic: H) -> impl Iterator<Item = u32> + 'a
+    where
+        H: IntoHeuristic,
+        H::Heuristic: 'a,
+    {
+        ExploreIter::new(self, heuristic.into_heuristic(), vec![], accept_all)
+    }
+
+    /// The number of items in the trie.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Checks if the trie has no items.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The number of internal nodes in the hot array.
+    pub fn hot_nodes(&self) -> usize {
+        self.hot.len()
+    }
+
+    /// The number of internal nodes in the cold pool.
+    pub fn cold_nodes(&self) -> usize {
+        self.cold.len()
+    }
+}
+
+impl Nodes for &HotColdTrie {
+    type Index = u32;
+
+    #[inline(always)]
+    fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    #[inline(always)]
+    fn root(&self) -> u32 {
+        0
+    }
+
+    #[inline(always)]
+    fn child(&self, index: u32, position: usize) -> Child {
+        Child::unpack(self.node(index as usize).0[position])
+    }
+
+    #[inline(always)]
+    unsafe fn child_unchecked(&self, index: u32, position: usize) -> Child {
+        Child::unpack(*self.node(index as usize).0.get_unchecked(position))
+    }
+
+    #[inline(always)]
+    fn terminal(&self, index: u32) -> Option<u32> {
+        self.terminals.get(&index).copied()
+    }
+
+    #[inline(always)]
+    fn child_checked(&self, index: u32, position: usize) -> Child {
+        let index = index as usize;
+        if index < self.hot.len() + self.cold.len() {
+            Child::unpack(self.node(index).0[position])
+        } else {
+            Child::Empty
+        }
+    }
+
+    #[inline(always)]
+    fn bucket_item(&self, item: u32, position: usize) -> Option<u32> {
+        self.buckets
+            .as_ref()
+            .and_then(|buckets| buckets.get(&item))
+            .and_then(|bucket| bucket.get(position))
+            .copied()
+    }
+
+    #[inline(always)]
+    fn is_verified(&self) -> bool {
+        // It is only made from trusted tries.
+        true
+    }
+}
@@ -0,0 +1,464 @@
+use crate::explore::{accept_all, ExploreIter, Location};
+use crate::{BinTrieError, Child, FilterHeuristic, IntoHeuristic, Nodes, HIGH};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+/// A value small enough to be stored inline in a leaf of an `InlineTrie`.
+pub trait InlineValue: Copy {
+    /// Converts the value into the bits stored in the leaf.
+    fn into_bits(self) -> u32;
+
+    /// Converts the bits stored in the leaf back into the value.
+    fn from_bits(bits: u32) -> Self;
+}
+
+impl InlineValue for u32 {
+    #[inline(always)]
+    fn into_bits(self) -> u32 {
+        self
+    }
+
+    #[inline(always)]
+    fn from_bits(bits: u32) -> Self {
+        bits
+    }
+}
+
+impl InlineValue for i32 {
+    #[inline(always)]
+    fn into_bits(self) -> u32 {
+        self as u32
+    }
+
+    #[inline(always)]
+    fn from_bits(bits: u32) -> Self {
+        bits as i32
+    }
+}
+
+impl InlineValue for f32 {
+    #[inline(always)]
+    fn into_bits(self) -> u32 {
+        self.to_bits()
+    }
+
+    #[inline(always)]
+    fn from_bits(bits: u32) -> Self {
+        f32::from_bits(bits)
+    }
+}
+
+/// Splits a 64-bit slot into its child ID and its inline value bits.
+#[inline(always)]
+fn split(slot: u64) -> (u32, u32) {
+    (slot as u32, (slot >> 32) as u32)
+}
+
+/// Packs a child ID and inline value bits into a 64-bit slot.
+#[inline(always)]
+fn pack(child: u32, value: u32) -> u64 {
+    u64::from(value) << 32 | u64::from(child)
+}
+
+/// A trie with 64-bit slots that stores a small value inline with every leaf.
+///
+/// This works exactly like `BinTrie`, except that every leaf also holds a
+/// value of type `V` in the upper half of its slot. Retrieving the value of
+/// a result therefore needs no lookup into a separate table, even when exploring.
+///
+/// ```
+/// # use bintrie::{FilterHeuristic, InlineTrie, Side};
+/// let mut trie = InlineTrie::<f32>::new_depth(8);
+/// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+/// for n in 0..4 {
+///     trie.insert(n, n as f32 * 0.5, |l| lookup(n, l), lookup);
+/// }
+/// assert_eq!(trie.len(), 4);
+/// assert_eq!(trie.get(|l| lookup(3, l)), Some((3, 1.5)));
+/// assert_eq!(trie.items().collect::<Vec<_>>(), vec![(0, 0.0), (2, 1.0), (1, 0.5), (3, 1.5)]);
+/// let odd = trie.explore(FilterHeuristic(|s| s == Side::Right)).collect::<Vec<_>>();
+/// assert_eq!(odd, vec![(3, 1.5)]);
+/// ```
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "InlineRepr", bound = ""))]
+pub struct InlineTrie<V> {
+    /// The root node is always at index `0`.
+    internals: Vec<[u64; 2]>,
+    /// The maximum depth to stop at.
+    depth: u32,
+    /// The number of items in the trie.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    len: usize,
+    /// The indices of internal nodes that were removed and can be reused.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    free: Vec<u32>,
+    _value: PhantomData<V>,
+}
+
+impl<V> InlineTrie<V>
+where
+    V: InlineValue,
+{
+    /// Makes a new trie with a maximum `depth` of `8192`.
+    pub fn new() -> Self {
+        Self::new_depth(8192)
+    }
+
+    /// Makes a new trie with a given maximum `depth`.
+    pub fn new_depth(depth: u32) -> Self {
+        assert!(depth > 0);
+        Self {
+            internals: vec![[0; 2]],
+            depth,
+            len: 0,
+            free: vec![],
+            _value: PhantomData,
+        }
+    }
+
+    /// Inserts an item along with its inline `value`.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the key.
+    /// `F(item, n)` - A function that must be able to look up the nth bit
+    ///    from a previously inserted item.
+    ///
+    /// Returns `Some` of a replaced leaf and its value if a leaf was replaced, otherwise None.
+    pub fn insert<K, F>(&mut self, item: u32, value: V, key: K, lookup: F) -> Option<(u32, V)>
+    where
+        K: FnMut(u32) -> bool,
+        F: FnMut(u32, u32) -> bool,
+    {
+        self.try_insert(item, value, key, lookup)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Inserts an item like `insert`, but returns an error rather than panicking.
+    ///
+    /// An error is returned if the item has the most significant bit set, or if
+    /// there is no room for another internal node, just like `BinTrie::try_insert`.
+    ///
+    /// ```
+    /// # use bintrie::{BinTrieError, InlineTrie};
+    /// let mut trie = InlineTrie::<u32>::new();
+    /// assert_eq!(trie.try_insert(5, 7, |_| false, |_, _| false), Ok(None));
+    /// assert_eq!(
+    ///     trie.try_insert(1 << 31, 7, |_| true, |_, _| false),
+    ///     Err(BinTrieError::ItemTooLarge(1 << 31)),
+    /// );
+    /// ```
+    pub fn try_insert<K, F>(
+        &mut self,
+        item: u32,
+        value: V,
+        mut key: K,
+        mut lookup: F,
+    ) -> Result<Option<(u32, V)>, BinTrieError>
+    where
+        K: FnMut(u32) -> bool,
+        F: FnMut(u32, u32) -> bool,
+    {
+        // Always check that the high bit is not set in the item.
+        if item & HIGH != 0 {
+            return Err(BinTrieError::ItemTooLarge(item));
+        }
+        let leaf = pack(item | HIGH, value.into_bits());
+        let mut index = 0;
+        for i in 0..self.depth - 1 {
+            let position = key(i) as usize;
+            match split(self.internals[index][position]) {
+                // Empty node encountered.
+                (0, _) => {
+                    self.internals[index][position] = leaf;
+                    self.len += 1;
+                    return Ok(None);
+                }
+                // Leaf node encountered.
+                (m, _) if m & HIGH != 0 => {
+                    let mut new_internal = [0; 2];
+                    new_internal[lookup(m & !HIGH, i + 1) as usize] =
+                        self.internals[index][position];
+                    let new_index = self.try_allocate(new_internal)?;
+                    self.internals[index][position] = u64::from(new_index);
+                    index = new_index as usize;
+                }
+                // Internal node encountered.
+                (m, _) => index = m as usize,
+            }
+        }
+        let position = key(self.depth - 1) as usize;
+        let old = core::mem::replace(&mut self.internals[index][position], leaf);
+        match split(old) {
+            (0, _) => {
+                self.len += 1;
+                Ok(None)
+            }
+            (m, value) => Ok(Some((m & !HIGH, V::from_bits(value)))),
+        }
+    }
+
+    /// Stores a new internal node, reusing a removed one if there is one.
+    fn try_allocate(&mut self, internal: [u64; 2]) -> Result<u32, BinTrieError> {
+        if let Some(index) = self.free.pop() {
+            self.internals[index as usize] = internal;
+            Ok(index)
+        } else {
+            let index = self.internals.len();
+            // Indices must fit beneath the high bit.
+            if index >= HIGH as usize {
+                return Err(BinTrieError::CapacityExhausted);
+            }
+            self.internals.push(internal);
+            Ok(index as u32)
+        }
+    }
+
+    /// Removes the item with a particular key along with its value.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the key.
+    /// `F(item, n)` - A function that must be able to look up the nth bit
+    ///    from a previously inserted item.
+    ///
+    /// Like `BinTrie::remove`, internal nodes left holding a single leaf are
+    /// collapsed, and are reused by later insertions.
+    ///
+    /// ```
+    /// # use bintrie::InlineTrie;
+    /// let mut trie = InlineTrie::<i32>::new_depth(8);
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// for n in 0..4 {
+    ///     trie.insert(n, -(n as i32), |l| lookup(n, l), lookup);
+    /// }
+    /// assert_eq!(trie.remove(|l| lookup(2, l), lookup), Some((2, -2)));
+    /// assert_eq!(trie.remove(|l| lookup(2, l), lookup), None);
+    /// assert_eq!(trie.len(), 3);
+    /// assert_eq!(trie.items().collect::<Vec<_>>(), vec![(0, 0), (1, -1), (3, -3)]);
+    /// ```
+    pub fn remove<K, F>(&mut self, mut key: K, mut lookup: F) -> Option<(u32, V)>
+    where
+        K: FnMut(u32) -> bool,
+        F: FnMut(u32, u32) -> bool,
+    {
+        // The internal nodes and positions taken to get to the item.
+        let mut path = vec![];
+        let mut index = 0;
+        let (item, value) = loop {
+            let level = path.len() as u32;
+            if level == self.depth {
+                return None;
+            }
+            let position = key(level) as usize;
+            path.push((index, position));
+            match split(self.internals[index][position]) {
+                // Empty node encountered.
+                (0, _) => return None,
+                // Leaf node encountered.
+                (m, value) if m & HIGH != 0 => break (m & !HIGH, value),
+                // Internal node encountered.
+                (m, _) => index = m as usize,
+            }
+        };
+        // Make sure the item actually has the key.
+        let level = path.len() as u32;
+        if (level..self.depth).any(|l| lookup(item, l) != key(l)) {
+            return None;
+        }
+        let (mut index, position) = path.pop().unwrap();
+        self.internals[index][position] = 0;
+        self.len -= 1;
+        // Collapse every ancestor left holding too little.
+        for &(parent, position) in path.iter().rev() {
+            let replacement = match self.internals[index] {
+                [0, 0] => 0,
+                [0, m] | [m, 0] if split(m).0 & HIGH != 0 => m,
+                _ => break,
+            };
+            self.internals[index] = [0, 0];
+            self.internals[parent][position] = replacement;
+            self.free.push(index as u32);
+            index = parent;
+        }
+        Some((item, V::from_bits(value)))
+    }
+
+    /// Perform a lookup for a particular item and its value.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the key.
+    pub fn get<K>(&self, mut key: K) -> Option<(u32, V)>
+    where
+        K: FnMut(u32) -> bool,
+    {
+        let mut index = 0;
+        for i in 0..self.depth {
+            match split(self.internals[index][key(i) as usize]) {
+                // Empty node encountered.
+                (0, _) => return None,
+                // Leaf node encountered.
+                (m, value) if m & HIGH != 0 => return Some((m & !HIGH, V::from_bits(value))),
+                // Internal node encountered.
+                (m, _) => index = m as usize,
+            }
+        }
+        None
+    }
+
+    /// Iterates over the trie while using the `heuristic` to guide iteration, like
+    /// `BinTrie::explore`, yielding every item along with its value.
+    pub fn explore<'a, H>(&'a self, heuristic: H) -> impl Iterator<Item = (u32, V)> + 'a
+    where
+        H: IntoHeuristic,
+        H::Heuristic: 'a,
+    {
+        let mut explore = ExploreIter::new(self, heuristic.into_heuristic(), vec![], accept_all);
+        core::iter::from_fn(move || {
+            let item = explore.next()?;
+            // The value is right next to the item in the slot it was found in.
+            let (index, side) = match explore.leaves.location {
+                Some(Location::Leaf(index, side)) => (index, side),
+                _ => unreachable!("every item of an inline trie is in a leaf"),
+            };
+            let slot = self.internals[index as usize][side.index()];
+            Some((item, V::from_bits(split(slot).1)))
+        })
+    }
+
+    /// Get an iterator over the items added to the trie along with their values.
+    pub fn items(&self) -> impl Iterator<Item = (u32, V)> + '_ {
+        self.explore(FilterHeuristic(|_| true))
+    }
+
+    /// The number of items in the trie.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Checks if the trie has no items.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl<V> Nodes for &InlineTrie<V> {
+    type Index = u32;
+
+    #[inline(always)]
+    fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    #[inline(always)]
+    fn root(&self) -> u32 {
+        0
+    }
+
+    #[inline(always)]
+    fn child(&self, index: u32, position: usize) -> Child {
+        Child::unpack(split(self.internals[index as usize][position]).0)
+    }
+
+    #[inline(always)]
+    unsafe fn child_unchecked(&self, index: u32, position: usize) -> Child {
+        let slot = *self
+            .internals
+            .get_unchecked(index as usize)
+            .get_unchecked(position);
+        Child::unpack(split(slot).0)
+    }
+
+    #[inline(always)]
+    fn terminal(&self, _index: u32) -> Option<u32> {
+        // Keys can't be terminated early.
+        None
+    }
+
+    #[inline(always)]
+    fn child_checked(&self, index: u32, position: usize) -> Child {
+        Child::unpack(
+            self.internals
+                .get(index as usize)
+                .map_or(0, |internal| split(internal[position]).0),
+        )
+    }
+
+    #[inline(always)]
+    fn bucket_item(&self, _item: u32, _position: usize) -> Option<u32> {
+        // Every leaf holds a single item.
+        None
+    }
+
+    #[inline(always)]
+    fn is_verified(&self) -> bool {
+        // Deserialized tries are validated.
+        true
+    }
+}
+
+impl<V> Default for InlineTrie<V>
+where
+    V: InlineValue,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The serialized form of an `InlineTrie`, before it has been validated.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct InlineRepr {
+    internals: Vec<[u64; 2]>,
+    depth: u32,
+}
+
+#[cfg(feature = "serde")]
+impl<V> core::convert::TryFrom<InlineRepr> for InlineTrie<V> {
+    type Error = crate::InvalidTrieError;
+
+    /// Makes sure that lookups can't go out of bounds.
+    fn try_from(repr: InlineRepr) -> Result<Self, Self::Error> {
+        use crate::InvalidTrieError;
+        let InlineRepr { internals, depth } = repr;
+        if depth == 0 {
+            return Err(InvalidTrieError::ZeroDepth);
+        }
+        if internals.is_empty() {
+            return Err(InvalidTrieError::MissingRoot);
+        }
+        let mut reached = vec![false; internals.len()];
+        let mut stack = vec![(0, 0)];
+        while let Some((index, level)) = stack.pop() {
+            for &slot in &internals[index] {
+                let (n, _) = split(slot);
+                if n == 0 || n & HIGH != 0 {
+                    continue;
+                }
+                if n as usize >= internals.len() {
+                    return Err(InvalidTrieError::IndexOutOfRange(n));
+                }
+                if reached[n as usize] {
+                    return Err(InvalidTrieError::RepeatedIndex(n));
+                }
+                if level + 1 >= depth {
+                    return Err(InvalidTrieError::TooDeep(n));
+                }
+                reached[n as usize] = true;
+                stack.push((n as usize, level + 1));
+            }
+        }
+        let len = reached
+            .iter()
+            .enumerate()
+            .filter(|&(index, &reached)| index == 0 || reached)
+            .flat_map(|(index, _)| internals[index].iter())
+            .filter(|&&slot| split(slot).0 & HIGH != 0)
+            .count();
+        Ok(Self {
+            internals,
+            depth,
+            len,
+            free: vec![],
+            _value: PhantomData,
+        })
+    }
+}
@@ -0,0 +1,190 @@
+use crate::{BinTrie, Items, HIGH};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Walks a trie while taking every item out of it, which `IntoItems` and `Drain` share.
+///
+/// Every child is left empty once it is taken, so nothing is visited twice.
+#[derive(Debug)]
+pub(crate) struct Taking {
+    /// Internal nodes whose children have not all been taken yet.
+    stack: Vec<u32>,
+    /// The rest of the bucket of the most recently yielded leaf.
+    bucket: vec::IntoIter<u32>,
+    remaining: usize,
+    /// Whether the trie is suspect, so every index must be checked.
+    checked: bool,
+}
+
+impl Taking {
+    pub(crate) fn new(trie: &BinTrie) -> Self {
+        let stack = if trie.internals.is_empty() {
+            vec![]
+        } else {
+            vec![0]
+        };
+        Self {
+            stack,
+            bucket: vec![].into_iter(),
+            remaining: trie.len,
+            checked: trie.is_suspect(),
+        }
+    }
+
+    /// Takes the next item out of the `trie`.
+    ///
+    /// `finished` is called with the trie whenever every child of an internal node was taken.
+    pub(crate) fn next<R>(&mut self, trie: &mut BinTrie, mut finished: R) -> Option<u32>
+    where
+        R: FnMut(&mut BinTrie),
+    {
+        if let Some(item) = self.bucket.next() {
+            self.remaining = self.remaining.saturating_sub(1);
+            return Some(item);
+        }
+        loop {
+            let index = *self.stack.last()? as usize;
+            // Take the next child, leaving it empty so it is not visited twice.
+            // A node that was already released has no children left.
+            let child = trie
+                .internals
+                .get_mut(index)
+                .and_then(|internal| internal.0.iter_mut().find(|n| **n != 0));
+            let n = match child {
+                Some(n) => core::mem::take(n),
+                None => {
+                    self.stack.pop();
+                    finished(trie);
+                    continue;
+                }
+            };
+            if n & HIGH != 0 {
+                let item = n & !HIGH;
+                if let Some(buckets) = &mut trie.buckets {
+                    if let Some(bucket) = buckets.remove(&item) {
+                        self.bucket = bucket.into_iter();
+                    }
+                }
+                self.remaining = self.remaining.saturating_sub(1);
+                return Some(item);
+            }
+            let terminal = trie.terminals.remove(&n);
+            // Nodes that were released already are empty, and suspect tries may
+            // point past the end or deeper than the depth.
+            let exists = (n as usize) < trie.internals.len()
+                && !(self.checked && self.stack.len() >= trie.depth as usize);
+            if exists {
+                self.stack.push(n);
+            }
+            if let Some(item) = terminal {
+                self.remaining = self.remaining.saturating_sub(1);
+                return Some(item);
+            }
+        }
+    }
+
+    pub(crate) fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+/// The iterator returned by `BinTrie::into_items`, which consumes the trie.
+///
+/// Items are yielded in the same order as `BinTrie::items`. Terminated items and
+/// buckets are dropped as soon as they are yielded, and internal nodes at the end
+/// of the trie are released once everything beneath them has been yielded.
+#[derive(Debug)]
+pub struct IntoItems {
+    trie: BinTrie,
+    taking: Taking,
+}
+
+impl IntoItems {
+    fn new(trie: BinTrie) -> Self {
+        Self {
+            taking: Taking::new(&trie),
+            trie,
+        }
+    }
+}
+
+/// Releases the empty internal nodes at the end of the trie.
+fn release(trie: &mut BinTrie) {
+    let internals = &mut trie.internals;
+    while internals.len() > 1 && internals.last().is_some_and(|last| last.0 == [0, 0]) {
+        internals.pop();
+    }
+    if internals.len() * 4 < internals.capacity() {
+        internals.shrink_to(internals.len() * 2);
+    }
+}
+
+impl Iterator for IntoItems {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.taking.next(&mut self.trie, release)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.taking.size_hint()
+    }
+}
+
+impl ExactSizeIterator for IntoItems {}
+
+impl BinTrie {
+    /// Consumes the trie and iterates over its items.
+    ///
+    /// This yields the same items in the same order as `items`, but memory is
+    /// released while iterating, which helps when the trie is very large and is
+    /// not needed afterwards.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let mut trie = BinTrie::new_depth(8);
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// for n in 0..32 {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// let items = trie.items().collect::<Vec<u32>>();
+    /// assert_eq!(trie.into_items().collect::<Vec<u32>>(), items);
+    /// ```
+    pub fn into_items(self) -> IntoItems {
+        IntoItems::new(self)
+    }
+}
+
+/// Iterates over the items of the trie like `BinTrie::into_items`.
+impl IntoIterator for BinTrie {
+    type Item = u32;
+    type IntoIter = IntoItems;
+
+    fn into_iter(self) -> IntoItems {
+        self.into_items()
+    }
+}
+
+/// Iterates over the items of the trie like `BinTrie::items`.
+///
+/// ```
+/// # use bintrie::BinTrie;
+/// let mut trie = BinTrie::new_depth(4);
+/// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+/// for n in 0..4 {
+///     trie.insert(n, |l| lookup(n, l), lookup);
+/// }
+/// let mut total = 0;
+/// for item in &trie {
+///     total += item;
+/// }
+/// assert_eq!(total, 6);
+/// ```
+impl<'a> IntoIterator for &'a BinTrie {
+    type Item = u32;
+    type IntoIter = Items<'a>;
+
+    fn into_iter(self) -> Items<'a> {
+        self.items()
+    }
+}
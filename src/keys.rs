@@ -0,0 +1,307 @@
+use crate::descriptor::descriptor_bit;
+use crate::{BinTrie, FastMap};
+use alloc::vec::Vec;
+use core::iter::FromIterator;
+
+/// A key which can provide any of its bits.
+///
+/// Integers provide their bits from the least significant bit up, while bytes
+/// provide their bits from the most significant bit of the first byte onwards,
+/// like `BinTrie::from_descriptors`.
+pub trait KeyBits {
+    /// Gets the `n`th bit of the key.
+    fn bit(&self, n: u32) -> bool;
+
+    /// The number of bits in the key, if it is known.
+    ///
+    /// This is used to choose the depth of a trie built with `collect`.
+    fn bit_len(&self) -> Option<u32> {
+        None
+    }
+}
+
+impl<T> KeyBits for &T
+where
+    T: KeyBits + ?Sized,
+{
+    #[inline(always)]
+    fn bit(&self, n: u32) -> bool {
+        (**self).bit(n)
+    }
+
+    #[inline(always)]
+    fn bit_len(&self) -> Option<u32> {
+        (**self).bit_len()
+    }
+}
+
+impl KeyBits for u32 {
+    #[inline(always)]
+    fn bit(&self, n: u32) -> bool {
+        self >> n & 1 == 1
+    }
+
+    #[inline(always)]
+    fn bit_len(&self) -> Option<u32> {
+        Some(32)
+    }
+}
+
+impl KeyBits for u64 {
+    #[inline(always)]
+    fn bit(&self, n: u32) -> bool {
+        self >> n & 1 == 1
+    }
+
+    #[inline(always)]
+    fn bit_len(&self) -> Option<u32> {
+        Some(64)
+    }
+}
+
+impl KeyBits for u128 {
+    #[inline(always)]
+    fn bit(&self, n: u32) -> bool {
+        self >> n & 1 == 1
+    }
+
+    #[inline(always)]
+    fn bit_len(&self) -> Option<u32> {
+        Some(128)
+    }
+}
+
+impl KeyBits for [u8] {
+    #[inline(always)]
+    fn bit(&self, n: u32) -> bool {
+        descriptor_bit(self, n)
+    }
+
+    #[inline(always)]
+    fn bit_len(&self) -> Option<u32> {
+        Some(self.len() as u32 * 8)
+    }
+}
+
+impl<const N: usize> KeyBits for [u8; N] {
+    #[inline(always)]
+    fn bit(&self, n: u32) -> bool {
+        descriptor_bit(self, n)
+    }
+
+    #[inline(always)]
+    fn bit_len(&self) -> Option<u32> {
+        Some(N as u32 * 8)
+    }
+}
+
+impl KeyBits for Vec<u8> {
+    #[inline(always)]
+    fn bit(&self, n: u32) -> bool {
+        descriptor_bit(self, n)
+    }
+
+    #[inline(always)]
+    fn bit_len(&self) -> Option<u32> {
+        Some(self.len() as u32 * 8)
+    }
+}
+
+#[cfg(feature = "bitvec")]
+impl<T, O> KeyBits for bitvec::slice::BitSlice<T, O>
+where
+    T: bitvec::store::BitStore,
+    O: bitvec::order::BitOrder,
+{
+    #[inline(always)]
+    fn bit(&self, n: u32) -> bool {
+        self[n as usize]
+    }
+
+    #[inline(always)]
+    fn bit_len(&self) -> Option<u32> {
+        Some(self.len() as u32)
+    }
+}
+
+#[cfg(feature = "bitvec")]
+impl<T, O> KeyBits for bitvec::vec::BitVec<T, O>
+where
+    T: bitvec::store::BitStore,
+    O: bitvec::order::BitOrder,
+{
+    #[inline(always)]
+    fn bit(&self, n: u32) -> bool {
+        self[n as usize]
+    }
+
+    #[inline(always)]
+    fn bit_len(&self) -> Option<u32> {
+        Some(self.len() as u32)
+    }
+}
+
+/// A key which can be rebuilt from its bits, numbered the same way as by `KeyBits`.
+///
+/// This is how `BinTrieOwnedKeys` gives back the keys it stores.
+pub trait FromKeyBits: KeyBits + Sized {
+    /// Rebuilds a key from its first `len` bits, where `B(n)` provides the `n`th bit.
+    ///
+    /// Any other bits of the key are `false`.
+    fn from_bits<B>(len: u32, bit: B) -> Self
+    where
+        B: FnMut(u32) -> bool;
+}
+
+macro_rules! from_bits_int {
+    ($($int:ty),*) => {$(
+        impl FromKeyBits for $int {
+            fn from_bits<B>(len: u32, mut bit: B) -> Self
+            where
+                B: FnMut(u32) -> bool,
+            {
+                (0..len.min(<$int>::BITS))
+                    .filter(|&n| bit(n))
+                    .fold(0, |key, n| key | 1 << n)
+            }
+        }
+    )*};
+}
+
+from_bits_int!(u32, u64, u128);
+
+/// Sets the first `len` bits of a descriptor, like `descriptor_bit` reads them.
+fn fill_descriptor<B>(descriptor: &mut [u8], len: u32, mut bit: B)
+where
+    B: FnMut(u32) -> bool,
+{
+    for n in 0..len.min(descriptor.len() as u32 * 8) {
+        descriptor[n as usize / 8] |= (bit(n) as u8) << (7 - n % 8);
+    }
+}
+
+impl<const N: usize> FromKeyBits for [u8; N] {
+    fn from_bits<B>(len: u32, bit: B) -> Self
+    where
+        B: FnMut(u32) -> bool,
+    {
+        let mut key = [0; N];
+        fill_descriptor(&mut key, len, bit);
+        key
+    }
+}
+
+impl FromKeyBits for Vec<u8> {
+    /// Rebuilds just enough bytes to hold `len` bits.
+    fn from_bits<B>(len: u32, bit: B) -> Self
+    where
+        B: FnMut(u32) -> bool,
+    {
+        let mut key = alloc::vec![0; len.div_ceil(8) as usize];
+        fill_descriptor(&mut key, len, bit);
+        key
+    }
+}
+
+impl BinTrie {
+    /// Inserts an item with a `key` that provides its own bits.
+    ///
+    /// `L(item)` - A function that gets the key of a previously inserted item.
+    ///
+    /// This works like `insert`, except that the same `KeyBits` implementation
+    /// provides the bits of both the new key and previously inserted keys, so
+    /// they cannot disagree about how bits are numbered.
+    ///
+    /// Returns `Some` of a replaced leaf if a leaf was replaced, otherwise None.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let keys: Vec<u64> = vec![0xDEAD, 0xBEEF, 0xCAFE];
+    /// let mut trie = BinTrie::new_depth(64);
+    /// for (n, key) in keys.iter().enumerate() {
+    ///     trie.insert_key(n as u32, key, |item| keys[item as usize]);
+    /// }
+    /// assert_eq!(trie.get_key(&0xBEEFu64), Some(1));
+    /// ```
+    pub fn insert_key<Q, L, T>(&mut self, item: u32, key: &Q, mut item_key: L) -> Option<u32>
+    where
+        Q: KeyBits + ?Sized,
+        L: FnMut(u32) -> T,
+        T: KeyBits,
+    {
+        self.insert(item, |n| key.bit(n), |item, n| item_key(item).bit(n))
+    }
+
+    /// Perform a lookup for a particular item with a `key` that provides its own bits.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let keys: [&[u8]; 2] = [b"ab", b"ba"];
+    /// let mut trie = BinTrie::new_depth(16);
+    /// for (n, key) in keys.iter().enumerate() {
+    ///     trie.insert_key(n as u32, *key, |item| keys[item as usize]);
+    /// }
+    /// assert_eq!(trie.get_key(&b"ba"[..]), Some(1));
+    /// ```
+    pub fn get_key<Q>(&self, key: &Q) -> Option<u32>
+    where
+        Q: KeyBits + ?Sized,
+    {
+        self.get(|n| key.bit(n))
+    }
+}
+
+/// Chooses the depth of a trie built from `keys`.
+///
+/// This is the length of the longest key, or `8192` like `BinTrie::new` if there
+/// are no keys or any key does not know its length.
+pub(crate) fn collected_depth<'a, K, I>(keys: I) -> u32
+where
+    K: KeyBits + 'a,
+    I: IntoIterator<Item = &'a K>,
+{
+    keys.into_iter()
+        .try_fold(0, |depth, key| Some(depth.max(key.bit_len()?)))
+        .filter(|&depth| depth > 0)
+        .unwrap_or(8192)
+}
+
+/// Builds a trie from pairs of items and their keys.
+///
+/// The depth of the trie is the length of the longest key when every key knows its
+/// length, otherwise it is `8192`. Keys must be able to provide every bit up to
+/// the depth that is needed to tell them apart. Like `insert`, a later item with
+/// the same key replaces an earlier one, and each item should only appear once.
+///
+/// Only `BinTrieMap` implements `Extend`. A `BinTrie` doesn't keep the keys of its
+/// items, and inserting needs them, so add pairs to a collected trie with
+/// `insert_key` and a lookup from items to their keys instead.
+///
+/// ```
+/// # use bintrie::BinTrie;
+/// let keys = [0xDEADu64, 0xBEEF, 0xCAFE, 0xF00D];
+/// let mut trie: BinTrie = keys[..3].iter().enumerate().map(|(n, &key)| (n as u32, key)).collect();
+/// assert_eq!(trie.depth(), 64);
+/// assert_eq!(trie.get_key(&0xCAFEu64), Some(2));
+/// trie.insert_key(3, &keys[3], |item| keys[item as usize]);
+/// assert_eq!(trie.get_key(&0xF00Du64), Some(3));
+/// ```
+impl<K> FromIterator<(u32, K)> for BinTrie
+where
+    K: KeyBits,
+{
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = (u32, K)>,
+    {
+        let pairs: Vec<(u32, K)> = iter.into_iter().collect();
+        let mut trie = BinTrie::new_depth(collected_depth(pairs.iter().map(|(_, key)| key)));
+        // The index in `pairs` of the key of every inserted item.
+        let mut keys: FastMap<u32, usize> = FastMap::new();
+        for (index, (item, key)) in pairs.iter().enumerate() {
+            trie.insert(*item, |n| key.bit(n), |item, n| pairs[keys[&item]].1.bit(n));
+            keys.insert(*item, index);
+        }
+        trie
+    }
+}
@@ -0,0 +1,155 @@
+use crate::distance::{HammingDistance, PathDistance};
+use crate::{BinTrie, CompiledHeuristic, Heuristic, QueryScratch, Side, HIGH};
+use alloc::collections::BinaryHeap;
+use alloc::vec::Vec;
+use core::cmp::Reverse;
+
+/// Something waiting in the queue of `knn_by`, ordered so that items come before
+/// nodes with the same distance.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Waiting {
+    /// An item whose exact distance is known.
+    Item(u32),
+    /// An internal node and its level, whose distance is a lower bound.
+    Node(u32, u32),
+}
+
+impl BinTrie {
+    /// Finds the `k` items nearest to `target` in Hamming distance.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the target key.
+    /// `F(item, n)` - A function that must be able to look up the nth bit
+    ///    from a previously inserted item.
+    ///
+    /// Unlike `explore`, this is exact: subtrees are visited in the order of the
+    /// lower bound of their distance, so a subtree is only skipped once `k` items
+    /// that are at least as near have been found. Returns the items along with their
+    /// exact distance, nearest first. Items at the same distance are in no particular order.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let mut trie = BinTrie::new_depth(8);
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// for &n in &[0b0000_0000, 0b1000_0000, 0b0000_0011, 0b1111_1111] {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// let nearest = trie.knn(|l| lookup(0b1000_0001, l), 2, lookup);
+    /// assert_eq!(nearest, vec![(0b1000_0000, 1), (0b0000_0000, 2)]);
+    /// ```
+    pub fn knn<K, F>(&self, target: K, k: usize, lookup: F) -> Vec<(u32, u32)>
+    where
+        K: FnMut(u32) -> bool,
+        F: FnMut(u32, u32) -> bool,
+    {
+        self.knn_by(HammingDistance::new(target, lookup), k)
+    }
+
+    /// Finds the `k` items nearest to a key like `knn`, but with any `metric`.
+    pub fn knn_by<D>(&self, metric: D, k: usize) -> Vec<(u32, u32)>
+    where
+        D: PathDistance,
+    {
+        let mut scratch = QueryScratch::<CompiledHeuristic>::new();
+        self.knn_by_in(metric, k, &mut scratch);
+        scratch.results
+    }
+
+    /// Finds the `k` items nearest to `target` like `knn`, but keeps the queue and
+    /// the results in `scratch`.
+    ///
+    /// The results are only valid until the scratch is used again.
+    pub fn knn_in<'s, K, F, H>(
+        &self,
+        target: K,
+        k: usize,
+        lookup: F,
+        scratch: &'s mut QueryScratch<H>,
+    ) -> &'s [(u32, u32)]
+    where
+        K: FnMut(u32) -> bool,
+        F: FnMut(u32, u32) -> bool,
+        H: Heuristic,
+    {
+        self.knn_by_in(HammingDistance::new(target, lookup), k, scratch)
+    }
+
+    /// Finds the `k` items nearest to a key like `knn_by`, but keeps the queue and
+    /// the results in `scratch`.
+    pub fn knn_by_in<'s, D, H>(
+        &self,
+        mut metric: D,
+        k: usize,
+        scratch: &'s mut QueryScratch<H>,
+    ) -> &'s [(u32, u32)]
+    where
+        D: PathDistance,
+        H: Heuristic,
+    {
+        let QueryScratch {
+            queue,
+            best,
+            results,
+            ..
+        } = scratch;
+        results.clear();
+        if k == 0 {
+            return results;
+        }
+        queue.clear();
+        queue.push(Reverse((0, Waiting::Node(0, 0))));
+        // The exact distances of the `k` nearest items found so far, farthest on top.
+        best.clear();
+        let found = |best: &mut BinaryHeap<u32>, distance: u32| {
+            if best.len() < k {
+                best.push(distance);
+            } else if best.peek().is_some_and(|&worst| distance < worst) {
+                best.pop();
+                best.push(distance);
+            }
+        };
+        while let Some(Reverse((distance, waiting))) = queue.pop() {
+            let (index, level) = match waiting {
+                Waiting::Item(item) => {
+                    results.push((item, distance));
+                    if results.len() == k {
+                        break;
+                    }
+                    continue;
+                }
+                Waiting::Node(index, level) => (index, level),
+            };
+            // Only follow the first slot that reaches each node of a suspect trie, and stop at the depth.
+            if level >= self.depth {
+                continue;
+            }
+            let children = self.children_checked(index as usize);
+            for (&side, &n) in Side::BOTH.iter().zip(children.iter()) {
+                if n == 0 {
+                    continue;
+                }
+                let distance = metric.branch(level, side, distance);
+                // Nothing beneath can be nearer than the `k` items found already.
+                if best.len() == k && best.peek().is_some_and(|&worst| distance > worst) {
+                    continue;
+                }
+                if n & HIGH != 0 {
+                    let item = n & !HIGH;
+                    let distance = metric.leaf(item, level + 1..self.depth, distance);
+                    found(best, distance);
+                    queue.push(Reverse((distance, Waiting::Item(item))));
+                    for &item in self.bucket(item) {
+                        found(best, distance);
+                        queue.push(Reverse((distance, Waiting::Item(item))));
+                    }
+                } else {
+                    if let Some(item) = self.terminal(n) {
+                        found(best, distance);
+                        queue.push(Reverse((distance, Waiting::Item(item))));
+                    }
+                    queue.push(Reverse((distance, Waiting::Node(n, level + 1))));
+                }
+            }
+        }
+        results
+    }
+}
@@ -0,0 +1,235 @@
+use crate::explore::{accept_all, ExploreIter};
+use crate::{Child, FilterHeuristic, IntoHeuristic, Nodes};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Marks a 64-bit child as a leaf.
+const LEAF: u64 = 1 << 63;
+
+/// A trie with 64-bit child indices, for more than `2^31` internal nodes.
+///
+/// A `BinTrie` stores its children as `u32`s with the high bit marking leaves,
+/// so it can't grow past `2^31` internal nodes, and `insert` panics once it
+/// runs out. This works like `BinTrie`, except that children are `u64`s, so
+/// the only limit is memory. Since the tag bit no longer shares space with the
+/// item, every `u32` can be stored as an item.
+///
+/// Each internal node takes twice the memory of one in a `BinTrie`, so only
+/// use this when a `BinTrie` would run out of indices.
+///
+/// ```
+/// # use bintrie::LargeTrie;
+/// let mut trie = LargeTrie::new_depth(32);
+/// let lookup = |n: u32, l: u32| n >> (31 - l) & 1 == 1;
+/// for &n in &[0, 7, u32::MAX] {
+///     trie.insert(n, |l| lookup(n, l), lookup);
+/// }
+/// assert_eq!(trie.get(|l| lookup(u32::MAX, l)), Some(u32::MAX));
+/// assert_eq!(trie.items().collect::<Vec<_>>(), vec![0, 7, u32::MAX]);
+/// ```
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LargeTrie {
+    /// The root node is always at index `0`.
+    internals: Vec<[u64; 2]>,
+    /// The maximum depth to stop at.
+    depth: u32,
+    /// The number of items in the trie.
+    len: usize,
+}
+
+impl Default for LargeTrie {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LargeTrie {
+    /// Makes a new trie with a maximum `depth` of `8192`.
+    pub fn new() -> Self {
+        Self::new_depth(8192)
+    }
+
+    /// Makes a new trie with a given maximum `depth`.
+    pub fn new_depth(depth: u32) -> Self {
+        assert!(depth > 0);
+        Self {
+            internals: vec![[0; 2]],
+            depth,
+            len: 0,
+        }
+    }
+
+    /// The maximum depth of the trie.
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    /// The number of items in the trie.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Checks if the trie has no items.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The number of internal nodes, including the root.
+    pub fn node_count(&self) -> usize {
+        self.internals.len()
+    }
+
+    /// Inserts an item.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the key.
+    /// `F(item, n)` - A function that must be able to look up the nth bit
+    ///    from a previously inserted item.
+    ///
+    /// Returns `Some` of a replaced leaf if a leaf was replaced, otherwise None.
+    pub fn insert<K, F>(&mut self, item: u32, mut key: K, mut lookup: F) -> Option<u32>
+    where
+        K: FnMut(u32) -> bool,
+        F: FnMut(u32, u32) -> bool,
+    {
+        let leaf = LEAF | u64::from(item);
+        let mut index = 0;
+        for i in 0..self.depth - 1 {
+            let position = key(i) as usize;
+            match self.internals[index][position] {
+                // Empty node encountered.
+                0 => {
+                    self.internals[index][position] = leaf;
+                    self.len += 1;
+                    return None;
+                }
+                // Leaf node encountered.
+                m if m & LEAF != 0 => {
+                    let mut new_internal = [0; 2];
+                    new_internal[lookup(m as u32, i + 1) as usize] = m;
+                    let new_index = self.internals.len() as u64;
+                    self.internals.push(new_internal);
+                    self.internals[index][position] = new_index;
+                    index = new_index as usize;
+                }
+                // Internal node encountered.
+                m => index = m as usize,
+            }
+        }
+        let position = key(self.depth - 1) as usize;
+        match core::mem::replace(&mut self.internals[index][position], leaf) {
+            0 => {
+                self.len += 1;
+                None
+            }
+            m => Some(m as u32),
+        }
+    }
+
+    /// Perform a lookup for a particular item.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the key.
+    pub fn get<K>(&self, mut key: K) -> Option<u32>
+    where
+        K: FnMut(u32) -> bool,
+    {
+        let mut index = 0;
+        for i in 0..self.depth {
+            match self.internals[index][key(i) as usize] {
+                // Empty node encountered.
+                0 => return None,
+                // Leaf node encountered.
+                m if m & LEAF != 0 => return Some(m as u32),
+                // Internal node encountered.
+                m => index = m as usize,
+            }
+        }
+        None
+    }
+
+    /// Get an iterator over the items added to the trie.
+    pub fn items(&self) -> impl Iterator<Item = u32> + '_ {
+        self.explore(FilterHeuristic(|_| true))
+    }
+
+    /// Explores the trie using a heuristic, like `BinTrie::explore`.
+    ///
+    /// ```
+    /// # use bintrie::{LargeTrie, HammingRadius};
+    /// let mut trie = LargeTrie::new_depth(4);
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// for n in 0..16 {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// let mut found = trie.explore(HammingRadius::new(0b0101, 1)).collect::<Vec<u32>>();
+    /// found.sort();
+    /// assert_eq!(found, vec![0b0001, 0b0100, 0b0101, 0b0111, 0b1101]);
+    /// ```
+    pub fn explore<'a, H>(&'a self, heuristic: H) -> impl Iterator<Item = u32> + 'a
+    where
+        H: IntoHeuristic,
+        H::Heuristic: 'a,
+    {
+        ExploreIter::new(self, heuristic.into_heuristic(), vec![], accept_all)
+    }
+}
+
+/// Decodes a 64-bit child.
+#[inline(always)]
+fn unpack(n: u64) -> Child<u64> {
+    match n {
+        0 => Child::Empty,
+        n if n & LEAF != 0 => Child::Leaf(n as u32),
+        n => Child::Internal(n),
+    }
+}
+
+impl Nodes for &LargeTrie {
+    type Index = u64;
+
+    #[inline(always)]
+    fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    #[inline(always)]
+    fn root(&self) -> u64 {
+        0
+    }
+
+    #[inline(always)]
+    fn child(&self, index: u64, position: usize) -> Child<u64> {
+        unpack(self.internals[index as usize][position])
+    }
+
+    #[inline(always)]
+    unsafe fn child_unchecked(&self, index: u64, position: usize) -> Child<u64> {
+        // Deserialized tries are not validated, so the bounds are still checked.
+        self.child(index, position)
+    }
+
+    #[inline(always)]
+    fn terminal(&self, _index: u64) -> Option<u32> {
+        // Keys can't be terminated early.
+        None
+    }
+
+    #[inline(always)]
+    fn child_checked(&self, index: u64, position: usize) -> Child<u64> {
+        self.internals
+            .get(index as usize)
+            .map_or(Child::Empty, |internal| unpack(internal[position]))
+    }
+
+    #[inline(always)]
+    fn bucket_item(&self, _item: u32, _position: usize) -> Option<u32> {
+        // Every leaf holds a single item.
+        None
+    }
+
+    #[inline(always)]
+    fn is_verified(&self) -> bool {
+        // A child that is out of range panics in `child_unchecked` instead.
+        true
+    }
+}
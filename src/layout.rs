@@ -0,0 +1,229 @@
+use crate::validate::{slot, UNREACHED};
+use crate::{BinTrie, Child, Internal};
+use alloc::vec;
+use alloc::vec::Vec;
+
+impl BinTrie {
+    /// Reorders the internal nodes into a van Emde Boas layout for read-only use.
+    ///
+    /// The trie is cut in half by level, the top half is laid out first, and then each
+    /// subtree hanging off the bottom of it, with each half laid out the same way in
+    /// turn. Every path from the root then crosses only a few cache lines and pages,
+    /// whatever their size, so lookups miss the cache far less often than with nodes
+    /// in insertion order. Unlike `reorganize`, there is nothing to tune, and the
+    /// nodes are moved in place rather than copied into a `HotColdTrie`.
+    ///
+    /// This changes where internal nodes are stored, so any `InsertHint` filled in
+    /// before optimizing starts over from the root the next time it is used. The table
+    /// of `build_top_table` and the counts of `build_subtree_counts` are kept up to date.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let mut trie = BinTrie::new_depth(24);
+    /// let lookup = |n: u32, l: u32| n.wrapping_mul(0x9E37_79B9) >> l & 1 == 1;
+    /// for n in 0..1000 {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// let before = trie.items().collect::<Vec<u32>>();
+    /// let nodes = trie.node_count();
+    /// trie.optimize_layout();
+    /// assert_eq!(trie.items().collect::<Vec<u32>>(), before);
+    /// assert_eq!(trie.node_count(), nodes);
+    /// for n in 0..1000 {
+    ///     assert_eq!(trie.get(|l| lookup(n, l)), Some(n));
+    /// }
+    /// ```
+    pub fn optimize_layout(&mut self) {
+        self.assert_trusted();
+        // Find how many levels of internal nodes there are.
+        let mut height = 0;
+        let mut stack = vec![(0, 1)];
+        while let Some((index, level)) = stack.pop() {
+            height = height.max(level);
+            stack.extend(
+                self.internal_children(index)
+                    .map(|child| (child, level + 1)),
+            );
+        }
+        let mut order = Vec::with_capacity(self.internals.len() - self.free.len());
+        self.van_emde_boas(0, height, &mut order);
+        self.permute(&order);
+    }
+
+    /// Adds the internal nodes in the `height` levels from `root` down to `order`
+    /// in a van Emde Boas layout.
+    fn van_emde_boas(&self, root: u32, height: u32, order: &mut Vec<u32>) {
+        if height == 1 {
+            order.push(root);
+            return;
+        }
+        let top = height / 2;
+        self.van_emde_boas(root, top, order);
+        // The bottom subtrees start `top` levels beneath the root, and are laid out from left to right.
+        let mut stack = vec![(root, 0)];
+        while let Some((index, level)) = stack.pop() {
+            if level == top {
+                self.van_emde_boas(index, height - top, order);
+            } else {
+                stack.extend(
+                    self.internal_children(index)
+                        .rev()
+                        .map(|child| (child, level + 1)),
+                );
+            }
+        }
+    }
+
+    /// Drops every internal node that is no longer used and closes the gaps they left.
+    ///
+    /// Removing items leaves collapsed internal nodes behind. Those are reused by later
+    /// insertions, but if few insertions follow, this can be used to shrink the trie
+    /// instead. The remaining nodes are stored in depth-first order.
+    ///
+    /// This changes where internal nodes are stored, so any `InsertHint` filled in
+    /// before compacting starts over from the root the next time it is used. The table
+    /// of `build_top_table` and the counts of `build_subtree_counts` are kept up to date.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let mut trie = BinTrie::new_depth(16);
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// for n in 0..1000 {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// for n in 0..900 {
+    ///     trie.remove(|l| lookup(n, l), lookup);
+    /// }
+    /// let mut fresh = BinTrie::new_depth(16);
+    /// for n in 900..1000 {
+    ///     fresh.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// trie.build_top_table(4);
+    /// trie.build_subtree_counts();
+    /// trie.compact();
+    /// assert!(trie.structurally_equal(&fresh));
+    /// assert_eq!(trie.node_count(), fresh.node_count());
+    /// assert!(trie.has_top_table() && trie.has_subtree_counts());
+    /// for n in 900..1000 {
+    ///     assert_eq!(trie.get(|l| lookup(n, l)), Some(n));
+    /// }
+    /// assert_eq!(trie.count_under_prefix(0, 0, lookup), 100);
+    /// assert_eq!(trie.count_under_prefix(0b1, 1, lookup), 50);
+    /// ```
+    pub fn compact(&mut self) {
+        self.assert_trusted();
+        let mut order = Vec::with_capacity(self.internals.len() - self.free.len());
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            order.push(index);
+            stack.extend(self.internal_children(index).rev());
+        }
+        self.permute(&order);
+    }
+
+    /// The number of internal nodes being stored, including ones that can be reused.
+    pub fn node_count(&self) -> usize {
+        self.internals.len()
+    }
+
+    /// The number of internal nodes that can be stored without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.internals.capacity()
+    }
+
+    /// Makes room for at least `additional` more internal nodes.
+    pub fn reserve(&mut self, additional: usize) {
+        self.internals.reserve(additional);
+    }
+
+    /// Gives back the memory that the node storage holds beyond what it uses.
+    ///
+    /// Internal nodes that were left behind by removals are still kept for reuse,
+    /// so use `compact` first to get rid of those as well.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let mut trie = BinTrie::new_depth(16);
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// trie.reserve(10_000);
+    /// for n in 0..100 {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// trie.shrink_to_fit();
+    /// assert!(trie.capacity() < 10_000);
+    /// assert!(trie.capacity() >= trie.node_count());
+    /// ```
+    pub fn shrink_to_fit(&mut self) {
+        self.internals.shrink_to_fit();
+        self.free.shrink_to_fit();
+        if let Some(order) = &mut self.insertion_order {
+            order.shrink_to_fit();
+        }
+    }
+
+    /// Gets the indices of the internal children of the internal node at `index`.
+    pub(crate) fn internal_children(&self, index: u32) -> impl DoubleEndedIterator<Item = u32> {
+        let children = self.internals[index as usize].0;
+        IntoIterator::into_iter(children).filter_map(|n| match Child::unpack(n) {
+            Child::Internal(m) => Some(m),
+            Child::Empty | Child::Leaf(_) => None,
+        })
+    }
+
+    /// Moves the internal nodes so that the node at `order[i]` ends up at index `i`.
+    ///
+    /// `order` must start with the root, and any node missing from it is dropped.
+    /// The table of `build_top_table`, the counts of `build_subtree_counts` and the
+    /// nodes that are suspect are kept, with their indices moved along with the nodes.
+    pub(crate) fn permute(&mut self, order: &[u32]) {
+        assert_eq!(order.first(), Some(&0));
+        // Anything that would be reused is dropped, and recorded paths become stale.
+        self.free.clear();
+        self.generation += 1;
+        let mut new_index = vec![u32::MAX; self.internals.len()];
+        for (new, &old) in order.iter().enumerate() {
+            new_index[old as usize] = new as u32;
+        }
+        let moved = |n: u32| match Child::unpack(n) {
+            Child::Internal(m) => new_index[m as usize],
+            Child::Empty | Child::Leaf(_) => n,
+        };
+        self.internals = order
+            .iter()
+            .map(|&old| Internal(self.internals[old as usize].0.map(moved)))
+            .collect();
+        self.terminals = core::mem::take(&mut self.terminals)
+            .into_iter()
+            .map(|(index, item)| (new_index[index as usize], item))
+            .collect();
+        if let Some(top) = &mut self.top {
+            for slot in &mut top.slots {
+                *slot = moved(*slot);
+            }
+        }
+        if let Some(counts) = &mut self.counts {
+            *counts = order.iter().map(|&old| counts[old as usize]).collect();
+        }
+        if let Some(accesses) = &mut self.accesses {
+            accesses.permute(order);
+        }
+        if !self.suspect.is_empty() {
+            let kept = |old: u64| new_index.get(old as usize).filter(|&&new| new != u32::MAX);
+            self.suspect = self
+                .suspect
+                .iter()
+                .filter_map(|&old| kept(u64::from(old)).copied())
+                .collect();
+            self.suspect.sort_unstable();
+            // The slots that first reach each node belong to the parents, which moved too.
+            self.reached_by = order
+                .iter()
+                .map(|&old| match self.reached_by.get(old as usize) {
+                    Some(&by) if by != UNREACHED => kept(by / 2)
+                        .map_or(UNREACHED, |&parent| slot(parent as usize, by as usize % 2)),
+                    _ => UNREACHED,
+                })
+                .collect();
+        }
+    }
+}
@@ -1,10 +1,141 @@
-mod heuristic;
+//! A binary trie of items, which does not store their keys.
+//!
+//! The crate is `no_std` and only needs `alloc` when the default `std` feature is
+//! disabled. Without `std`, the binary format of `BinTrie::write_to` is unavailable
+//! and maps keyed by item are ordered maps rather than hash maps.
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+mod advisor;
+mod batch;
+#[cfg(feature = "std")]
+mod binary;
+mod borrowed;
+mod bucket;
+mod build;
+mod child;
+mod compressed;
+mod concurrent;
+mod descriptor;
+mod distance;
+mod dot;
+mod drain;
+mod entry;
+mod error;
+pub mod eval;
+mod explore;
+mod forest;
+mod frozen;
+mod gray;
+mod group;
+mod hashed;
+pub mod heuristic;
+mod holes;
+mod hotcold;
+mod inline;
+mod into_items;
+mod keys;
+mod knn;
+mod large;
+mod layout;
+mod map;
+mod merge;
+mod multi;
+mod owned;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod persistent;
+mod prefix;
+mod range;
+mod relocate;
+mod remove;
+mod repair;
+#[cfg(feature = "rkyv")]
+mod rkyv_support;
+mod rotating;
+mod sample;
+mod scored;
+mod scratch;
+#[cfg(feature = "serde")]
+mod serde_support;
+mod setops;
+mod side;
+mod split;
+mod stats;
+mod terminated;
+mod top;
+mod trace;
+mod trie64;
+mod validate;
+mod wide;
+
+pub use advisor::CompressionAdvice;
+pub use borrowed::BinTrieRef;
+pub use build::SortedBuilder;
+pub use child::Child;
+pub use compressed::CompressedTrie;
+pub use concurrent::ConcurrentBinTrie;
+pub use distance::{HammingDistance, PathDistance, SpillPolicy};
+pub use drain::Drain;
+pub use entry::{Entry, OccupiedEntry, VacantEntry};
+pub use error::*;
+pub use explore::{BudgetedExplore, ExploreFrame, FilteredExplore};
+pub use forest::TrieForest;
+pub use frozen::FrozenTrie;
+pub use gray::GrayKey;
+pub use hashed::{HashedBits, HashedKey};
 pub use heuristic::*;
+pub use hotcold::HotColdTrie;
+pub use inline::{InlineTrie, InlineValue};
+pub use into_items::IntoItems;
+pub use keys::{FromKeyBits, KeyBits};
+pub use large::LargeTrie;
+pub use map::BinTrieMap;
+pub use multi::MultiExplore;
+pub use owned::BinTrieOwnedKeys;
+pub use persistent::PersistentBinTrie;
+pub use repair::RepairReport;
+pub use rotating::RotatingTrie;
+pub use scratch::{BatchScratch, QueryScratch};
+pub use setops::DiffEntry;
+pub use side::*;
+pub use stats::TrieStats;
+pub use trace::VisitEvent;
+pub use trie64::BinTrie64;
+pub use validate::LoadMode;
+pub use wide::WideTrie;
+
+use explore::ExploreIter;
+use group::GroupIter;
+use hotcold::AccessCounts;
+use range::RangeIter;
+use top::TopTable;
+use trace::TraceIter;
 
 const HIGH: u32 = 0x8000_0000;
 
-use std::slice;
+/// The map used for data keyed by items, which is ordered without `std`.
+#[cfg(feature = "std")]
+type FastMap<K, V> = std::collections::HashMap<K, V>;
+#[cfg(not(feature = "std"))]
+type FastMap<K, V> = alloc::collections::BTreeMap<K, V>;
+
+/// The set used for items and locations, which is ordered without `std`.
+#[cfg(feature = "std")]
+type FastSet<T> = std::collections::HashSet<T>;
+#[cfg(not(feature = "std"))]
+type FastSet<T> = alloc::collections::BTreeSet<T>;
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::hash::Hash;
+use core::ops::Deref;
+use core::slice;
+use core::sync::atomic::AtomicBool;
 
 /// Contains a list of 2 children node IDs.
 ///
@@ -12,15 +143,195 @@ use std::slice;
 /// leaf node.
 ///
 /// If a child is `0` then it is empty because the root node can never be pointed to.
+/// Use `Child` to decode them.
 #[derive(Copy, Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
+#[repr(transparent)]
 struct Internal([u32; 2]);
 
+/// Read access to the nodes of a trie, wherever they are stored.
+///
+/// This lets the iterators run over both owned tries and borrowed ones.
+pub(crate) trait Nodes {
+    /// Identifies an internal node, which is its index for tries stored in an array.
+    type Index: Copy + Ord + Hash;
+    /// The depth of the trie.
+    fn depth(&self) -> u32;
+    /// Gets the root internal node.
+    fn root(&self) -> Self::Index;
+    /// Gets the child at `position` of the internal node at `index`.
+    fn child(&self, index: Self::Index, position: usize) -> Child<Self::Index>;
+    /// Gets a child like `child`, but without checking bounds.
+    ///
+    /// # Safety
+    ///
+    /// `index` must be an internal node of the trie and `position` must be `0` or `1`.
+    unsafe fn child_unchecked(&self, index: Self::Index, position: usize) -> Child<Self::Index>;
+    /// Gets the item whose key terminates at the internal node at `index`.
+    fn terminal(&self, index: Self::Index) -> Option<u32>;
+    /// Gets a child like `child`, but treats a node that is out of range as empty.
+    fn child_checked(&self, index: Self::Index, position: usize) -> Child<Self::Index>;
+    /// Gets the item at `position` in the bucket of the leaf `item`, which shares its whole key.
+    fn bucket_item(&self, item: u32, position: usize) -> Option<u32>;
+    /// Checks if the nodes are known to uphold the invariants that unchecked traversals rely on.
+    ///
+    /// Otherwise traversals must use `child_checked` and stop at the depth of the trie.
+    fn is_verified(&self) -> bool;
+}
+
+impl<T> Nodes for T
+where
+    T: Deref<Target = BinTrie>,
+{
+    type Index = u32;
+
+    #[inline(always)]
+    fn depth(&self) -> u32 {
+        self.deref().depth
+    }
+
+    #[inline(always)]
+    fn root(&self) -> u32 {
+        0
+    }
+
+    #[inline(always)]
+    fn child(&self, index: u32, position: usize) -> Child {
+        Child::unpack(self.deref().internals[index as usize].0[position])
+    }
+
+    #[inline(always)]
+    unsafe fn child_unchecked(&self, index: u32, position: usize) -> Child {
+        Child::unpack(
+            *self
+                .deref()
+                .internals
+                .get_unchecked(index as usize)
+                .0
+                .get_unchecked(position),
+        )
+    }
+
+    #[inline(always)]
+    fn terminal(&self, index: u32) -> Option<u32> {
+        self.deref().terminal(index)
+    }
+
+    #[inline(always)]
+    fn child_checked(&self, index: u32, position: usize) -> Child {
+        Child::unpack(self.deref().child_checked(index as usize, position))
+    }
+
+    #[inline(always)]
+    fn bucket_item(&self, item: u32, position: usize) -> Option<u32> {
+        self.deref().bucket(item).get(position).copied()
+    }
+
+    #[inline(always)]
+    fn is_verified(&self) -> bool {
+        !self.deref().is_suspect()
+    }
+}
+
+/// A binary trie of items, which does not store their keys.
+///
+/// With the `serde` feature the trie can be serialized. It is validated when it is
+/// deserialized, so an untrusted payload can't make lookups go out of bounds.
+///
+/// ```
+/// # #[cfg(feature = "serde")]
+/// # {
+/// # use bintrie::BinTrie;
+/// let mut trie = BinTrie::new_depth(8);
+/// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+/// for n in 0..16 {
+///     trie.insert(n, |l| lookup(n, l), lookup);
+/// }
+/// let json = serde_json::to_string(&trie).unwrap();
+/// let decoded: BinTrie = serde_json::from_str(&json).unwrap();
+/// assert!(decoded.structurally_equal(&trie));
+/// assert_eq!(decoded.len(), 16);
+/// // A child that points out of bounds is rejected.
+/// let corrupt = json.replacen("[1,", "[99,", 1);
+/// assert!(serde_json::from_str::<BinTrie>(&corrupt).is_err());
+/// # }
+/// ```
+///
+/// With the `rkyv` feature the trie can be archived. An `ArchivedBinTrie` can be
+/// queried directly from the bytes of the archive without deserializing it, and is
+/// validated when it is accessed.
+///
+/// There is no `LoadMode` for archives. `rkyv::access` either verifies the whole
+/// archive or rejects it, and an `&ArchivedBinTrie` points straight into the bytes,
+/// so there is nowhere to keep the suspect nodes that a lenient load would find.
+/// Use `BinTrieRef::from_bytes_with` or `BinTrie::read_from_with` to load a partially
+/// corrupt trie.
+///
+/// ```
+/// # #[cfg(feature = "rkyv")]
+/// # {
+/// # use bintrie::{ArchivedBinTrie, BinTrie, FilterHeuristic, Side};
+/// let mut trie = BinTrie::new_depth(8);
+/// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+/// for n in 0..16 {
+///     trie.insert(n, |l| lookup(n, l), lookup);
+/// }
+/// let bytes = rkyv::to_bytes::<rkyv::rancor::Error>(&trie).unwrap();
+/// let archived = rkyv::access::<ArchivedBinTrie, rkyv::rancor::Error>(&bytes).unwrap();
+/// assert_eq!(archived.get(|l| lookup(5, l)), Some(5));
+/// assert_eq!(archived.items().collect::<Vec<u32>>(), trie.items().collect::<Vec<u32>>());
+/// let left = archived.explore(FilterHeuristic(|s| s == Side::Left)).collect::<Vec<u32>>();
+/// assert_eq!(left, vec![0]);
+/// let decoded = rkyv::deserialize::<BinTrie, rkyv::rancor::Error>(archived).unwrap();
+/// assert!(decoded.structurally_equal(&trie));
+/// # }
+/// ```
 #[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    rkyv(bytecheck(verify))
+)]
 pub struct BinTrie {
     /// The root node is always at index `0`.
     internals: Vec<Internal>,
     /// The maximum depth to stop at.
     depth: u32,
+    /// Items whose keys end at an internal node, keyed by the internal node index.
+    terminals: BTreeMap<u32, u32>,
+    /// The items in the order they were inserted, if it is being tracked.
+    insertion_order: Option<Vec<u32>>,
+    /// User-defined metadata that is kept alongside the trie.
+    metadata: Vec<u8>,
+    /// A table that replaces the top levels of lookups, if one was built.
+    #[cfg_attr(feature = "rkyv", rkyv(with = rkyv::with::Skip))]
+    top: Option<TopTable>,
+    /// The number of items beneath each internal node, if they were counted.
+    #[cfg_attr(feature = "rkyv", rkyv(with = rkyv::with::Skip))]
+    counts: Option<Vec<u32>>,
+    /// The number of items in the trie.
+    len: usize,
+    /// The indices of internal nodes that were removed and can be reused.
+    free: Vec<u32>,
+    /// The other items sharing the whole key of a leaf, keyed by the leaf item, if bucketing.
+    buckets: Option<FastMap<u32, Vec<u32>>>,
+    /// Internal nodes with children that break the invariants of the trie, if it was loaded leniently.
+    #[cfg_attr(feature = "rkyv", rkyv(with = rkyv::with::Skip))]
+    suspect: Vec<u32>,
+    /// The child slot that first reaches each internal node, as `2 * index + position`,
+    /// if the trie is suspect.
+    #[cfg_attr(feature = "rkyv", rkyv(with = rkyv::with::Skip))]
+    reached_by: Vec<u64>,
+    /// Bumped whenever internal nodes are freed or moved, so stale `InsertHint`s are noticed.
+    #[cfg_attr(feature = "rkyv", rkyv(with = rkyv::with::Skip))]
+    generation: u64,
+    /// How many lookups passed through each internal node, if they are being counted.
+    #[cfg_attr(feature = "rkyv", rkyv(with = rkyv::with::Skip))]
+    accesses: Option<AccessCounts>,
 }
 
 impl BinTrie {
@@ -45,9 +356,156 @@ impl BinTrie {
         Self {
             internals: vec![Internal::default()],
             depth,
+            terminals: BTreeMap::new(),
+            insertion_order: None,
+            metadata: vec![],
+            top: None,
+            counts: None,
+            len: 0,
+            free: vec![],
+            buckets: None,
+            suspect: vec![],
+            reached_by: vec![],
+            generation: 0,
+            accesses: None,
+        }
+    }
+
+    /// Makes a new trie with a given maximum `depth` and room for `nodes` internal nodes.
+    ///
+    /// Bulk loads that know roughly how many internal nodes they will make can use this
+    /// to avoid growing the node storage over and over. A trie with `n` items with
+    /// random keys needs about `1.44 * n` internal nodes.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let trie = BinTrie::with_capacity(32, 1000);
+    /// assert!(trie.capacity() >= 1000);
+    /// assert!(trie.is_empty());
+    /// ```
+    pub fn with_capacity(depth: u32, nodes: usize) -> Self {
+        let mut trie = Self::new_depth(depth);
+        trie.reserve(nodes.saturating_sub(1));
+        trie
+    }
+
+    /// Rebuilds the trie with a different maximum `depth`.
+    ///
+    /// `F(item, n)` - A function that must be able to look up the nth bit
+    ///    from a previously inserted item.
+    ///
+    /// Every item is inserted again under the new depth. When the depth is made
+    /// shallower, items which only differed in the removed bits collide. In that
+    /// case a `DepthChangeError` is returned which lists every collision along
+    /// with the rebuilt trie.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let mut trie = BinTrie::new_depth(3);
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// for n in &[0b000, 0b011, 0b111] {
+    ///     trie.insert(*n, |l| lookup(*n, l), lookup);
+    /// }
+    /// let trie = trie.with_depth(8, lookup).unwrap();
+    /// let error = trie.with_depth(2, lookup).unwrap_err();
+    /// assert_eq!(error.collisions, vec![(0b011, 0b111)]);
+    /// assert_eq!(error.trie.items().collect::<Vec<u32>>(), vec![0b000, 0b011]);
+    /// ```
+    pub fn with_depth<F>(self, depth: u32, lookup: F) -> Result<BinTrie, DepthChangeError>
+    where
+        F: Fn(u32, u32) -> bool,
+    {
+        let mut trie = BinTrie::new_depth(depth);
+        trie.metadata = self.metadata.clone();
+        let mut collisions = vec![];
+        for item in self.items() {
+            if let Some(kept) = trie.insert(item, |n| lookup(item, n), &lookup) {
+                // Put the first item back so that collisions keep the earlier item.
+                trie.insert(kept, |n| lookup(kept, n), &lookup);
+                collisions.push((kept, item));
+            }
+        }
+        if collisions.is_empty() {
+            Ok(trie)
+        } else {
+            Err(DepthChangeError {
+                collisions,
+                trie: Box::new(trie),
+            })
         }
     }
 
+    /// Joins two tries under a new root, with `left` and `right` as its two children.
+    ///
+    /// Both tries must have the same depth and are treated as though their keys
+    /// were missing the first bit of the joined trie's keys, so the joined trie is
+    /// one level deeper. This allows each half of a trie to be built independently,
+    /// for instance on separate threads.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// // Build each half over the key without its first bit.
+    /// let half = |bit: u32| {
+    ///     let mut trie = BinTrie::new_depth(3);
+    ///     for n in (0..16).filter(|n| n & 1 == bit) {
+    ///         trie.insert(n, |l| lookup(n, l + 1), |n, l| lookup(n, l + 1));
+    ///     }
+    ///     trie
+    /// };
+    /// let trie = BinTrie::join(half(0), half(1));
+    /// for n in 0..16 {
+    ///     assert_eq!(trie.get(|l| lookup(n, l)), Some(n));
+    /// }
+    /// ```
+    pub fn join(left: BinTrie, right: BinTrie) -> BinTrie {
+        left.assert_trusted();
+        right.assert_trusted();
+        assert_eq!(left.depth, right.depth);
+        let mut trie = BinTrie::new_depth(left.depth + 1);
+        trie.len = left.len + right.len;
+        if left.buckets.is_some() || right.buckets.is_some() {
+            trie.buckets = Some(
+                left.buckets
+                    .iter()
+                    .chain(&right.buckets)
+                    .flatten()
+                    .map(|(&item, bucket)| (item, bucket.clone()))
+                    .collect(),
+            );
+        }
+        for (position, child) in [left, right].iter().enumerate() {
+            let root = child.internals[0].0;
+            trie.internals[0].0[position] = match root {
+                // An empty trie leaves the side empty.
+                [0, 0] => 0,
+                // A single leaf can be stored directly in the new root.
+                [0, m] | [m, 0] if m & HIGH != 0 => m,
+                _ => {
+                    let offset = trie.internals.len() as u32;
+                    // Panic if we go too high to fit in our indices.
+                    assert!((offset as usize + child.internals.len()) & HIGH as usize == 0);
+                    trie.internals
+                        .extend(child.internals.iter().map(|internal| {
+                            Internal(internal.0.map(|n| match n {
+                                0 => 0,
+                                n if n & HIGH != 0 => n,
+                                n => n + offset,
+                            }))
+                        }));
+                    trie.terminals.extend(
+                        child
+                            .terminals
+                            .iter()
+                            .map(|(&index, &item)| (index + offset, item)),
+                    );
+                    offset
+                }
+            };
+        }
+        trie
+    }
+
     /// Inserts a number that does not have the most significant bit set.
     ///
     /// `K(n)` - A function that provides the `n`th bit for the key.
@@ -65,50 +523,225 @@ impl BinTrie {
     /// assert_eq!(trie.items().collect::<Vec<u32>>(), vec![5]);
     /// ```
     #[inline(always)]
-    pub fn insert<K, F>(&mut self, item: u32, mut key: K, mut lookup: F) -> Option<u32>
+    pub fn insert<K, F>(&mut self, item: u32, key: K, lookup: F) -> Option<u32>
+    where
+        K: FnMut(u32) -> bool,
+        F: FnMut(u32, u32) -> bool,
+    {
+        self.try_insert(item, key, lookup)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Inserts an item like `insert`, but returns an error rather than panicking.
+    ///
+    /// An error is returned if the item has the most significant bit set, or if
+    /// there is no room for another internal node. In the latter case, the item is
+    /// not inserted, but existing items may have been moved further down the trie.
+    ///
+    /// ```
+    /// # use bintrie::{BinTrie, BinTrieError};
+    /// let mut trie = BinTrie::new();
+    /// assert_eq!(trie.try_insert(5, |_| false, |_, _| false), Ok(None));
+    /// assert_eq!(
+    ///     trie.try_insert(1 << 31, |_| true, |_, _| false),
+    ///     Err(BinTrieError::ItemTooLarge(1 << 31)),
+    /// );
+    /// ```
+    #[inline(always)]
+    pub fn try_insert<K, F>(
+        &mut self,
+        item: u32,
+        key: K,
+        lookup: F,
+    ) -> Result<Option<u32>, BinTrieError>
+    where
+        K: FnMut(u32) -> bool,
+        F: FnMut(u32, u32) -> bool,
+    {
+        self.insert_from((0, 0), item, key, lookup, |_, _| {}, |_| item)
+    }
+
+    /// Inserts an item, letting `merge` decide what to keep if its key is already taken.
+    ///
+    /// Takes the same arguments as `insert`. When an existing item shares every
+    /// key bit with the new item, `merge` is passed a `Collision` describing both
+    /// items and returns the item to store in their shared place. Since the
+    /// collision can only happen at the full depth, both items share the whole key
+    /// that was passed in, so `merge` can rely on that key to combine them.
+    ///
+    /// Returns `Some` of the existing item if there was a collision, otherwise `None`.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let mut trie = BinTrie::new_depth(4);
+    /// trie.insert(3, |_| true, |_, _| true);
+    /// // Keep the smaller of the two items.
+    /// let existing = trie.insert_merge(7, |_| true, |_, _| true, |c| c.existing.min(c.incoming));
+    /// assert_eq!(existing, Some(3));
+    /// assert_eq!(trie.get(|_| true), Some(3));
+    /// ```
+    pub fn insert_merge<K, F, M>(&mut self, item: u32, key: K, lookup: F, merge: M) -> Option<u32>
+    where
+        K: FnMut(u32) -> bool,
+        F: FnMut(u32, u32) -> bool,
+        M: FnOnce(Collision) -> u32,
+    {
+        self.insert_from(
+            (0, 0),
+            item,
+            key,
+            lookup,
+            |_, _| {},
+            |existing| match existing {
+                Some(existing) => merge(Collision {
+                    existing,
+                    incoming: item,
+                }),
+                None => item,
+            },
+        )
+        .unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Inserts an item using the path of a previous insertion as a hint.
+    ///
+    /// Takes the same arguments as `insert`, along with an `InsertHint` that
+    /// records the path taken by the previous insertion. Rather than descending
+    /// from the root, the descent resumes from the deepest node on the recorded
+    /// path that shares the key's prefix. When inserting items in key order,
+    /// consecutive keys share long prefixes and most of the descent is skipped.
+    ///
+    /// A hint must only be used with the trie it was filled in by. Removing items or
+    /// moving internal nodes makes the recorded path stale, in which case the next
+    /// insertion descends from the root and records a new path.
+    ///
+    /// ```
+    /// # use bintrie::{BinTrie, InsertHint};
+    /// let mut trie = BinTrie::new_depth(8);
+    /// let lookup = |n: u32, l: u32| n >> (7 - l) & 1 == 1;
+    /// let mut hint = InsertHint::new();
+    /// for n in 0..256 {
+    ///     trie.insert_after(&mut hint, n, |l| lookup(n, l), lookup);
+    /// }
+    /// assert_eq!(trie.items().collect::<Vec<u32>>(), (0..256).collect::<Vec<u32>>());
+    ///
+    /// // The nodes freed here are reused below, but the old hint still lands correctly.
+    /// for n in 1..256 {
+    ///     trie.remove(|l| lookup(n, l), lookup);
+    /// }
+    /// trie.insert(2, |l| lookup(2, l), lookup);
+    /// trie.insert_after(&mut hint, 254, |l| lookup(254, l), lookup);
+    /// assert_eq!(trie.items().collect::<Vec<u32>>(), vec![0, 2, 254]);
+    /// assert_eq!(trie.get(|l| lookup(254, l)), Some(254));
+    /// ```
+    pub fn insert_after<K, F>(
+        &mut self,
+        hint: &mut InsertHint,
+        item: u32,
+        mut key: K,
+        lookup: F,
+    ) -> Option<u32>
+    where
+        K: FnMut(u32) -> bool,
+        F: FnMut(u32, u32) -> bool,
+    {
+        self.assert_trusted();
+        // Nodes were freed or moved since the path was recorded, so it can't be trusted.
+        if hint.generation != self.generation {
+            hint.path.clear();
+            hint.generation = self.generation;
+        }
+        let path = &mut hint.path;
+        // Find how much of the recorded path the key shares.
+        let shared = path
+            .iter()
+            .enumerate()
+            .position(|(i, &(_, position))| (key(i as u32) as u8) != position)
+            .unwrap_or(path.len());
+        // Resume from the node where the key diverges, or the last recorded node.
+        let start = shared.min(path.len().saturating_sub(1));
+        let index = path.get(start).map_or(0, |&(index, _)| index as usize);
+        // The hint might be from another trie, so make sure it is in bounds.
+        assert!(index < self.internals.len());
+        path.truncate(start);
+        self.insert_from(
+            (index, start as u32),
+            item,
+            key,
+            lookup,
+            |index, position| path.push((index as u32, position as u8)),
+            |_| item,
+        )
+        .unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Inserts an item starting from the internal node at `index` which is at depth `start`.
+    ///
+    /// `record` is called with every internal node index and position on the path.
+    /// `merge` is called with the existing item when the item is placed at
+    /// the last level, and returns the item to store there.
+    #[inline(always)]
+    fn insert_from<K, F, R, M>(
+        &mut self,
+        (mut index, start): (usize, u32),
+        item: u32,
+        mut key: K,
+        mut lookup: F,
+        mut record: R,
+        merge: M,
+    ) -> Result<Option<u32>, BinTrieError>
     where
         K: FnMut(u32) -> bool,
         F: FnMut(u32, u32) -> bool,
+        R: FnMut(usize, usize),
+        M: FnOnce(Option<u32>) -> u32,
     {
         // Always check that the high bit is not set in the item.
-        assert!(item & HIGH == 0);
+        if item & HIGH != 0 {
+            return Err(BinTrieError::ItemTooLarge(item));
+        }
+        // The unchecked descent below relies on the indices of a verified trie.
+        if self.is_suspect() {
+            return Err(BinTrieError::SuspectTrie);
+        }
+        // The table of the top levels and the subtree counts would become stale.
+        self.top = None;
+        self.counts = None;
         // This unsafe block is only used to allow indexing [u32; 2] by a `1` or `0`.
         unsafe {
-            let mut index = 0;
-            for i in 0..self.depth - 1 {
+            for i in start..self.depth - 1 {
                 let position = if key(i) { 1 } else { 0 };
-                match *self
-                    .internals
-                    .get_unchecked(index)
-                    .0
-                    .get_unchecked(position)
-                {
+                record(index, position);
+                match Child::unpack(
+                    *self
+                        .internals
+                        .get_unchecked(index)
+                        .0
+                        .get_unchecked(position),
+                ) {
                     // Empty node encountered.
-                    0 => {
-                        // Insert the item in the empty spot, making sure to set
-                        // its most significant bit to indicate it is a leaf.
+                    Child::Empty => {
+                        // Insert the item in the empty spot as a leaf.
                         *self
                             .internals
                             .get_unchecked_mut(index)
                             .0
-                            .get_unchecked_mut(position) = item | HIGH;
+                            .get_unchecked_mut(position) = Child::Leaf(item).pack();
+                        self.record_insertion(item, None);
                         // That's it.
-                        return None;
+                        return Ok(None);
                     }
                     // Leaf node encountered.
-                    m if m & HIGH != 0 => {
+                    Child::Leaf(m) => {
                         // Make an empty node.
                         let mut new_internal = Internal::default();
                         // Add the existing `m` to its proper location.
                         *new_internal
                             .0
-                            .get_unchecked_mut(if lookup(m & !HIGH, i + 1) { 1 } else { 0 }) = m;
-                        // Get the index of the next internal node.
-                        let new_index = self.internals.len() as u32;
-                        // Panic if we go too high to fit in our indices.
-                        assert!(new_index & HIGH == 0);
-                        // Insert the new internal node onto the internals vector.
-                        self.internals.push(new_internal);
+                            .get_unchecked_mut(if lookup(m, i + 1) { 1 } else { 0 }) =
+                            Child::Leaf(m).pack();
+                        // Store the new internal node and get its index.
+                        let new_index = self.try_allocate(new_internal)?;
                         // Insert the new index to the parent node.
                         *self
                             .internals
@@ -120,7 +753,7 @@ impl BinTrie {
                         index = new_index as usize;
                     }
                     // Internal node encountered.
-                    m => {
+                    Child::Internal(m) => {
                         // Move to the internal node.
                         index = m as usize;
                     }
@@ -130,20 +763,33 @@ impl BinTrie {
             // For the last bit we only handle the case that we can insert it.
             // If something occupies the space we replace it and return it.
             let position = if key(self.depth - 1) { 1 } else { 0 };
+            record(index, position);
             let spot = self
                 .internals
                 .get_unchecked_mut(index)
                 .0
                 .get_unchecked_mut(position);
-            let old = *spot;
-            *spot = item | HIGH;
-            // Check if it was not an empty node.
-            if old != 0 {
-                // Return the item that was replaced.
-                Some(old & !HIGH)
-            } else {
-                None
+            let old = match Child::unpack(*spot) {
+                Child::Leaf(m) => Some(m),
+                _ => None,
+            };
+            // Colliding items are kept beside the existing leaf when bucketing.
+            if let (Some(existing), Some(buckets)) = (old, &mut self.buckets) {
+                if existing != item {
+                    buckets.entry(existing).or_default().push(item);
+                    self.record_insertion(item, None);
+                    return Ok(None);
+                }
             }
+            let item = merge(old);
+            // The merged item must also not have the high bit set.
+            if item & HIGH != 0 {
+                return Err(BinTrieError::ItemTooLarge(item));
+            }
+            *spot = Child::Leaf(item).pack();
+            self.record_insertion(item, old);
+            // Return the item that was replaced, if any.
+            Ok(old)
         }
     }
 
@@ -165,24 +811,39 @@ impl BinTrie {
     where
         K: FnMut(u32) -> bool,
     {
+        if self.is_suspect() {
+            return self.get_checked(key);
+        }
+        if let Some(accesses) = &self.accesses {
+            return self.get_counted(accesses, key);
+        }
+        // Skip the top levels if there is a table for them.
+        let (mut index, start) = match &self.top {
+            Some(top) => match Child::unpack(top.slot(&mut key)) {
+                Child::Empty => return None,
+                Child::Leaf(m) => return Some(m),
+                Child::Internal(m) => (m as usize, top.bits),
+            },
+            None => (0, 0),
+        };
         // This unsafe block is only used to allow indexing [u32; 2] by a `1` or `0`.
         unsafe {
-            let mut index = 0;
-            for i in 0..self.depth {
-                match *self
-                    .internals
-                    .get_unchecked(index)
-                    .0
-                    .get_unchecked(if key(i) { 1 } else { 0 })
-                {
+            for i in start..self.depth {
+                match Child::unpack(
+                    *self
+                        .internals
+                        .get_unchecked(index)
+                        .0
+                        .get_unchecked(if key(i) { 1 } else { 0 }),
+                ) {
                     // Empty node encountered.
-                    0 => {
+                    Child::Empty => {
                         return None;
                     }
                     // Leaf node encountered.
-                    m if m & HIGH != 0 => return Some(m & !HIGH),
+                    Child::Leaf(m) => return Some(m),
                     // Internal node encountered.
-                    m => {
+                    Child::Internal(m) => {
                         // Move to the internal node.
                         index = m as usize;
                     }
@@ -192,6 +853,44 @@ impl BinTrie {
         }
     }
 
+    /// Checks if an item might have a key by only looking at its first `levels` bits.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the key.
+    ///
+    /// Returns `false` if there is definitely no item with the key, and `true` if
+    /// there possibly is. This is a cheap pre-filter for a full lookup, since it
+    /// stops after `levels` internal nodes even when the trie is very deep.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let mut trie = BinTrie::new_depth(32);
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// for n in &[0b0000, 0b0100, 0b0110] {
+    ///     trie.insert(*n, |l| lookup(*n, l), lookup);
+    /// }
+    /// assert!(trie.maybe_contains(|l| lookup(0b0100, l), 2));
+    /// assert!(!trie.maybe_contains(|l| lookup(0b0001, l), 2));
+    /// // Only a full lookup can tell that this is not in the trie.
+    /// assert!(trie.maybe_contains(|l| lookup(0b1000, l), 2));
+    /// ```
+    pub fn maybe_contains<K>(&self, mut key: K, levels: u32) -> bool
+    where
+        K: FnMut(u32) -> bool,
+    {
+        let mut index = 0;
+        for i in 0..levels.min(self.depth) {
+            match Child::unpack(self.child_checked(index, key(i) as usize)) {
+                // Empty node encountered.
+                Child::Empty => return false,
+                // Leaf node encountered.
+                Child::Leaf(_) => return true,
+                // Internal node encountered.
+                Child::Internal(m) => index = m as usize,
+            }
+        }
+        true
+    }
+
     /// Get an iterator over the items added to the trie.
     ///
     /// ```
@@ -200,8 +899,190 @@ impl BinTrie {
     /// trie.insert(3, |_| false, |_, _| false);
     /// assert_eq!(trie.items().collect::<Vec<u32>>(), vec![3]);
     /// ```
-    pub fn items<'a>(&'a self) -> impl Iterator<Item = u32> + 'a {
-        Iter::new(self)
+    pub fn items(&self) -> Items<'_> {
+        Items {
+            iter: Iter::new(self),
+            remaining: self.len,
+        }
+    }
+
+    /// The number of items in the trie.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let mut trie = BinTrie::new_depth(3);
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// assert!(trie.is_empty());
+    /// for n in 0..8 {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// // Replacing an item does not change the length.
+    /// trie.insert(9, |l| lookup(9, l), lookup);
+    /// assert_eq!(trie.len(), 8);
+    /// assert_eq!(trie.items().len(), 8);
+    /// assert!(!trie.is_empty());
+    /// ```
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Checks if the trie has no items.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The maximum depth of the trie, which is the number of bits in every key.
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    /// Attaches a small blob of user-defined `metadata` to the trie.
+    ///
+    /// The metadata is not interpreted by the trie, but it is kept with it when it is
+    /// rebuilt or persisted. It is intended for things like the build timestamp, a hash
+    /// of the source dataset, or the version of the key encoding, so that a service can
+    /// refuse to use a trie that was built with an incompatible key encoding.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let mut trie = BinTrie::new();
+    /// assert_eq!(trie.metadata(), b"");
+    /// trie.set_metadata(b"key-encoding-v2");
+    /// assert_eq!(trie.metadata(), b"key-encoding-v2");
+    /// ```
+    pub fn set_metadata(&mut self, metadata: &[u8]) {
+        self.metadata = metadata.to_vec();
+    }
+
+    /// Gets the user-defined metadata attached with `set_metadata`.
+    pub fn metadata(&self) -> &[u8] {
+        &self.metadata
+    }
+
+    /// Starts tracking the order that items are inserted in.
+    ///
+    /// Items already in the trie are treated as having been inserted in key order.
+    /// When an item is replaced, it is removed from the order and the new item is
+    /// treated as the most recently inserted. Tracking costs an extra 4 bytes per item
+    /// and makes each replacement linear in the number of items.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let mut trie = BinTrie::new().with_insertion_order();
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// for &n in &[3, 0, 2, 1] {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// assert_eq!(trie.items().collect::<Vec<u32>>(), vec![0, 2, 1, 3]);
+    /// assert_eq!(trie.items_in_insertion_order().unwrap().collect::<Vec<u32>>(), vec![3, 0, 2, 1]);
+    /// ```
+    pub fn with_insertion_order(mut self) -> Self {
+        if self.insertion_order.is_none() {
+            self.insertion_order = Some(self.items().collect());
+        }
+        self
+    }
+
+    /// Get an iterator over the items in the order they were inserted.
+    ///
+    /// Returns `None` unless insertion order is being tracked, which is enabled
+    /// with `with_insertion_order`.
+    pub fn items_in_insertion_order(&self) -> Option<impl Iterator<Item = u32> + '_> {
+        self.insertion_order
+            .as_ref()
+            .map(|order| order.iter().copied())
+    }
+
+    /// Iterates over items in key order starting at the first item whose key is at least `key`.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the key.
+    /// `F(item, n)` - A function that must be able to look up the nth bit
+    ///    from a previously inserted item.
+    ///
+    /// Keys are ordered by their bits starting from bit `0`, with `false`
+    /// ordered before `true`. The `lookup` is needed to compare the key
+    /// against the item found where descending along `key` ends.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let mut trie = BinTrie::new_depth(4);
+    /// let lookup = |n: u32, l: u32| n >> (3 - l) & 1 == 1;
+    /// for n in (0..16).step_by(3) {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// let range = |k: u32| trie.range_from(|l| lookup(k, l), lookup).collect::<Vec<u32>>();
+    /// assert_eq!(range(7), vec![9, 12, 15]);
+    /// assert_eq!(range(6), vec![6, 9, 12, 15]);
+    /// assert_eq!(range(0), vec![0, 3, 6, 9, 12, 15]);
+    /// ```
+    pub fn range_from<'a, K, F>(&'a self, key: K, lookup: F) -> impl Iterator<Item = u32> + 'a
+    where
+        K: FnMut(u32) -> bool,
+        F: FnMut(u32, u32) -> bool,
+    {
+        RangeIter::new(self, key, lookup, false)
+    }
+
+    /// Iterates over items in reverse key order starting at the last item whose key is at most `key`.
+    ///
+    /// This is the reverse counterpart of `range_from` and takes the same arguments.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let mut trie = BinTrie::new_depth(4);
+    /// let lookup = |n: u32, l: u32| n >> (3 - l) & 1 == 1;
+    /// for n in (0..16).step_by(3) {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// let range = |k: u32| trie.range_from_rev(|l| lookup(k, l), lookup).collect::<Vec<u32>>();
+    /// assert_eq!(range(7), vec![6, 3, 0]);
+    /// assert_eq!(range(9), vec![9, 6, 3, 0]);
+    /// assert_eq!(range(15), vec![15, 12, 9, 6, 3, 0]);
+    /// ```
+    pub fn range_from_rev<'a, K, F>(&'a self, key: K, lookup: F) -> impl Iterator<Item = u32> + 'a
+    where
+        K: FnMut(u32) -> bool,
+        F: FnMut(u32, u32) -> bool,
+    {
+        RangeIter::new(self, key, lookup, true)
+    }
+
+    /// Groups the items by the first `k` bits of their keys.
+    ///
+    /// `F(item, n)` - A function that must be able to look up the nth bit
+    ///    from a previously inserted item.
+    ///
+    /// Yields every occupied prefix in key order along with an iterator over the
+    /// items with that prefix. The prefix is a `k` bit number whose most significant
+    /// bit is bit `0` of the key. The `lookup` is used to find the prefix of items
+    /// that are stored less than `k` levels deep. `k` can be at most `64`.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let mut trie = BinTrie::new_depth(4);
+    /// let lookup = |n: u32, l: u32| n >> (3 - l) & 1 == 1;
+    /// for &n in &[0b0001, 0b0011, 0b1000, 0b1001] {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// let groups = trie
+    ///     .group_by_prefix(2, lookup)
+    ///     .map(|(prefix, items)| (prefix, items.collect::<Vec<u32>>()))
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(groups, vec![(0b00, vec![0b0001, 0b0011]), (0b10, vec![0b1000, 0b1001])]);
+    /// ```
+    pub fn group_by_prefix<'a, F>(
+        &'a self,
+        k: u32,
+        lookup: F,
+    ) -> impl Iterator<Item = (u64, impl Iterator<Item = u32> + 'a)> + 'a
+    where
+        F: FnMut(u32, u32) -> bool + 'a,
+    {
+        assert!(k <= 64 && k <= self.depth);
+        GroupIter::new(self, k, lookup).map(move |(prefix, slot)| match slot {
+            Some(slot) => (prefix, Iter::from_slot(self, slot)),
+            None => (prefix, Iter::new(self)),
+        })
     }
 
     /// Iterates over the trie while using the `heuristic` to guide iteration.
@@ -217,7 +1098,7 @@ impl BinTrie {
     /// `Heuristic` trait satisfies.
     ///
     /// ```
-    /// # use bintrie::{BinTrie, FilterHeuristic};
+    /// # use bintrie::{BinTrie, FilterHeuristic, Side};
     /// let mut trie = BinTrie::new();
     /// let lookup = |n, l| match n {
     ///     3 => false,
@@ -228,16 +1109,16 @@ impl BinTrie {
     /// trie.insert(3, |n| lookup(3, n), lookup);
     /// trie.insert(5, |n| lookup(5, n), lookup);
     /// trie.insert(7, |n| lookup(7, n), lookup);
-    /// assert_eq!(trie.explore(FilterHeuristic(|n| n)).collect::<Vec<u32>>(), vec![7]);
+    /// assert_eq!(trie.explore(FilterHeuristic(|s| s == Side::Right)).collect::<Vec<u32>>(), vec![7]);
     /// let mut level = 0;
     /// // Try and find the 5.
-    /// assert_eq!(trie.explore(FilterHeuristic(move |n: bool| {
+    /// assert_eq!(trie.explore(FilterHeuristic(move |s| {
     ///     level += 1;
     ///     match level {
     ///         // Go left.
-    ///         1 => !n,
+    ///         1 => s == Side::Left,
     ///         // Then go right.
-    ///         2 => n,
+    ///         2 => s == Side::Right,
     ///         _ => false,
     ///     }
     /// })).collect::<Vec<u32>>(), vec![5]);
@@ -247,7 +1128,720 @@ impl BinTrie {
         H: IntoHeuristic,
         H::Heuristic: 'a,
     {
-        ExploreIter::new(self, heuristic.into_heuristic())
+        ExploreIter::new(self, heuristic.into_heuristic(), vec![], |_| true)
+    }
+
+    /// Explores the trie like `explore`, but stops once `cancel` is set.
+    ///
+    /// The flag is checked every `64` internal nodes that are entered, so an
+    /// exploration that searches a large part of the trie without finding anything
+    /// can still be aborted from another thread, such as when the deadline of a query
+    /// passes. Up to `64` more nodes may be entered after the flag is set. Once
+    /// cancelled, the iterator only returns `None`.
+    ///
+    /// ```
+    /// # use bintrie::{BinTrie, FilterHeuristic};
+    /// use std::sync::atomic::{AtomicBool, Ordering};
+    /// let mut trie = BinTrie::new_depth(16);
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// for n in 0..1000 {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// let cancel = AtomicBool::new(false);
+    /// let mut explore = trie.explore_cancellable(FilterHeuristic(|_| true), &cancel);
+    /// assert_eq!(explore.by_ref().take(10).count(), 10);
+    /// cancel.store(true, Ordering::Relaxed);
+    /// assert!(explore.count() < 990);
+    /// ```
+    pub fn explore_cancellable<'a, H>(
+        &'a self,
+        heuristic: H,
+        cancel: &'a AtomicBool,
+    ) -> impl Iterator<Item = u32> + 'a
+    where
+        H: IntoHeuristic,
+        H::Heuristic: 'a,
+    {
+        ExploreIter::new(self, heuristic.into_heuristic(), vec![], |_| true).cancel_on(cancel)
+    }
+
+    /// Explores the trie like `explore`, but keeps the traversal stack in `scratch`.
+    ///
+    /// Reusing the same `QueryScratch` across many queries avoids allocating
+    /// a new stack for every query.
+    ///
+    /// ```
+    /// # use bintrie::{BinTrie, FilterHeuristic, QueryScratch, Side};
+    /// let mut trie = BinTrie::new();
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// for n in 0..4 {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// let mut scratch = QueryScratch::new();
+    /// for &side in &[Side::Left, Side::Right] {
+    ///     let found = trie
+    ///         .explore_with(FilterHeuristic(move |s| s == side), &mut scratch)
+    ///         .collect::<Vec<u32>>();
+    ///     assert_eq!(found, vec![if side == Side::Left { 0 } else { 3 }]);
+    /// }
+    /// ```
+    pub fn explore_with<'a, H>(
+        &'a self,
+        heuristic: H,
+        scratch: &'a mut QueryScratch<H::Heuristic>,
+    ) -> impl Iterator<Item = u32> + 'a
+    where
+        H: IntoHeuristic,
+        H::Heuristic: 'a,
+    {
+        ExploreIter::new(
+            self,
+            heuristic.into_heuristic(),
+            &mut scratch.frames,
+            |_| true,
+        )
+    }
+
+    /// Explores the trie like `explore`, but keeps the traversal stack in `stack`.
+    ///
+    /// The stack is cleared before use, so the same `Vec` can be reused across
+    /// queries and will only allocate when it needs to grow.
+    ///
+    /// ```
+    /// # use bintrie::{BinTrie, FilterHeuristic};
+    /// let mut trie = BinTrie::new();
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// for n in 0..4 {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// let mut stack = Vec::new();
+    /// for _ in 0..2 {
+    ///     let found = trie.explore_in(FilterHeuristic(|_| true), &mut stack).count();
+    ///     assert_eq!(found, 4);
+    /// }
+    /// ```
+    pub fn explore_in<'a, H>(
+        &'a self,
+        heuristic: H,
+        stack: &'a mut Vec<ExploreFrame<H::Heuristic>>,
+    ) -> impl Iterator<Item = u32> + 'a
+    where
+        H: IntoHeuristic,
+        H::Heuristic: 'a,
+    {
+        ExploreIter::new(self, heuristic.into_heuristic(), stack, |_| true)
+    }
+
+    /// Explores the trie like `explore`, but yields where each item was found.
+    ///
+    /// Each item comes with the number of sides taken to reach it and each of those
+    /// sides, starting from the root. For a leaf this is a prefix of its key,
+    /// so the rest of the key is all that has to be looked up to find its exact
+    /// distance to a query. For a terminated item it is the whole key.
+    ///
+    /// ```
+    /// # use bintrie::{BinTrie, FilterHeuristic, Side};
+    /// let mut trie = BinTrie::new_depth(4);
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// for &n in &[0b0000, 0b0010, 0b0001] {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// let found = trie.explore_paths(FilterHeuristic(|_| true)).collect::<Vec<_>>();
+    /// assert_eq!(
+    ///     found,
+    ///     vec![
+    ///         (0b0000, 2, vec![Side::Left, Side::Left]),
+    ///         (0b0010, 2, vec![Side::Left, Side::Right]),
+    ///         (0b0001, 1, vec![Side::Right]),
+    ///     ]
+    /// );
+    /// ```
+    pub fn explore_paths<'a, H>(
+        &'a self,
+        heuristic: H,
+    ) -> impl Iterator<Item = (u32, u32, Vec<Side>)> + 'a
+    where
+        H: IntoHeuristic,
+        H::Heuristic: 'a,
+    {
+        let mut explore = ExploreIter::new(self, heuristic.into_heuristic(), vec![], |_| true);
+        core::iter::from_fn(move || {
+            let item = explore.next()?;
+            let path = explore.path();
+            Some((item, path.len() as u32, path))
+        })
+    }
+
+    /// Explores the trie like `explore`, but enters at most `max_nodes` internal nodes.
+    ///
+    /// This bounds the time a query can take, at the cost of missing the items beneath
+    /// nodes that were never entered. The returned iterator reports how many internal
+    /// nodes were visited and whether the budget ran out, and the budget can be raised
+    /// to continue exploring.
+    ///
+    /// ```
+    /// # use bintrie::{BinTrie, HammingNearestFirst};
+    /// let mut trie = BinTrie::new_depth(8);
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// for n in 0..256 {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// let mut explore = trie.explore_budgeted(HammingNearestFirst::new(0), 10);
+    /// let found = explore.by_ref().count();
+    /// assert!(found < 256);
+    /// assert_eq!(explore.visited(), 10);
+    /// assert!(explore.is_exhausted());
+    /// explore.extend_budget(usize::MAX);
+    /// assert_eq!(found + explore.count(), 256);
+    /// ```
+    pub fn explore_budgeted<H>(
+        &self,
+        heuristic: H,
+        max_nodes: usize,
+    ) -> BudgetedExplore<'_, H::Heuristic>
+    where
+        H: IntoHeuristic,
+    {
+        let mut explore = ExploreIter::new(
+            self,
+            heuristic.into_heuristic(),
+            vec![],
+            explore::accept_all as fn(u32) -> bool,
+        );
+        explore.limit = max_nodes;
+        BudgetedExplore(explore)
+    }
+
+    /// Explores the trie like `explore`, but only yields leaves accepted by `filter`.
+    ///
+    /// The filter is applied inside the exploration before a leaf is yielded, and
+    /// the number of rejected leaves is available from `FilteredExplore::rejected`.
+    ///
+    /// ```
+    /// # use bintrie::{BinTrie, FilterHeuristic};
+    /// let mut trie = BinTrie::new();
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// for n in 0..8 {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// let mut explore = trie.explore_filtered(FilterHeuristic(|_| true), |n| n % 2 == 0);
+    /// assert_eq!(explore.by_ref().collect::<Vec<u32>>(), vec![0, 4, 2, 6]);
+    /// assert_eq!(explore.rejected(), 4);
+    /// ```
+    pub fn explore_filtered<H, P>(
+        &self,
+        heuristic: H,
+        filter: P,
+    ) -> FilteredExplore<'_, H::Heuristic, P>
+    where
+        H: IntoHeuristic,
+        P: FnMut(u32) -> bool,
+    {
+        FilteredExplore(ExploreIter::new(
+            self,
+            heuristic.into_heuristic(),
+            vec![],
+            filter,
+        ))
+    }
+
+    /// Explores the trie like `explore`, but never yields the same leaf twice.
+    ///
+    /// A heuristic may choose the same side of a node more than once, which would
+    /// normally yield the leaves beneath it again. This keeps a set of the places
+    /// leaves were found in so that each is only yielded once.
+    ///
+    /// ```
+    /// # use bintrie::{BinTrie, Heuristic, Side};
+    /// /// Visits every side twice.
+    /// #[derive(Clone)]
+    /// struct Twice;
+    ///
+    /// impl Heuristic for Twice {
+    ///     type Iter = std::vec::IntoIter<Side>;
+    ///
+    ///     fn enter(&mut self, _: Side, _: u32) {}
+    ///
+    ///     fn iter(&self, _: u32) -> Self::Iter {
+    ///         vec![Side::Left, Side::Left, Side::Right, Side::Right].into_iter()
+    ///     }
+    /// }
+    ///
+    /// let mut trie = BinTrie::new();
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// for n in 0..4 {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// assert_eq!(trie.explore(Twice).count(), 16);
+    /// assert_eq!(trie.explore_dedup(Twice).collect::<Vec<u32>>(), vec![0, 2, 1, 3]);
+    /// ```
+    pub fn explore_dedup<'a, H>(&'a self, heuristic: H) -> impl Iterator<Item = u32> + 'a
+    where
+        H: IntoHeuristic,
+        H::Heuristic: 'a,
+    {
+        ExploreIter::new(self, heuristic.into_heuristic(), vec![], |_| true).deduplicate()
+    }
+
+    /// Explores the trie with several heuristics at once, taking turns between them.
+    ///
+    /// Each heuristic is a probe, such as a search around a perturbed query key.
+    /// The probes take turns yielding their next item in round-robin order. At most
+    /// `budget` internal nodes are entered in total across all probes, and an item
+    /// found by several probes is only yielded the first time it is found.
+    ///
+    /// ```
+    /// # use bintrie::{BinTrie, FilterHeuristic, Side};
+    /// let mut trie = BinTrie::new();
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// for n in 0..4 {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// let probe = |side| FilterHeuristic(move |s| s == side);
+    /// let found = trie
+    ///     .explore_multi(vec![probe(Side::Left), probe(Side::Right), probe(Side::Left)], 16)
+    ///     .collect::<Vec<u32>>();
+    /// assert_eq!(found, vec![0, 3]);
+    /// ```
+    pub fn explore_multi<I>(
+        &self,
+        heuristics: I,
+        budget: usize,
+    ) -> MultiExplore<'_, <I::Item as IntoHeuristic>::Heuristic>
+    where
+        I: IntoIterator,
+        I::Item: IntoHeuristic,
+    {
+        MultiExplore::new(
+            self,
+            heuristics.into_iter().map(IntoHeuristic::into_heuristic),
+            budget,
+        )
+    }
+
+    /// Finds every item whose key matches `key` on the bits selected by `mask`.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the key.
+    /// `M(n)` - A function that returns `false` if the `n`th bit of the key
+    ///    is a don't-care bit which may match either side.
+    /// `F(item, n)` - A function that must be able to look up the nth bit
+    ///    from a previously inserted item.
+    ///
+    /// The search is guided by a `MaskedHeuristic`, and the `lookup` is used to check
+    /// the bits of items that are stored above the depth where their keys stop matching.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let mut trie = BinTrie::new_depth(4);
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// for n in 0..16 {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// // Match 0bX1X1, where X is a don't-care bit.
+    /// let mut found = trie
+    ///     .get_masked(|l| lookup(0b0101, l), |l| lookup(0b0101, l), lookup)
+    ///     .collect::<Vec<u32>>();
+    /// found.sort();
+    /// assert_eq!(found, vec![0b0101, 0b0111, 0b1101, 0b1111]);
+    /// ```
+    pub fn get_masked<'a, K, M, F>(
+        &'a self,
+        key: K,
+        mask: M,
+        mut lookup: F,
+    ) -> impl Iterator<Item = u32> + 'a
+    where
+        K: Fn(u32) -> bool + Clone + 'a,
+        M: Fn(u32) -> bool + Clone + 'a,
+        F: FnMut(u32, u32) -> bool + 'a,
+    {
+        let depth = self.depth;
+        let heuristic = MaskedHeuristic::new(key.clone(), mask.clone());
+        self.explore_filtered(heuristic, move |item| {
+            (0..depth).all(|l| !mask(l) || lookup(item, l) == key(l))
+        })
+    }
+
+    /// Explores the trie exactly like `explore`, but yields every step taken.
+    ///
+    /// Each internal node entered, each item yielded, and each occupied side
+    /// that the heuristic declined to explore is reported as a `VisitEvent`
+    /// in the order it happens. The items are those `explore` yields, including
+    /// terminated items and the rest of the buckets. This is intended for testing
+    /// and visualizing the behavior of a `Heuristic`.
+    ///
+    /// ```
+    /// # use bintrie::{BinTrie, FilterHeuristic, Side, VisitEvent};
+    /// let mut trie = BinTrie::new();
+    /// let lookup = |n, l| match n {
+    ///     3 => false,
+    ///     5 => if l == 1 { true } else { false },
+    ///     7 => if l == 1 { false } else { true },
+    ///     _ => true,
+    /// };
+    /// trie.insert(3, |n| lookup(3, n), lookup);
+    /// trie.insert(5, |n| lookup(5, n), lookup);
+    /// trie.insert(7, |n| lookup(7, n), lookup);
+    /// assert_eq!(
+    ///     trie.explore_trace(FilterHeuristic(|s| s == Side::Right)).collect::<Vec<_>>(),
+    ///     vec![
+    ///         VisitEvent::Leaf { item: 7, depth: 0, side: Side::Right },
+    ///         VisitEvent::Prune { depth: 0, side: Side::Left },
+    ///     ],
+    /// );
+    /// assert_eq!(
+    ///     trie.explore_trace(FilterHeuristic(|s| s == Side::Left)).collect::<Vec<_>>(),
+    ///     vec![
+    ///         VisitEvent::Enter { depth: 0, side: Side::Left },
+    ///         VisitEvent::Leaf { item: 3, depth: 1, side: Side::Left },
+    ///         VisitEvent::Prune { depth: 1, side: Side::Right },
+    ///         VisitEvent::Prune { depth: 0, side: Side::Right },
+    ///     ],
+    /// );
+    ///
+    /// let mut trie = BinTrie::new_depth(8);
+    /// let keys: [&[bool]; 3] = [&[true], &[true, false], &[true, false, true]];
+    /// let lookup = |n: u32, l: u32| keys[n as usize].get(l as usize).copied();
+    /// for n in 0..3 {
+    ///     trie.insert_terminated(n, |l| lookup(n, l), lookup);
+    /// }
+    /// assert_eq!(
+    ///     trie.explore_trace(FilterHeuristic(|_| true)).collect::<Vec<_>>(),
+    ///     vec![
+    ///         VisitEvent::Enter { depth: 0, side: Side::Right },
+    ///         VisitEvent::Terminal { item: 0, depth: 1 },
+    ///         VisitEvent::Enter { depth: 1, side: Side::Left },
+    ///         VisitEvent::Terminal { item: 1, depth: 2 },
+    ///         VisitEvent::Leaf { item: 2, depth: 2, side: Side::Right },
+    ///     ],
+    /// );
+    /// ```
+    pub fn explore_trace<'a, H>(&'a self, heuristic: H) -> impl Iterator<Item = VisitEvent> + 'a
+    where
+        H: IntoHeuristic,
+        H::Heuristic: 'a,
+    {
+        TraceIter::new(self, heuristic.into_heuristic())
+    }
+
+    /// Checks if two tries have items in exactly the same places, ignoring the items themselves.
+    ///
+    /// This is useful to verify that two ways of building a trie place everything
+    /// identically, even if the items are numbered differently.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// let mut a = BinTrie::new();
+    /// let mut b = BinTrie::new();
+    /// for n in 0..8 {
+    ///     a.insert(n, |l| lookup(n, l), lookup);
+    ///     // Number the items differently, but keep the same keys.
+    ///     b.insert(n + 100, |l| lookup(n, l), |m, l| lookup(m - 100, l));
+    /// }
+    /// assert!(a.structurally_equal(&b));
+    /// b.insert(108, |l| lookup(8, l), |m, l| lookup(m - 100, l));
+    /// assert!(!a.structurally_equal(&b));
+    /// ```
+    pub fn structurally_equal(&self, other: &BinTrie) -> bool {
+        if self.depth != other.depth {
+            return false;
+        }
+        let mut stack = vec![(0, 0, 0)];
+        while let Some((a, b, depth)) = stack.pop() {
+            if self.terminal(a).is_some() != other.terminal(b).is_some() {
+                return false;
+            }
+            // Only follow the first slot that reaches each node of a suspect trie, and stop at the depth.
+            if depth >= self.depth {
+                return false;
+            }
+            let (ours, theirs) = (
+                self.children_checked(a as usize),
+                other.children_checked(b as usize),
+            );
+            for (&a, &b) in ours.iter().zip(&theirs) {
+                match (a, b) {
+                    // Both are empty.
+                    (0, 0) => {}
+                    // Both are leaves.
+                    (a, b) if a & HIGH != 0 && b & HIGH != 0 => {}
+                    // Both are internal nodes.
+                    (a, b) if a != 0 && b != 0 && a & HIGH == 0 && b & HIGH == 0 => {
+                        stack.push((a, b, depth + 1))
+                    }
+                    _ => return false,
+                }
+            }
+        }
+        true
+    }
+
+    /// Estimates the number of items in the trie without visiting the whole thing.
+    ///
+    /// Only internal nodes at a depth of at most `max_depth` are visited (the root
+    /// is at depth `0`). Items found along the way are counted exactly, and the
+    /// rest are extrapolated from how many of the `2^(max_depth + 1)` places one level
+    /// further down are occupied, with linear counting. This assumes that keys are
+    /// spread evenly, such as hashes. The estimate is never less than the items
+    /// found plus two for each internal node that was not visited, and it is exact
+    /// once `max_depth` reaches the height of the trie.
+    ///
+    /// Linear counting works best when there are fewer items than places. Once every
+    /// place is occupied it can't tell how many more items there are, so choose a
+    /// `max_depth` close to the log2 of the expected number of items.
+    ///
+    /// This is useful for progress reporting on huge tries where counting
+    /// every item would touch every node.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let mut trie = BinTrie::new();
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// for n in 0..4 {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// assert_eq!(trie.estimate_len(0), 4);
+    /// assert_eq!(trie.estimate_len(8), 4);
+    ///
+    /// let hash = |n: u32| {
+    ///     let n = (n ^ n >> 16).wrapping_mul(0x85EB_CA6B);
+    ///     let n = (n ^ n >> 13).wrapping_mul(0xC2B2_AE35);
+    ///     n ^ n >> 16
+    /// };
+    /// let lookup = |n: u32, l: u32| hash(n) >> l & 1 == 1;
+    /// let mut trie = BinTrie::new_depth(32);
+    /// for n in 0..20_000 {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// let estimate = trie.estimate_len(12);
+    /// assert!(19_000 < estimate && estimate < 21_000);
+    /// assert_eq!(trie.estimate_len(32), 20_000);
+    /// ```
+    pub fn estimate_len(&self, max_depth: u32) -> usize {
+        // Items found, leaves found and internal nodes that were not visited.
+        let (mut found, mut leaves, mut unvisited) = (0, 0, 0);
+        let mut stack = vec![(0, 0)];
+        while let Some((index, depth)) = stack.pop() {
+            // Only follow the first slot that reaches each node of a suspect trie, and stop at the depth.
+            if depth >= self.depth {
+                continue;
+            }
+            let children = self.children_checked(index);
+            for &n in &children {
+                match n {
+                    // Empty node
+                    0 => {}
+                    // Leaf node
+                    n if n & HIGH != 0 => {
+                        found += 1 + self.bucket(n & !HIGH).len();
+                        leaves += 1;
+                    }
+                    // Internal node
+                    n => {
+                        if depth < max_depth {
+                            found += self.terminal(n).is_some() as usize;
+                            stack.push((n as usize, depth + 1));
+                        } else {
+                            unvisited += 1;
+                        }
+                    }
+                }
+            }
+        }
+        let lower = found + 2 * unvisited;
+        // Every leaf and unvisited node occupies one place on the level below `max_depth`.
+        let level = max_depth + 1;
+        if unvisited == 0 || level >= 64 {
+            return lower;
+        }
+        let places = (1u64 << level) as f64;
+        let occupied = ((leaves + unvisited) as f64).min(places - 1.0);
+        let counted = -places * ln(1.0 - occupied / places);
+        (found - leaves + counted as usize).max(lower)
+    }
+
+    /// Counts how many left and right children are occupied at every level.
+    ///
+    /// The returned `Vec` has an entry for every level down to the deepest
+    /// occupied one, where each entry is the number of occupied `(left, right)`
+    /// children at that level. A skew at a level indicates that the key bit
+    /// for that level is poorly distributed.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let mut trie = BinTrie::new();
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// for n in &[0, 1, 2, 3, 5] {
+    ///     trie.insert(*n, |l| lookup(*n, l), lookup);
+    /// }
+    /// assert_eq!(trie.level_balance(), vec![(1, 1), (2, 2), (1, 1)]);
+    /// ```
+    pub fn level_balance(&self) -> Vec<(u64, u64)> {
+        let mut balance: Vec<(u64, u64)> = vec![];
+        let mut stack = vec![(0, 0)];
+        while let Some((index, depth)) = stack.pop() {
+            // Only follow the first slot that reaches each node of a suspect trie, and stop at the depth.
+            if depth >= self.depth as usize {
+                continue;
+            }
+            let [left, right] = self.children_checked(index);
+            if left == 0 && right == 0 {
+                continue;
+            }
+            if balance.len() <= depth {
+                balance.resize(depth + 1, (0, 0));
+            }
+            balance[depth].0 += (left != 0) as u64;
+            balance[depth].1 += (right != 0) as u64;
+            for &n in &[left, right] {
+                if n != 0 && n & HIGH == 0 {
+                    stack.push((n as usize, depth + 1));
+                }
+            }
+        }
+        balance
+    }
+
+    /// Finds which of the `2^level` prefixes of `level` bits are used by at least one key.
+    ///
+    /// `F(item, n)` - A function that must be able to look up the nth bit
+    ///    from a previously inserted item.
+    ///
+    /// The prefixes are numbered like in `group_by_prefix`, and prefix `p` is occupied if
+    /// bit `p % 64` of word `p / 64` is set. The `lookup` is used to find the prefix of
+    /// items that are stored less than `level` levels deep. Terminated keys shorter than
+    /// `level` bits do not occupy any prefix. `level` can be at most `32`.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let mut trie = BinTrie::new_depth(4);
+    /// let lookup = |n: u32, l: u32| n >> (3 - l) & 1 == 1;
+    /// for &n in &[0b0001, 0b0011, 0b1000, 0b1001] {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// assert_eq!(trie.occupancy(2, lookup), vec![0b0101]);
+    /// assert_eq!(trie.occupancy(3, lookup), vec![0b0001_0011]);
+    /// ```
+    pub fn occupancy<F>(&self, level: u32, mut lookup: F) -> Vec<u64>
+    where
+        F: FnMut(u32, u32) -> bool,
+    {
+        assert!(level <= 32 && level <= self.depth);
+        let mut occupied = vec![0u64; (1usize << level).div_ceil(64)];
+        let mut mark = |prefix: usize| occupied[prefix / 64] |= 1 << (prefix % 64);
+        if level == 0 {
+            if !self.is_empty() {
+                mark(0);
+            }
+            return occupied;
+        }
+        // Each internal node along with its depth and the prefix leading to it.
+        let mut stack = vec![(0, 0, 0)];
+        while let Some((index, depth, prefix)) = stack.pop() {
+            for (position, &n) in self.children_checked(index).iter().enumerate() {
+                let prefix = prefix << 1 | position;
+                match n {
+                    0 => {}
+                    _ if depth + 1 == level => mark(prefix),
+                    // The rest of the prefix of a leaf comes from its key.
+                    n if n & HIGH != 0 => mark((depth + 1..level).fold(prefix, |prefix, l| {
+                        prefix << 1 | lookup(n & !HIGH, l) as usize
+                    })),
+                    n => stack.push((n as usize, depth + 1, prefix)),
+                }
+            }
+        }
+        occupied
+    }
+}
+
+/// Remembers the path of a previous insertion for `BinTrie::insert_after`.
+#[derive(Clone, Debug, Default)]
+pub struct InsertHint {
+    /// The internal node index and position taken at every depth.
+    path: Vec<(u32, u8)>,
+    /// The generation of the trie when the path was recorded.
+    generation: u64,
+}
+
+impl InsertHint {
+    /// Makes an empty hint, which starts insertion from the root.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Two items that share an entire key, as passed to the `merge` of `BinTrie::insert_merge`.
+///
+/// `BinTrieOwnedKeys::insert_merge` passes the key they share along with it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Collision {
+    /// The item already stored under the key.
+    pub existing: u32,
+    /// The item being inserted under the key.
+    pub incoming: u32,
+}
+
+impl BinTrie {
+    /// Records that `item` was stored in place of `replaced`.
+    #[inline(always)]
+    fn record_insertion(&mut self, item: u32, replaced: Option<u32>) {
+        if replaced.is_none() {
+            self.len += 1;
+        }
+        if let Some(order) = &mut self.insertion_order {
+            if replaced != Some(item) {
+                if let Some(position) = order.iter().position(|&n| Some(n) == replaced) {
+                    order.remove(position);
+                }
+                order.push(item);
+            }
+        }
+    }
+
+    /// Records that `item` was removed, which also removes it from the insertion order.
+    fn forget_insertion(&mut self, item: u32) {
+        self.len -= 1;
+        if let Some(order) = &mut self.insertion_order {
+            if let Some(position) = order.iter().position(|&n| n == item) {
+                order.remove(position);
+            }
+        }
+    }
+
+    /// Stores a new internal node, reusing a removed one if possible, and gets its index.
+    #[inline(always)]
+    fn try_allocate(&mut self, internal: Internal) -> Result<u32, BinTrieError> {
+        if let Some(index) = self.free.pop() {
+            self.internals[index as usize] = internal;
+            Ok(index)
+        } else {
+            let index = self.internals.len();
+            // Indices must fit beneath the high bit.
+            if index >= HIGH as usize {
+                return Err(BinTrieError::CapacityExhausted);
+            }
+            self.internals.push(internal);
+            Ok(index as u32)
+        }
+    }
+
+    /// Stores a new internal node like `try_allocate`, but panics if there is no room.
+    #[inline(always)]
+    fn allocate(&mut self, internal: Internal) -> u32 {
+        self.try_allocate(internal)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Gets the item whose key ends at the internal node at `index`, if any.
+    #[inline(always)]
+    fn terminal(&self, index: u32) -> Option<u32> {
+        if self.terminals.is_empty() {
+            None
+        } else {
+            self.terminals.get(&index).copied()
+        }
     }
 }
 
@@ -256,6 +1850,18 @@ impl Default for BinTrie {
         Self {
             internals: vec![Internal::default()],
             depth: 8192,
+            terminals: BTreeMap::new(),
+            insertion_order: None,
+            metadata: vec![],
+            top: None,
+            counts: None,
+            len: 0,
+            free: vec![],
+            buckets: None,
+            suspect: vec![],
+            reached_by: vec![],
+            generation: 0,
+            accesses: None,
         }
     }
 }
@@ -263,21 +1869,66 @@ impl Default for BinTrie {
 struct Iter<'a> {
     trie: &'a BinTrie,
     indices: Vec<slice::Iter<'a, u32>>,
+    /// The rest of the bucket of the most recently yielded leaf.
+    bucket: slice::Iter<'a, u32>,
+    /// Whether the trie is suspect, so every index must be checked.
+    checked: bool,
 }
 
 impl<'a> Iter<'a> {
     fn new(trie: &'a BinTrie) -> Self {
         Self {
             trie,
-            indices: vec![trie.internals[0].0.iter()],
+            indices: vec![trie
+                .internals
+                .first()
+                .map_or([].iter(), |root| root.0.iter())],
+            bucket: [].iter(),
+            checked: trie.is_suspect(),
+        }
+    }
+
+    /// Iterates over the items beneath a single child `slot` of an internal node.
+    fn from_slot(trie: &'a BinTrie, slot: &'a u32) -> Self {
+        Self {
+            trie,
+            indices: vec![slice::from_ref(slot).iter()],
+            bucket: [].iter(),
+            checked: trie.is_suspect(),
         }
     }
 }
 
+/// The iterator returned by `BinTrie::items`, which knows how many items are left.
+pub struct Items<'a> {
+    iter: Iter<'a>,
+    remaining: usize,
+}
+
+impl<'a> Iterator for Items<'a> {
+    type Item = u32;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.iter.next()?;
+        self.remaining -= 1;
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a> ExactSizeIterator for Items<'a> {}
+
 impl<'a> Iterator for Iter<'a> {
     type Item = u32;
     #[inline(always)]
     fn next(&mut self) -> Option<Self::Item> {
+        if let Some(&item) = self.bucket.next() {
+            return Some(item);
+        }
         loop {
             // Get the current slice. If there is none, then we return `None`.
             let mut current = self.indices.pop()?;
@@ -289,81 +1940,56 @@ impl<'a> Iterator for Iter<'a> {
             } else {
                 continue;
             };
-            // Check what kind of node it is.
-            match n {
-                // Empty node
-                0 => {}
-                // Leaf node
-                n if n & HIGH != 0 => {
-                    return Some(n & !HIGH);
-                }
-                // Internal node
-                &n => self.indices.push(self.trie.internals[n as usize].0.iter()),
-            }
-        }
-    }
-}
-
-struct ExploreIter<'a, H>
-where
-    H: Heuristic,
-{
-    trie: &'a BinTrie,
-    indices: Vec<(&'a [u32; 2], H, H::Iter)>,
-}
-
-impl<'a, H> ExploreIter<'a, H>
-where
-    H: Heuristic,
-{
-    fn new(trie: &'a BinTrie, heuristic: H) -> Self {
-        let iter = heuristic.iter();
-        Self {
-            trie,
-            indices: vec![(&trie.internals[0].0, heuristic, iter)],
-        }
-    }
-}
-
-impl<'a, H> Iterator for ExploreIter<'a, H>
-where
-    H: Heuristic,
-{
-    type Item = u32;
-    #[inline(always)]
-    fn next(&mut self) -> Option<Self::Item> {
-        loop {
-            // Get the current array, heuristic, and iter.
-            // If there is none, then we return `None`.
-            let (array, heuristic, mut iter) = self.indices.pop()?;
-            // Clone the heuristic before we put it back so we can
-            // use it when descending further.
-            let mut next_heuristic = heuristic.clone();
-            // Get the next item in the array or continue the loop if its empty.
-            let (choice, n) = if let Some(choice) = iter.next() {
-                let n = unsafe { array.get_unchecked(if choice { 1 } else { 0 }) };
-                // Push the state back.
-                self.indices.push((array, heuristic, iter));
-                (choice, n)
+            // Only follow the first slot that reaches each node of a suspect trie.
+            let n = if self.checked {
+                self.trie.slot_checked(n)
             } else {
-                continue;
+                *n
             };
             // Check what kind of node it is.
-            match n {
+            match Child::unpack(n) {
                 // Empty node
-                0 => {}
+                Child::Empty => {}
                 // Leaf node
-                n if n & HIGH != 0 => {
-                    return Some(n & !HIGH);
+                Child::Leaf(n) => {
+                    self.bucket = self.trie.bucket(n).iter();
+                    return Some(n);
                 }
                 // Internal node
-                &n => {
-                    next_heuristic.enter(choice);
-                    let iter = next_heuristic.iter();
-                    self.indices
-                        .push((&self.trie.internals[n as usize].0, next_heuristic, iter))
+                Child::Internal(n) => {
+                    let internal = if self.checked {
+                        // Skip nodes that don't exist or that are too deep, which may be cycles.
+                        match self.trie.internals.get(n as usize) {
+                            Some(internal) if self.indices.len() < self.trie.depth as usize => {
+                                internal
+                            }
+                            _ => continue,
+                        }
+                    } else {
+                        &self.trie.internals[n as usize]
+                    };
+                    self.indices.push(internal.0.iter());
+                    if let Some(item) = self.trie.terminal(n) {
+                        return Some(item);
+                    }
                 }
             }
         }
     }
 }
+
+/// The natural logarithm of a positive `x`, which also works without `std`.
+fn ln(x: f64) -> f64 {
+    // Split `x` into `m * 2^e` where `m` is in `[1, 2)`.
+    let bits = x.to_bits();
+    let e = ((bits >> 52) & 0x7ff) as i64 - 1023;
+    let m = f64::from_bits(bits & !(0x7ff << 52) | 1023 << 52);
+    // ln(m) = 2 atanh(s), where `s` is at most `1/3`, so the series converges quickly.
+    let s = (m - 1.0) / (m + 1.0);
+    let (mut term, mut sum) = (s, 0.0);
+    for n in 0..20 {
+        sum += term / (2 * n + 1) as f64;
+        term *= s * s;
+    }
+    e as f64 * core::f64::consts::LN_2 + 2.0 * sum
+}
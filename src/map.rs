@@ -0,0 +1,249 @@
+use crate::keys::collected_depth;
+use crate::{BinTrie, Collision, KeyBits};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::iter::FromIterator;
+
+/// A trie that stores an owned value of type `V` along with every item.
+///
+/// The leaves of the underlying `BinTrie` hold indices into parallel vectors of
+/// the items and their values, so values of any type can be stored without a
+/// separate table that maps items to values.
+///
+/// ```
+/// # use bintrie::BinTrieMap;
+/// let mut trie = BinTrieMap::new_depth(8);
+/// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+/// for n in 0..4 {
+///     trie.insert(n, format!("item {}", n), |l| lookup(n, l), lookup);
+/// }
+/// trie.get_mut(|l| lookup(3, l)).unwrap().1.push('!');
+/// assert_eq!(trie.get(|l| lookup(3, l)), Some((3, &"item 3!".to_owned())));
+/// assert_eq!(
+///     trie.insert(7, "seven".to_owned(), |l| lookup(3, l), lookup),
+///     Some((3, "item 3!".to_owned())),
+/// );
+/// assert_eq!(
+///     trie.iter().map(|(item, value)| (item, value.as_str())).collect::<Vec<_>>(),
+///     vec![(0, "item 0"), (2, "item 2"), (1, "item 1"), (7, "seven")],
+/// );
+/// ```
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(
+        try_from = "MapRepr<V>",
+        bound(deserialize = "V: serde::Deserialize<'de>")
+    )
+)]
+pub struct BinTrieMap<V> {
+    /// Stores an index into `items` and `values` in each leaf.
+    trie: BinTrie,
+    items: Vec<u32>,
+    values: Vec<V>,
+}
+
+impl<V> BinTrieMap<V> {
+    /// Makes a new trie with a maximum `depth` of `8192`.
+    pub fn new() -> Self {
+        Self::new_depth(8192)
+    }
+
+    /// Makes a new trie with a given maximum `depth`.
+    pub fn new_depth(depth: u32) -> Self {
+        Self {
+            trie: BinTrie::new_depth(depth),
+            items: vec![],
+            values: vec![],
+        }
+    }
+
+    /// Inserts an item along with its `value`.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the key.
+    /// `F(item, n)` - A function that must be able to look up the nth bit
+    ///    from a previously inserted item.
+    ///
+    /// Returns `Some` of a replaced item and its value if one was replaced, otherwise None.
+    pub fn insert<K, F>(
+        &mut self,
+        item: u32,
+        value: V,
+        mut key: K,
+        mut lookup: F,
+    ) -> Option<(u32, V)>
+    where
+        K: FnMut(u32) -> bool,
+        F: FnMut(u32, u32) -> bool,
+    {
+        self.insert_indexed(
+            item,
+            value,
+            |_, n| key(n),
+            |items, _, index, n| lookup(items[index as usize], n),
+        )
+    }
+
+    /// Inserts an item like `insert`, where the key may be read from the new `value` and
+    /// `lookup` gets the bits of the entry at an index from the items and values.
+    fn insert_indexed<K, F>(
+        &mut self,
+        item: u32,
+        value: V,
+        mut key: K,
+        mut lookup: F,
+    ) -> Option<(u32, V)>
+    where
+        K: FnMut(&V, u32) -> bool,
+        F: FnMut(&[u32], &[V], u32, u32) -> bool,
+    {
+        let index = self.items.len() as u32;
+        let (items, values) = (&self.items, &self.values);
+        let mut replaced = None;
+        // On a collision, the existing index is kept and its entry is overwritten.
+        self.trie.insert_merge(
+            index,
+            |n| key(&value, n),
+            |index, n| lookup(items, values, index, n),
+            |Collision { existing, .. }| {
+                replaced = Some(existing as usize);
+                existing
+            },
+        );
+        if let Some(existing) = replaced {
+            let item = core::mem::replace(&mut self.items[existing], item);
+            let value = core::mem::replace(&mut self.values[existing], value);
+            Some((item, value))
+        } else {
+            self.items.push(item);
+            self.values.push(value);
+            None
+        }
+    }
+
+    /// Perform a lookup for a particular item and its value.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the key.
+    pub fn get<K>(&self, key: K) -> Option<(u32, &V)>
+    where
+        K: FnMut(u32) -> bool,
+    {
+        let index = self.trie.get(key)? as usize;
+        Some((self.items[index], &self.values[index]))
+    }
+
+    /// Perform a lookup for a particular item and a mutable reference to its value.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the key.
+    pub fn get_mut<K>(&mut self, key: K) -> Option<(u32, &mut V)>
+    where
+        K: FnMut(u32) -> bool,
+    {
+        let index = self.trie.get(key)? as usize;
+        Some((self.items[index], &mut self.values[index]))
+    }
+
+    /// Get an iterator over the items added to the trie along with their values.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (u32, &V)> + '_ {
+        self.trie
+            .items()
+            .map(move |index| (self.items[index as usize], &self.values[index as usize]))
+    }
+
+    /// The number of items in the trie.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Checks if the trie has no items.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+impl<V> Default for BinTrieMap<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Builds a map from items to their keys, which lets it be extended later.
+///
+/// The depth is chosen like `FromIterator` for `BinTrie` does.
+///
+/// ```
+/// # use bintrie::BinTrieMap;
+/// let mut trie: BinTrieMap<u32> = (0..4).map(|n| (n, n * 3)).collect();
+/// trie.extend((4..8).map(|n| (n, n * 3)));
+/// assert_eq!(trie.get(|l| 15 >> l & 1 == 1), Some((5, &15)));
+/// assert_eq!(trie.len(), 8);
+/// ```
+impl<K> FromIterator<(u32, K)> for BinTrieMap<K>
+where
+    K: KeyBits,
+{
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = (u32, K)>,
+    {
+        let pairs: Vec<(u32, K)> = iter.into_iter().collect();
+        let mut trie = Self::new_depth(collected_depth(pairs.iter().map(|(_, key)| key)));
+        trie.extend(pairs);
+        trie
+    }
+}
+
+/// Inserts every item with its key, replacing any item that has the same key.
+impl<K> Extend<(u32, K)> for BinTrieMap<K>
+where
+    K: KeyBits,
+{
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = (u32, K)>,
+    {
+        for (item, key) in iter {
+            self.insert_indexed(
+                item,
+                key,
+                |key, n| key.bit(n),
+                |_, keys, index, n| keys[index as usize].bit(n),
+            );
+        }
+    }
+}
+
+/// The serialized form of a `BinTrieMap`, before it has been validated.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct MapRepr<V> {
+    trie: BinTrie,
+    items: Vec<u32>,
+    values: Vec<V>,
+}
+
+#[cfg(feature = "serde")]
+impl<V> core::convert::TryFrom<MapRepr<V>> for BinTrieMap<V> {
+    type Error = crate::InvalidTrieError;
+
+    /// Makes sure that every leaf refers to an item and a value.
+    fn try_from(repr: MapRepr<V>) -> Result<Self, Self::Error> {
+        let MapRepr {
+            trie,
+            items,
+            values,
+        } = repr;
+        if items.len() != values.len() {
+            return Err(crate::InvalidTrieError::InvalidEntry(values.len() as u32));
+        }
+        if let Some(index) = trie.items().find(|&index| index as usize >= items.len()) {
+            return Err(crate::InvalidTrieError::InvalidEntry(index));
+        }
+        Ok(Self {
+            trie,
+            items,
+            values,
+        })
+    }
+}
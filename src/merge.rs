@@ -0,0 +1,161 @@
+use crate::{BinTrie, Child, Collision, FastMap, Internal};
+use alloc::vec;
+
+impl BinTrie {
+    /// Inserts every item of `other` into this trie.
+    ///
+    /// Both tries must have the same depth and be built with the same key function.
+    /// They are walked together, and wherever `other` has a subtree that this trie
+    /// has nothing at, the whole subtree is copied over without looking up any keys.
+    /// Only items that land where this trie already has a leaf are inserted one at
+    /// a time. This makes combining tries built on separate threads cheap when
+    /// their items are mostly in different parts of the trie.
+    ///
+    /// Items of `other` replace items of this trie with the same key, like `insert`,
+    /// unless this trie is bucketing, in which case both are kept. If `other` has
+    /// items in buckets, this trie starts bucketing too, so that none of them are lost.
+    ///
+    /// `F(item, n)` - A function that must be able to look up the nth bit
+    ///    from an item of either trie.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// let mut evens = BinTrie::new_depth(8);
+    /// let mut odds = BinTrie::new_depth(8);
+    /// for n in 0..64 {
+    ///     let trie = if n % 2 == 0 { &mut evens } else { &mut odds };
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// evens.merge(&odds, lookup);
+    /// let mut all = BinTrie::new_depth(8);
+    /// for n in 0..64 {
+    ///     all.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// assert_eq!(evens.len(), 64);
+    /// assert_eq!(evens.items().collect::<Vec<_>>(), all.items().collect::<Vec<_>>());
+    ///
+    /// // Merging a bucketed trie keeps the items that share a key.
+    /// let mut bucketed = BinTrie::new_depth(2).with_buckets();
+    /// for n in 0..8 {
+    ///     bucketed.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// let mut plain = BinTrie::new_depth(2);
+    /// plain.merge(&bucketed, lookup);
+    /// assert_eq!(plain.len(), 8);
+    /// assert_eq!(plain.get_all(|l| lookup(1, l)), vec![1, 5]);
+    /// ```
+    pub fn merge<F>(&mut self, other: &BinTrie, lookup: F)
+    where
+        F: Fn(u32, u32) -> bool,
+    {
+        self.assert_trusted();
+        other.assert_trusted();
+        assert_eq!(self.depth, other.depth);
+        // The table of the top levels and the subtree counts would become stale.
+        self.top = None;
+        self.counts = None;
+        if other
+            .buckets
+            .as_ref()
+            .is_some_and(|buckets| !buckets.is_empty())
+        {
+            self.buckets.get_or_insert_with(FastMap::new);
+        }
+        // Items of `other` that land on a leaf of this trie.
+        let mut incoming = vec![];
+        // Items of this trie whose leaves were replaced by subtrees of `other`.
+        let mut displaced = vec![];
+        // Internal nodes at the same place in both tries.
+        let mut stack = vec![(0, 0)];
+        while let Some((index, other_index)) = stack.pop() {
+            if let Some(item) = other.terminal(other_index) {
+                let replaced = self.terminals.insert(index, item);
+                self.record_insertion(item, replaced);
+            }
+            for position in 0..2 {
+                let theirs = other.internals[other_index as usize].0[position];
+                let ours = self.internals[index as usize].0[position];
+                match (Child::unpack(ours), Child::unpack(theirs)) {
+                    (_, Child::Empty) => {}
+                    (_, Child::Leaf(m)) => {
+                        incoming.push(m);
+                        incoming.extend_from_slice(other.bucket(m));
+                    }
+                    (Child::Empty, Child::Internal(m)) => {
+                        let spliced = self.splice(other, m);
+                        self.internals[index as usize].0[position] = spliced;
+                    }
+                    (Child::Leaf(item), Child::Internal(m)) => {
+                        displaced.push(item);
+                        if let Some(bucket) = self.buckets.as_mut().and_then(|b| b.remove(&item)) {
+                            displaced.extend(bucket);
+                        }
+                        let spliced = self.splice(other, m);
+                        self.internals[index as usize].0[position] = spliced;
+                    }
+                    (Child::Internal(n), Child::Internal(m)) => stack.push((n, m)),
+                }
+            }
+        }
+        for item in incoming {
+            self.insert(item, |l| lookup(item, l), &lookup);
+        }
+        // Items that were already here lose to the items from `other`.
+        for &item in &displaced {
+            self.forget_insertion(item);
+        }
+        for item in displaced {
+            self.insert_merge(
+                item,
+                |l| lookup(item, l),
+                &lookup,
+                |Collision { existing, .. }| existing,
+            );
+        }
+    }
+
+    /// Copies the subtree of `other` beneath the internal node `root` into this trie.
+    ///
+    /// Returns the index of the copy of `root`.
+    fn splice(&mut self, other: &BinTrie, root: u32) -> u32 {
+        let copy = |trie: &mut BinTrie| {
+            trie.try_allocate(Internal::default())
+                .unwrap_or_else(|error| panic!("{}", error))
+        };
+        let spliced = copy(self);
+        let mut stack = vec![(root, spliced)];
+        while let Some((from, to)) = stack.pop() {
+            if let Some(item) = other.terminal(from) {
+                self.terminals.insert(to, item);
+                self.record_insertion(item, None);
+            }
+            for position in 0..2 {
+                let n = other.internals[from as usize].0[position];
+                let child = match Child::unpack(n) {
+                    Child::Empty => 0,
+                    Child::Leaf(item) => {
+                        self.record_insertion(item, None);
+                        let bucket = other.bucket(item);
+                        if !bucket.is_empty() {
+                            // `merge` made this trie bucket if `other` has any buckets.
+                            let buckets = self.buckets.get_or_insert_with(FastMap::new);
+                            buckets.insert(item, bucket.to_vec());
+                            for &item in bucket {
+                                self.record_insertion(item, None);
+                            }
+                        }
+                        n
+                    }
+                    Child::Internal(n) => {
+                        let child = copy(self);
+                        stack.push((n, child));
+                        child
+                    }
+                };
+                self.internals[to as usize].0[position] = child;
+            }
+        }
+        spliced
+    }
+}
@@ -0,0 +1,101 @@
+use crate::explore::{accept_all, ExploreIter, Location, OwnedExplore};
+use crate::{BinTrie, FastSet, Heuristic};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::sync::atomic::AtomicBool;
+
+/// A single probe, which accepts every leaf.
+type Probe<'a, H> = OwnedExplore<'a, H, fn(u32) -> bool>;
+
+/// The iterator returned by `BinTrie::explore_multi`.
+///
+/// Every heuristic is a probe with its own exploration. The probes take turns
+/// yielding an item, and every internal node any of them enters is taken from a
+/// shared budget. A leaf is only yielded the first time any probe finds it.
+pub struct MultiExplore<'a, H>
+where
+    H: Heuristic,
+{
+    probes: Vec<Probe<'a, H>>,
+    /// The probe whose turn it is.
+    turn: usize,
+    /// The number of internal nodes that may still be entered.
+    remaining: usize,
+    /// The locations of every leaf yielded so far, with the item since a bucket shares
+    /// the location of its leaf.
+    seen: FastSet<(Option<Location<u32>>, u32)>,
+}
+
+impl<'a, H> MultiExplore<'a, H>
+where
+    H: Heuristic,
+{
+    pub(crate) fn new<I>(trie: &'a BinTrie, heuristics: I, budget: usize) -> Self
+    where
+        I: IntoIterator<Item = H>,
+    {
+        Self {
+            probes: heuristics
+                .into_iter()
+                .map(|heuristic| {
+                    ExploreIter::new(trie, heuristic, vec![], accept_all as fn(u32) -> bool)
+                })
+                .collect(),
+            turn: 0,
+            remaining: budget,
+            seen: FastSet::new(),
+        }
+    }
+
+    /// The number of internal nodes that may still be entered by the probes.
+    pub fn remaining_budget(&self) -> usize {
+        self.remaining
+    }
+
+    /// Stops every probe once `cancel` is set, like `BinTrie::explore_cancellable`.
+    pub fn cancel_on(self, cancel: &'a AtomicBool) -> Self {
+        Self {
+            probes: self
+                .probes
+                .into_iter()
+                .map(|probe| probe.cancel_on(cancel))
+                .collect(),
+            ..self
+        }
+    }
+}
+
+impl<'a, H> Iterator for MultiExplore<'a, H>
+where
+    H: Heuristic,
+{
+    type Item = u32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.probes.is_empty() {
+                return None;
+            }
+            let turn = self.turn % self.probes.len();
+            let probe = &mut self.probes[turn];
+            // Let the probe use whatever budget is left.
+            let entered = probe.entered;
+            probe.limit = entered.saturating_add(self.remaining);
+            let item = probe.next();
+            self.remaining -= probe.entered - entered;
+            match item {
+                Some(item) => {
+                    self.turn = turn + 1;
+                    if self.seen.insert((probe.leaves.location, item)) {
+                        return Some(item);
+                    }
+                }
+                // The probe is either done or out of budget, which never grows back.
+                None => {
+                    self.probes.remove(turn);
+                    self.turn = turn;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,368 @@
+use crate::{BinTrie, Collision, CompiledHeuristic, FastMap, FromKeyBits, KeyBits};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Gets bit `n` of packed key words.
+#[inline(always)]
+fn packed_bit(words: &[u64], n: u32) -> bool {
+    words[n as usize / 64] >> (n % 64) & 1 == 1
+}
+
+/// Counts the bits that differ between two packed keys.
+///
+/// The words are xored and counted four at a time, which the compiler turns into
+/// vector popcounts on targets that have them.
+#[inline]
+fn hamming(a: &[u64], b: &[u64]) -> u32 {
+    let (a_chunks, b_chunks) = (a.chunks_exact(4), b.chunks_exact(4));
+    let rest = a_chunks
+        .remainder()
+        .iter()
+        .zip(b_chunks.remainder())
+        .map(|(a, b)| (a ^ b).count_ones())
+        .sum::<u32>();
+    let mut lanes = [0u32; 4];
+    for (a, b) in a_chunks.zip(b_chunks) {
+        for lane in 0..4 {
+            lanes[lane] += (a[lane] ^ b[lane]).count_ones();
+        }
+    }
+    lanes.iter().sum::<u32>() + rest
+}
+
+/// A trie that keeps a copy of the key of every item.
+///
+/// A `BinTrie` needs a lookup function that reproduces the key bits of previously
+/// inserted items, and if that disagrees with the keys that were inserted, the
+/// trie is silently corrupted. This instead records the bits of every key up to
+/// the depth of the trie, packed into `u64` words, so inserting never calls back
+/// into user code. Since the whole key is known, `get` also only returns an item
+/// whose key matches exactly.
+///
+/// ```
+/// # use bintrie::BinTrieOwnedKeys;
+/// let mut trie = BinTrieOwnedKeys::new_depth(16);
+/// for n in 0..100u32 {
+///     trie.insert(n, |l| (n * 31) >> l & 1 == 1);
+/// }
+/// assert_eq!(trie.get(|l| (62u32 >> l) & 1 == 1), Some(2));
+/// assert_eq!(trie.get_key(&93u32), Some(3));
+/// assert_eq!(trie.key(3), Some(&[93][..]));
+/// // Items can be explored through the underlying trie.
+/// assert_eq!(trie.trie().items().len(), 100);
+/// ```
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "OwnedKeysRepr"))]
+pub struct BinTrieOwnedKeys {
+    trie: BinTrie,
+    /// The number of words each key is packed into.
+    words: usize,
+    /// The packed keys, `words` at a time.
+    keys: Vec<u64>,
+    /// Where the key of each item is stored in `keys`, in multiples of `words`.
+    slots: FastMap<u32, usize>,
+}
+
+impl BinTrieOwnedKeys {
+    /// Makes a new trie with a given maximum `depth`.
+    ///
+    /// Every key takes up `depth` bits, rounded up to a multiple of `64`.
+    pub fn new_depth(depth: u32) -> Self {
+        Self {
+            trie: BinTrie::new_depth(depth),
+            words: depth.div_ceil(64) as usize,
+            keys: vec![],
+            slots: FastMap::new(),
+        }
+    }
+
+    /// Inserts a number that does not have the most significant bit set.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the key.
+    ///
+    /// The key is only read while inserting, and is kept for the rest of the life of
+    /// the item. An item may only be stored under a single key, so inserting an item
+    /// which is already in the trie with a different key panics.
+    ///
+    /// Returns `Some` of a replaced leaf if a leaf was replaced, otherwise None.
+    pub fn insert<K>(&mut self, item: u32, key: K) -> Option<u32>
+    where
+        K: FnMut(u32) -> bool,
+    {
+        self.insert_merge(item, key, |collision, _| collision.incoming)
+    }
+
+    /// Inserts an item, letting `merge` decide what to keep if its key is already taken.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the key.
+    /// `M(collision, key)` - A function that is passed both items and the packed words
+    ///    of the key they share, and returns the item to store under the key.
+    ///
+    /// This works like `BinTrie::insert_merge`, except that the key is passed along.
+    /// Whichever item is returned takes over the key, and the item that isn't kept
+    /// no longer has one. Returning an item which is already stored under a different
+    /// key panics, just like inserting it would.
+    ///
+    /// Returns `Some` of the existing item if there was a collision, otherwise `None`.
+    ///
+    /// ```
+    /// # use bintrie::BinTrieOwnedKeys;
+    /// let mut trie = BinTrieOwnedKeys::new_depth(8);
+    /// trie.insert_key(3, &5u32);
+    /// // Keep whichever item is nearer to the key.
+    /// let existing = trie.insert_merge(4, |l| 5u32 >> l & 1 == 1, |c, key| {
+    ///     let distance = |n: u32| (i64::from(n) - key[0] as i64).abs();
+    ///     if distance(c.incoming) < distance(c.existing) { c.incoming } else { c.existing }
+    /// });
+    /// assert_eq!(existing, Some(3));
+    /// assert_eq!(trie.get_key(&5u32), Some(4));
+    /// assert_eq!(trie.key(4), Some(&[5][..]));
+    /// assert_eq!(trie.key(3), None);
+    /// ```
+    pub fn insert_merge<K, M>(&mut self, item: u32, mut key: K, merge: M) -> Option<u32>
+    where
+        K: FnMut(u32) -> bool,
+        M: FnOnce(Collision, &[u64]) -> u32,
+    {
+        let depth = self.trie.depth();
+        let mut packed = vec![0u64; self.words];
+        for n in 0..depth {
+            packed[n as usize / 64] |= u64::from(key(n)) << (n % 64);
+        }
+        if let Some(existing) = self.key(item) {
+            assert!(
+                existing == &packed[..],
+                "item {} is already stored under a different key",
+                item
+            );
+        }
+        let Self {
+            trie,
+            words,
+            keys,
+            slots,
+        } = self;
+        let words = *words;
+        let mut kept = item;
+        let replaced = trie.insert_merge(
+            item,
+            |n| packed_bit(&packed, n),
+            |item, n| packed_bit(&keys[slots[&item] * words..], n),
+            |collision| {
+                kept = merge(collision, &packed);
+                if kept != collision.existing {
+                    if let Some(&slot) = slots.get(&kept) {
+                        assert!(
+                            keys[slot * words..(slot + 1) * words] == packed[..],
+                            "item {} is already stored under a different key",
+                            kept
+                        );
+                    }
+                }
+                kept
+            },
+        );
+        match replaced {
+            // The existing item was kept under its key.
+            Some(old) if old == kept => {}
+            // The kept item takes over the key of the old one.
+            Some(old) => {
+                let slot = slots.remove(&old).expect("every item has a key");
+                slots.insert(kept, slot);
+            }
+            None => {
+                slots.insert(item, keys.len() / words);
+                keys.extend_from_slice(&packed);
+            }
+        }
+        replaced
+    }
+
+    /// Inserts an item with a `key` that provides its own bits, like `insert`.
+    pub fn insert_key<Q>(&mut self, item: u32, key: &Q) -> Option<u32>
+    where
+        Q: KeyBits + ?Sized,
+    {
+        self.insert(item, |n| key.bit(n))
+    }
+
+    /// Perform a lookup for the item with exactly this key.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the key.
+    pub fn get<K>(&self, mut key: K) -> Option<u32>
+    where
+        K: FnMut(u32) -> bool,
+    {
+        let mut bits = vec![];
+        let item = self.trie.get(|n| {
+            let bit = key(n);
+            bits.push(bit);
+            bit
+        })?;
+        // The trie only looks at as much of the key as is needed to find a leaf.
+        let stored = self.key(item).expect("every item has a key");
+        let matches = (0..self.trie.depth()).all(|n| {
+            let bit = bits.get(n as usize).copied().unwrap_or_else(|| key(n));
+            bit == packed_bit(stored, n)
+        });
+        if matches {
+            Some(item)
+        } else {
+            None
+        }
+    }
+
+    /// Perform a lookup for the item with exactly `key`, which provides its own bits.
+    pub fn get_key<Q>(&self, key: &Q) -> Option<u32>
+    where
+        Q: KeyBits + ?Sized,
+    {
+        self.get(|n| key.bit(n))
+    }
+
+    /// Iterates over every item within Hamming distance `radius` of `query`.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the query key.
+    ///
+    /// Candidates are found like `BinTrie::within_distance`, but since leaves can be
+    /// stored above the last level, some of them are further away than `radius`. The
+    /// exact distance of every candidate is computed by a popcount over its stored key,
+    /// so no lookup function is needed and only items within the radius are yielded,
+    /// each with its distance, in the order they are found.
+    ///
+    /// ```
+    /// # use bintrie::BinTrieOwnedKeys;
+    /// let mut trie = BinTrieOwnedKeys::new_depth(100);
+    /// let key = |n: u32| move |l: u32| (u128::from(n) * 0x9E37_79B9_7F4A_7C15) >> l & 1 == 1;
+    /// for n in 0..500 {
+    ///     trie.insert(n, key(n));
+    /// }
+    /// let distance = |a: u32, b: u32| (0..100).filter(|&l| key(a)(l) != key(b)(l)).count() as u32;
+    /// let mut found = trie.within_radius(key(7), 40).collect::<Vec<_>>();
+    /// found.sort();
+    /// let expected = (0..500)
+    ///     .map(|n| (n, distance(n, 7)))
+    ///     .filter(|&(_, d)| d <= 40)
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(found, expected);
+    /// assert!(found.contains(&(7, 0)));
+    /// ```
+    pub fn within_radius<'a, K>(
+        &'a self,
+        mut query: K,
+        radius: u32,
+    ) -> impl Iterator<Item = (u32, u32)> + 'a
+    where
+        K: FnMut(u32) -> bool,
+    {
+        let depth = self.trie.depth();
+        let mut packed = vec![0u64; self.words];
+        for n in 0..depth {
+            packed[n as usize / 64] |= u64::from(query(n)) << (n % 64);
+        }
+        let heuristic = CompiledHeuristic::hamming(|n| packed_bit(&packed, n), depth, radius);
+        self.trie.explore(heuristic).filter_map(move |item| {
+            let stored = self.key(item).expect("every item has a key");
+            let distance = hamming(stored, &packed);
+            Some((item, distance)).filter(|_| distance <= radius)
+        })
+    }
+
+    /// Gets the key of `item`, with bit `n` stored in bit `n % 64` of word `n / 64`.
+    pub fn key(&self, item: u32) -> Option<&[u64]> {
+        let slot = *self.slots.get(&item)?;
+        Some(&self.keys[slot * self.words..(slot + 1) * self.words])
+    }
+
+    /// Iterates over the items along with their keys, rebuilt as a `Q`.
+    ///
+    /// Items are in the same order as `BinTrie::items`. Only the first `depth` bits of
+    /// every key are stored, so keys with more bits than that come back with the rest
+    /// of their bits `false`.
+    ///
+    /// ```
+    /// # use bintrie::BinTrieOwnedKeys;
+    /// let mut trie = BinTrieOwnedKeys::new_depth(64);
+    /// let keys = [0xDEAD_BEEFu64, 0xCAFE, 1 << 63];
+    /// for (n, key) in keys.iter().enumerate() {
+    ///     trie.insert_key(n as u32, key);
+    /// }
+    /// let mut found = trie.iter::<u64>().collect::<Vec<_>>();
+    /// found.sort_by_key(|&(_, item)| item);
+    /// assert_eq!(found, vec![(0xDEAD_BEEF, 0), (0xCAFE, 1), (1 << 63, 2)]);
+    ///
+    /// let mut trie = BinTrieOwnedKeys::new_depth(24);
+    /// trie.insert_key(0, b"abc");
+    /// trie.insert_key(1, &b"xyz"[..]);
+    /// let mut keys = trie.keys::<[u8; 3]>().collect::<Vec<_>>();
+    /// keys.sort();
+    /// assert_eq!(keys, vec![*b"abc", *b"xyz"]);
+    /// assert!(trie.keys::<Vec<u8>>().all(|key| key.len() == 3));
+    /// ```
+    pub fn iter<Q>(&self) -> impl Iterator<Item = (Q, u32)> + '_
+    where
+        Q: FromKeyBits,
+    {
+        let depth = self.trie.depth();
+        self.trie.items().map(move |item| {
+            let stored = self.key(item).expect("every item has a key");
+            (Q::from_bits(depth, |n| packed_bit(stored, n)), item)
+        })
+    }
+
+    /// Iterates over the keys of the items, rebuilt as a `Q`, like `iter`.
+    pub fn keys<Q>(&self) -> impl Iterator<Item = Q> + '_
+    where
+        Q: FromKeyBits,
+    {
+        self.iter().map(|(key, _)| key)
+    }
+
+    /// Gets the underlying trie, which can be used to explore the items.
+    pub fn trie(&self) -> &BinTrie {
+        &self.trie
+    }
+}
+
+/// The serialized form of a `BinTrieOwnedKeys`, before it has been validated.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct OwnedKeysRepr {
+    trie: BinTrie,
+    words: usize,
+    keys: Vec<u64>,
+    slots: FastMap<u32, usize>,
+}
+
+#[cfg(feature = "serde")]
+impl core::convert::TryFrom<OwnedKeysRepr> for BinTrieOwnedKeys {
+    type Error = crate::InvalidTrieError;
+
+    /// Makes sure that every item has a whole key.
+    fn try_from(repr: OwnedKeysRepr) -> Result<Self, Self::Error> {
+        let OwnedKeysRepr {
+            trie,
+            words,
+            keys,
+            slots,
+        } = repr;
+        if words != trie.depth().div_ceil(64) as usize {
+            return Err(crate::InvalidTrieError::InvalidEntry(words as u32));
+        }
+        let missing = trie.items().find(|item| {
+            slots
+                .get(item)
+                .is_none_or(|&slot| (slot + 1) * words > keys.len())
+        });
+        if let Some(item) = missing {
+            return Err(crate::InvalidTrieError::InvalidEntry(item));
+        }
+        Ok(Self {
+            trie,
+            words,
+            keys,
+            slots,
+        })
+    }
+}
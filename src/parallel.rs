@@ -0,0 +1,170 @@
+use crate::explore::{accept_all, ExploreIter, Shifted};
+use crate::{BinTrie, Heuristic, IntoHeuristic, Iter, HIGH};
+use alloc::vec;
+use alloc::vec::Vec;
+use rayon::iter::Either;
+use rayon::prelude::*;
+
+/// How many parts the trie is split into for every thread, so that uneven subtrees
+/// can still be balanced between the threads.
+const PARTS_PER_THREAD: usize = 4;
+
+/// A part of the trie that a thread iterates over on its own.
+enum Part<'a, H> {
+    /// An item that was found while splitting the trie.
+    Item(u32),
+    /// The subtree beneath a child slot, along with the heuristic that reached it
+    /// and the level of the node in the slot.
+    Slot(&'a u32, H, u32),
+}
+
+impl BinTrie {
+    /// Get a parallel iterator over the items added to the trie.
+    ///
+    /// The trie is split into subtrees that are iterated over on separate threads.
+    /// Collecting the items keeps them in the same order as `items`.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// use rayon::prelude::*;
+    /// let mut trie = BinTrie::new_depth(16);
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// for n in 0..1000 {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// let items = trie.par_items().collect::<Vec<u32>>();
+    /// assert_eq!(items, trie.items().collect::<Vec<u32>>());
+    /// assert_eq!(trie.par_items().filter(|&n| n % 2 == 0).count(), 500);
+    /// ```
+    pub fn par_items(&self) -> impl ParallelIterator<Item = u32> + '_ {
+        let mut parts = vec![];
+        if !self.internals.is_empty() {
+            self.expand_items(0, (), 0, &mut parts);
+        }
+        self.split(parts, Self::expand_items)
+            .into_par_iter()
+            .flat_map_iter(move |part| match part {
+                Part::Item(item) => Either::Left(core::iter::once(item)),
+                Part::Slot(slot, (), _) => Either::Right(Iter::from_slot(self, slot)),
+            })
+    }
+
+    /// Explores the trie using a heuristic like `explore`, but on separate threads.
+    ///
+    /// The heuristic is run from the root until there are enough subtrees for every
+    /// thread, and then each subtree is explored on its own. Collecting the items
+    /// keeps them in the same order as `explore`.
+    ///
+    /// ```
+    /// # use bintrie::{BinTrie, HammingRadius};
+    /// use rayon::prelude::*;
+    /// let mut trie = BinTrie::new_depth(12);
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// for n in 0..4096 {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// let query = HammingRadius::new(0b1010_0101_1100, 2);
+    /// let found = trie.par_explore(query.clone()).collect::<Vec<u32>>();
+    /// assert_eq!(found.len(), 1 + 12 + 66);
+    /// assert_eq!(found, trie.explore(query).collect::<Vec<u32>>());
+    /// ```
+    pub fn par_explore<'a, H>(&'a self, heuristic: H) -> impl ParallelIterator<Item = u32> + 'a
+    where
+        H: IntoHeuristic,
+        H::Heuristic: Send + 'a,
+    {
+        let mut parts = vec![];
+        if !self.internals.is_empty() {
+            let mut heuristic = heuristic.into_heuristic();
+            heuristic.begin();
+            self.expand_explore(0, heuristic, 0, &mut parts);
+        }
+        self.split(parts, Self::expand_explore)
+            .into_par_iter()
+            .flat_map_iter(move |part| match part {
+                Part::Item(item) => Either::Left(core::iter::once(item)),
+                Part::Slot(&n, heuristic, levels) => Either::Right(ExploreIter::new_at(
+                    self,
+                    n,
+                    Shifted { heuristic, levels },
+                    vec![],
+                    accept_all,
+                )),
+            })
+    }
+
+    /// Splits subtrees into the parts beneath them until there are enough parts for
+    /// every thread or only leaves are left.
+    ///
+    /// `E(index, heuristic, level, parts)` - Adds the parts beneath the internal node
+    ///    at `index` on `level` in the order they are iterated over.
+    fn split<'a, H, E>(&'a self, mut parts: Vec<Part<'a, H>>, mut expand: E) -> Vec<Part<'a, H>>
+    where
+        E: FnMut(&'a Self, u32, H, u32, &mut Vec<Part<'a, H>>),
+    {
+        let target = rayon::current_num_threads() * PARTS_PER_THREAD;
+        // Every round goes one level deeper, which also stops at cycles in a suspect trie.
+        for _ in 0..self.depth {
+            if parts.len() >= target {
+                break;
+            }
+            let mut split = Vec::with_capacity(parts.len() * 2);
+            let mut expanded = false;
+            for part in parts {
+                match part {
+                    // Only the first slot that reaches each node of a suspect trie is followed.
+                    Part::Slot(slot, heuristic, level) if matches!(self.slot_checked(slot), n if n != 0 && n & HIGH == 0) =>
+                    {
+                        expand(self, *slot, heuristic, level, &mut split);
+                        expanded = true;
+                    }
+                    part => split.push(part),
+                }
+            }
+            parts = split;
+            if !expanded {
+                break;
+            }
+        }
+        parts
+    }
+
+    /// Adds the parts beneath an internal node in the order of `items`.
+    fn expand_items<'a>(&'a self, index: u32, _: (), level: u32, parts: &mut Vec<Part<'a, ()>>) {
+        if index != 0 {
+            parts.extend(self.terminal(index).map(Part::Item));
+        }
+        for slot in &self.internals[index as usize].0 {
+            if *slot != 0 {
+                parts.push(Part::Slot(slot, (), level + 1));
+            }
+        }
+    }
+
+    /// Adds the parts beneath an internal node in the order a heuristic chooses them.
+    fn expand_explore<'a, H>(
+        &'a self,
+        index: u32,
+        heuristic: H,
+        level: u32,
+        parts: &mut Vec<Part<'a, H>>,
+    ) where
+        H: Heuristic,
+    {
+        for side in heuristic.iter(level) {
+            let slot = &self.internals[index as usize].0[side.index()];
+            // Only the first slot that reaches each node of a suspect trie is followed.
+            match self.slot_checked(slot) {
+                0 => {}
+                n if n & HIGH != 0 => parts.push(Part::Item(n & !HIGH)),
+                n => {
+                    let mut heuristic = heuristic.clone();
+                    heuristic.enter(side, level);
+                    // Exploring a subtree does not yield the item terminated at its root.
+                    parts.extend(self.terminal(n).map(Part::Item));
+                    parts.push(Part::Slot(slot, heuristic, level + 1));
+                }
+            }
+        }
+    }
+}
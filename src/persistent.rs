@@ -0,0 +1,287 @@
+use crate::explore::{accept_all, ExploreIter};
+use crate::{Child, FilterHeuristic, IntoHeuristic, Nodes};
+use alloc::sync::Arc;
+use alloc::vec;
+use core::cmp::Ordering;
+use core::hash::{Hash, Hasher};
+use core::ptr;
+
+/// A child of an internal node of a `PersistentBinTrie`.
+#[derive(Clone, Debug, Default)]
+enum Slot {
+    #[default]
+    Empty,
+    Leaf(u32),
+    /// Internal nodes are shared between snapshots until one of them changes.
+    Internal(Arc<Node>),
+}
+
+impl Slot {
+    /// Decodes the slot into the child it holds.
+    #[inline(always)]
+    fn child(&self) -> Child<NodeRef<'_>> {
+        match self {
+            Slot::Empty => Child::Empty,
+            &Slot::Leaf(m) => Child::Leaf(m),
+            Slot::Internal(node) => Child::Internal(NodeRef(node)),
+        }
+    }
+}
+
+/// An internal node of a `PersistentBinTrie`.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Node {
+    children: [Slot; 2],
+}
+
+/// Identifies an internal node of a `PersistentBinTrie` by its address.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct NodeRef<'a>(&'a Node);
+
+impl PartialEq for NodeRef<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        ptr::eq(self.0, other.0)
+    }
+}
+
+impl Eq for NodeRef<'_> {}
+
+impl Hash for NodeRef<'_> {
+    fn hash<S: Hasher>(&self, state: &mut S) {
+        ptr::hash(self.0, state)
+    }
+}
+
+impl PartialOrd for NodeRef<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for NodeRef<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.0 as *const Node).cmp(&(other.0 as *const Node))
+    }
+}
+
+/// A trie with cheap point-in-time snapshots that share their unchanged nodes.
+///
+/// Every internal node is reference counted, so `snapshot` only clones the root
+/// pointer. Inserting copies the nodes along the path to the new item that are
+/// still shared with a snapshot and leaves everything else shared, so a snapshot
+/// keeps seeing the items it was taken with while the trie keeps changing. This
+/// lets readers keep querying a snapshot on other threads during reindexing.
+///
+/// Since nodes are allocated individually, this uses more memory and is slower
+/// to walk than a `BinTrie`. Any `u32` can be stored as an item.
+///
+/// ```
+/// # use bintrie::PersistentBinTrie;
+/// let mut trie = PersistentBinTrie::new_depth(8);
+/// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+/// for n in 0..16 {
+///     trie.insert(n, |l| lookup(n, l), lookup);
+/// }
+/// let snapshot = trie.snapshot();
+/// for n in 16..32 {
+///     trie.insert(n, |l| lookup(n, l), lookup);
+/// }
+/// assert_eq!(snapshot.len(), 16);
+/// assert_eq!(snapshot.items().max(), Some(15));
+/// assert_eq!(trie.len(), 32);
+/// assert_eq!(trie.get(|l| lookup(20, l)), Some(20));
+/// ```
+#[derive(Clone, Debug)]
+pub struct PersistentBinTrie {
+    root: Arc<Node>,
+    /// The maximum depth to stop at.
+    depth: u32,
+    /// The number of items in the trie.
+    len: usize,
+}
+
+impl Default for PersistentBinTrie {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PersistentBinTrie {
+    /// Makes a new trie with a maximum `depth` of `8192`.
+    pub fn new() -> Self {
+        Self::new_depth(8192)
+    }
+
+    /// Makes a new trie with a given maximum `depth`.
+    pub fn new_depth(depth: u32) -> Self {
+        assert!(depth > 0);
+        Self {
+            root: Arc::default(),
+            depth,
+            len: 0,
+        }
+    }
+
+    /// The maximum depth of the trie.
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    /// The number of items in the trie.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Checks if the trie has no items.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Takes a snapshot of the trie in constant time.
+    ///
+    /// Later changes to either trie are not seen by the other one.
+    pub fn snapshot(&self) -> Self {
+        self.clone()
+    }
+
+    /// Inserts an item, copying the nodes on its path that are shared with a snapshot.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the key.
+    /// `F(item, n)` - A function that must be able to look up the nth bit
+    ///    from a previously inserted item.
+    ///
+    /// Returns `Some` of a replaced leaf if a leaf was replaced, otherwise None.
+    pub fn insert<K, F>(&mut self, item: u32, mut key: K, mut lookup: F) -> Option<u32>
+    where
+        K: FnMut(u32) -> bool,
+        F: FnMut(u32, u32) -> bool,
+    {
+        let mut node = Arc::make_mut(&mut self.root);
+        for i in 0..self.depth - 1 {
+            let slot = &mut node.children[key(i) as usize];
+            match *slot {
+                // Empty node encountered.
+                Slot::Empty => {
+                    *slot = Slot::Leaf(item);
+                    self.len += 1;
+                    return None;
+                }
+                // Leaf node encountered.
+                Slot::Leaf(m) => {
+                    let mut new_internal = Node::default();
+                    new_internal.children[lookup(m, i + 1) as usize] = Slot::Leaf(m);
+                    *slot = Slot::Internal(Arc::new(new_internal));
+                }
+                // Internal node encountered.
+                Slot::Internal(_) => {}
+            }
+            node = match slot {
+                Slot::Internal(child) => Arc::make_mut(child),
+                _ => unreachable!(),
+            };
+        }
+        let slot = &mut node.children[key(self.depth - 1) as usize];
+        match core::mem::replace(slot, Slot::Leaf(item)) {
+            Slot::Leaf(m) => Some(m),
+            _ => {
+                self.len += 1;
+                None
+            }
+        }
+    }
+
+    /// Perform a lookup for a particular item.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the key.
+    pub fn get<K>(&self, mut key: K) -> Option<u32>
+    where
+        K: FnMut(u32) -> bool,
+    {
+        let mut node = &*self.root;
+        for i in 0..self.depth {
+            match &node.children[key(i) as usize] {
+                // Empty node encountered.
+                Slot::Empty => return None,
+                // Leaf node encountered.
+                &Slot::Leaf(m) => return Some(m),
+                // Internal node encountered.
+                Slot::Internal(child) => node = child,
+            }
+        }
+        None
+    }
+
+    /// Get an iterator over the items added to the trie.
+    pub fn items(&self) -> impl Iterator<Item = u32> + '_ {
+        self.explore(FilterHeuristic(|_| true))
+    }
+
+    /// Explores the trie using a heuristic, like `BinTrie::explore`.
+    ///
+    /// ```
+    /// # use bintrie::{PersistentBinTrie, HammingRadius};
+    /// let mut trie = PersistentBinTrie::new_depth(4);
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// for n in 0..16 {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// let mut found = trie.explore(HammingRadius::new(0b0101, 1)).collect::<Vec<u32>>();
+    /// found.sort();
+    /// assert_eq!(found, vec![0b0001, 0b0100, 0b0101, 0b0111, 0b1101]);
+    /// ```
+    pub fn explore<'a, H>(&'a self, heuristic: H) -> impl Iterator<Item = u32> + 'a
+    where
+        H: IntoHeuristic,
+        H::Heuristic: 'a,
+    {
+        ExploreIter::new(self, heuristic.into_heuristic(), vec![], accept_all)
+    }
+}
+
+impl<'a> Nodes for &'a PersistentBinTrie {
+    type Index = NodeRef<'a>;
+
+    #[inline(always)]
+    fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    #[inline(always)]
+    fn root(&self) -> NodeRef<'a> {
+        NodeRef(&self.root)
+    }
+
+    #[inline(always)]
+    fn child(&self, index: NodeRef<'a>, position: usize) -> Child<NodeRef<'a>> {
+        index.0.children[position].child()
+    }
+
+    #[inline(always)]
+    unsafe fn child_unchecked(&self, index: NodeRef<'a>, position: usize) -> Child<NodeRef<'a>> {
+        index.0.children.get_unchecked(position).child()
+    }
+
+    #[inline(always)]
+    fn terminal(&self, _index: NodeRef<'a>) -> Option<u32> {
+        // Items are only ever stored in leaves.
+        None
+    }
+
+    #[inline(always)]
+    fn child_checked(&self, index: NodeRef<'a>, position: usize) -> Child<NodeRef<'a>> {
+        // Every node is reached through a reference, so there is nothing out of range.
+        self.child(index, position)
+    }
+
+    #[inline(always)]
+    fn bucket_item(&self, _item: u32, _position: usize) -> Option<u32> {
+        // There are no buckets.
+        None
+    }
+
+    #[inline(always)]
+    fn is_verified(&self) -> bool {
+        // The nodes can only be made by inserting.
+        true
+    }
+}
@@ -0,0 +1,157 @@
+use crate::relocate::prefix_bit;
+use crate::{BinTrie, Iter, HIGH};
+use alloc::vec;
+
+/// Where the items with a prefix are found.
+enum Subtree<'a> {
+    /// No item has the prefix.
+    Empty,
+    /// Every item in the trie has the empty prefix.
+    Root,
+    /// The items are beneath a child slot of an internal node.
+    Slot(&'a u32),
+}
+
+impl BinTrie {
+    /// Finds the slot that holds every item whose key starts with the `len` bit `prefix`.
+    fn find_prefix<F>(&self, prefix: u64, len: u32, mut lookup: F) -> Subtree<'_>
+    where
+        F: FnMut(u32, u32) -> bool,
+    {
+        assert!(len <= 64 && len <= self.depth);
+        if len == 0 {
+            return Subtree::Root;
+        }
+        let mut index = 0;
+        for i in 0..len {
+            let slot = &self.internals[index].0[prefix_bit(prefix, len, i) as usize];
+            match self.slot_checked(slot) {
+                0 => return Subtree::Empty,
+                // A leaf above the end of the prefix only has it if the rest of its key matches.
+                n if n & HIGH != 0 => {
+                    let item = n & !HIGH;
+                    return if (i + 1..len).all(|l| lookup(item, l) == prefix_bit(prefix, len, l)) {
+                        Subtree::Slot(slot)
+                    } else {
+                        Subtree::Empty
+                    };
+                }
+                _ if i == len - 1 => return Subtree::Slot(slot),
+                n => index = n as usize,
+            }
+        }
+        unreachable!("the last bit of the prefix always returns")
+    }
+
+    /// Iterates over the items whose keys start with the `len` bit `prefix`.
+    ///
+    /// `F(item, n)` - A function that must be able to look up the nth bit
+    ///    from a previously inserted item.
+    ///
+    /// The prefix is a `len` bit number whose most significant bit is bit `0` of the
+    /// key, like in `group_by_prefix`, and `len` can be at most `64`. The trie is
+    /// descended to the subtree with the prefix, and only the items beneath it are
+    /// visited, in the same order as `items`. The `lookup` is used to check the
+    /// prefix of an item that is stored less than `len` levels deep.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let mut trie = BinTrie::new_depth(4);
+    /// let lookup = |n: u32, l: u32| n >> (3 - l) & 1 == 1;
+    /// for &n in &[0b0001, 0b0011, 0b1000, 0b1001] {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// let under = |prefix, len| trie.items_under_prefix(prefix, len, lookup).collect::<Vec<u32>>();
+    /// assert_eq!(under(0b00, 2), vec![0b0001, 0b0011]);
+    /// assert_eq!(under(0b1, 1), vec![0b1000, 0b1001]);
+    /// assert!(under(0b011, 3).is_empty());
+    /// assert_eq!(under(0, 0).len(), 4);
+    /// ```
+    pub fn items_under_prefix<F>(
+        &self,
+        prefix: u64,
+        len: u32,
+        lookup: F,
+    ) -> impl Iterator<Item = u32> + '_
+    where
+        F: FnMut(u32, u32) -> bool,
+    {
+        let iter = match self.find_prefix(prefix, len, lookup) {
+            Subtree::Empty => None,
+            Subtree::Root => Some(Iter::new(self)),
+            Subtree::Slot(slot) => Some(Iter::from_slot(self, slot)),
+        };
+        iter.into_iter().flatten()
+    }
+
+    /// Counts the items whose keys start with the `len` bit `prefix`.
+    ///
+    /// `F(item, n)` - A function that must be able to look up the nth bit
+    ///    from a previously inserted item.
+    ///
+    /// The prefix is the same as in `items_under_prefix`. Normally every item beneath
+    /// the prefix is visited to count them, but once `build_subtree_counts` was called
+    /// this only takes a walk down the prefix.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let mut trie = BinTrie::new_depth(8);
+    /// let lookup = |n: u32, l: u32| n >> (7 - l) & 1 == 1;
+    /// for n in 0..200 {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// assert_eq!(trie.count_under_prefix(0b1, 1, lookup), 72);
+    /// trie.build_subtree_counts();
+    /// assert_eq!(trie.count_under_prefix(0b1, 1, lookup), 72);
+    /// assert_eq!(trie.count_under_prefix(0b1100, 4, lookup), 8);
+    /// assert_eq!(trie.count_under_prefix(0, 0, lookup), 200);
+    /// ```
+    pub fn count_under_prefix<F>(&self, prefix: u64, len: u32, lookup: F) -> usize
+    where
+        F: FnMut(u32, u32) -> bool,
+    {
+        match self.find_prefix(prefix, len, lookup) {
+            Subtree::Empty => 0,
+            Subtree::Root => self.len,
+            Subtree::Slot(&n) if n & HIGH != 0 => 1 + self.bucket(n & !HIGH).len(),
+            Subtree::Slot(slot) => match &self.counts {
+                Some(counts) => counts[*slot as usize] as usize,
+                None => Iter::from_slot(self, slot).count(),
+            },
+        }
+    }
+
+    /// Counts the items beneath every internal node so that `count_under_prefix` is fast.
+    ///
+    /// This costs 4 bytes per internal node. Like the table of `build_top_table`,
+    /// the counts are a snapshot of the trie, so any modification of the items drops
+    /// them and they must be counted again once the modifications are done.
+    pub fn build_subtree_counts(&mut self) {
+        self.assert_trusted();
+        let mut counts = vec![0; self.internals.len()];
+        // Every internal node is counted after the nodes beneath it.
+        let mut order = vec![];
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            order.push(index);
+            stack.extend(self.internal_children(index));
+        }
+        for &index in order.iter().rev() {
+            let mut count = self.terminal(index).is_some() as u32;
+            for n in self.internals[index as usize].0 {
+                count += match n {
+                    0 => 0,
+                    n if n & HIGH != 0 => 1 + self.bucket(n & !HIGH).len() as u32,
+                    n => counts[n as usize],
+                };
+            }
+            counts[index as usize] = count;
+        }
+        self.counts = Some(counts);
+    }
+
+    /// Checks if counts built by `build_subtree_counts` are being used.
+    pub fn has_subtree_counts(&self) -> bool {
+        self.counts.is_some()
+    }
+}
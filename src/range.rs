@@ -0,0 +1,154 @@
+use crate::{Child, Nodes};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Iterates over leaves in key order (or reverse key order) from a starting point.
+pub(crate) struct RangeIter<T>
+where
+    T: Nodes,
+{
+    trie: T,
+    /// Each node along with how many of its children are left to visit.
+    indices: Vec<(T::Index, u8)>,
+    /// A leaf found while descending to the starting point that must come first.
+    first: Option<u32>,
+    /// The most recently yielded leaf and the position of the next item in its bucket.
+    bucket: Option<(u32, usize)>,
+    reverse: bool,
+    /// Whether the trie is not verified, so every index must be checked.
+    checked: bool,
+}
+
+impl<T> RangeIter<T>
+where
+    T: Nodes,
+{
+    /// Iterates over every item in key order.
+    pub(crate) fn all(trie: T) -> Self {
+        Self {
+            checked: !trie.is_verified(),
+            indices: vec![(trie.root(), 2)],
+            trie,
+            first: None,
+            bucket: None,
+            reverse: false,
+        }
+    }
+
+    /// Descends along `key` to find where iteration should start.
+    pub(crate) fn new<K, F>(trie: T, mut key: K, mut lookup: F, reverse: bool) -> Self
+    where
+        K: FnMut(u32) -> bool,
+        F: FnMut(u32, u32) -> bool,
+    {
+        let checked = !trie.is_verified();
+        let mut indices = vec![];
+        let mut first = None;
+        let mut index = trie.root();
+        for i in 0..trie.depth() {
+            let position = if key(i) { 1 } else { 0 };
+            // Only the children after (or before, in reverse) the key are left.
+            let remaining = if reverse { position } else { 1 - position };
+            indices.push((index, remaining as u8));
+            let child = if checked {
+                trie.child_checked(index, position)
+            } else {
+                trie.child(index, position)
+            };
+            match child {
+                // Empty node
+                Child::Empty => break,
+                // Leaf node
+                Child::Leaf(m) => {
+                    // Compare the rest of the leaf's key against the search key.
+                    let past = (i + 1..trie.depth())
+                        .map(|l| (lookup(m, l), key(l)))
+                        .find(|&(a, b)| a != b)
+                        .map(|(a, _)| a != reverse);
+                    if past.unwrap_or(true) {
+                        first = Some(m);
+                    }
+                    break;
+                }
+                // Internal node
+                Child::Internal(m) => index = m,
+            }
+        }
+        Self {
+            trie,
+            indices,
+            first,
+            bucket: None,
+            reverse,
+            checked,
+        }
+    }
+
+    /// Yields the leaf `item`, which is followed by the rest of its bucket.
+    fn leaf(&mut self, item: u32) -> Option<u32> {
+        self.bucket = Some((item, 0));
+        Some(item)
+    }
+}
+
+impl<T> Iterator for RangeIter<T>
+where
+    T: Nodes,
+{
+    type Item = u32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some((leaf, position)) = self.bucket {
+            if let Some(item) = self.trie.bucket_item(leaf, position) {
+                self.bucket = Some((leaf, position + 1));
+                return Some(item);
+            }
+            self.bucket = None;
+        }
+        if let Some(first) = self.first.take() {
+            return self.leaf(first);
+        }
+        loop {
+            let (index, remaining) = self.indices.pop()?;
+            if remaining == 0 {
+                // In reverse, a key that ends at a node comes after everything beneath it.
+                if self.reverse {
+                    if let Some(item) = self.trie.terminal(index) {
+                        return Some(item);
+                    }
+                }
+                continue;
+            }
+            self.indices.push((index, remaining - 1));
+            let position = if self.reverse {
+                remaining - 1
+            } else {
+                2 - remaining
+            };
+            let child = if self.checked {
+                self.trie.child_checked(index, position as usize)
+            } else {
+                self.trie.child(index, position as usize)
+            };
+            match child {
+                // Empty node
+                Child::Empty => {}
+                // Leaf node
+                Child::Leaf(n) => return self.leaf(n),
+                // Internal node that is too deep, which may be a cycle in a suspect trie.
+                Child::Internal(_)
+                    if self.checked && self.indices.len() >= self.trie.depth() as usize => {}
+                // Internal node
+                Child::Internal(n) => {
+                    self.indices.push((n, 2));
+                    // A key that ends at a node comes before everything beneath it.
+                    if !self.reverse {
+                        if let Some(item) = self.trie.terminal(n) {
+                            return Some(item);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
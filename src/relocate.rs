@@ -0,0 +1,130 @@
+use crate::{BinTrie, Internal, HIGH};
+use alloc::vec;
+
+/// Gets bit `n` of a `k` bit prefix whose most significant bit is bit `0` of the key.
+#[inline(always)]
+pub(crate) fn prefix_bit(prefix: u64, k: u32, n: u32) -> bool {
+    prefix >> (k - 1 - n) & 1 == 1
+}
+
+impl BinTrie {
+    /// Moves every item whose key starts with the `k` bit prefix `from` so that its key starts with `to`.
+    ///
+    /// `F(item, n)` - A function that must be able to look up the nth bit
+    ///    from a previously inserted item, as it was before relocating.
+    ///
+    /// Prefixes are `k` bit numbers whose most significant bit is bit `0` of the key,
+    /// like in `group_by_prefix`. The subtree under `from` is detached and attached
+    /// under `to` as a whole, so only the internal nodes along the two paths change
+    /// and no item is inserted again. Afterwards, the first `k` bits of the keys of the
+    /// moved items are those of `to`, and the rest of their bits are unchanged.
+    ///
+    /// This changes the path to other items, so any `InsertHint` filled in before
+    /// relocating starts over from the root the next time it is used.
+    ///
+    /// Returns `false` without changing anything if an item already has the prefix `to`.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let mut trie = BinTrie::new_depth(8);
+    /// let lookup = |n: u32, l: u32| n >> (7 - l) & 1 == 1;
+    /// for &n in &[0x11, 0x12, 0x13, 0x24, 0x35] {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// assert!(!trie.relocate_prefix(4, 0x1, 0x2, lookup));
+    /// assert!(trie.relocate_prefix(4, 0x1, 0xA, lookup));
+    /// // The moved items are now found under the new prefix.
+    /// let moved = |n: u32, l: u32| lookup(if n >> 4 == 1 { n & 0xF | 0xA0 } else { n }, l);
+    /// assert_eq!(trie.get(|l| moved(0x12, l)), Some(0x12));
+    /// assert_eq!(trie.get(|l| lookup(0x12, l)), None);
+    /// let mut fresh = BinTrie::new_depth(8);
+    /// for &n in &[0x11, 0x12, 0x13, 0x24, 0x35] {
+    ///     fresh.insert(n, |l| moved(n, l), moved);
+    /// }
+    /// assert!(trie.structurally_equal(&fresh));
+    /// ```
+    pub fn relocate_prefix<F>(&mut self, k: u32, from: u64, to: u64, mut lookup: F) -> bool
+    where
+        F: FnMut(u32, u32) -> bool,
+    {
+        self.assert_trusted();
+        assert!(k > 0 && k <= 64 && k <= self.depth);
+        // Checks if the leaf `item` found at `level` really has the `k` bit `prefix`.
+        let mut has_prefix = |item: u32, level: u32, prefix: u64| {
+            (level + 1..k).all(|l| lookup(item, l) == prefix_bit(prefix, k, l))
+        };
+        // Make sure nothing has the destination prefix.
+        let mut index = 0;
+        for i in 0..k {
+            match self.internals[index].0[prefix_bit(to, k, i) as usize] {
+                0 => break,
+                m if m & HIGH != 0 => {
+                    if has_prefix(m & !HIGH, i, to) {
+                        return false;
+                    }
+                    break;
+                }
+                _ if i == k - 1 => return false,
+                m => index = m as usize,
+            }
+        }
+        if from == to {
+            return true;
+        }
+        // Find whatever holds the items with the source prefix.
+        let mut path = vec![];
+        let mut index = 0;
+        let moving = loop {
+            let i = path.len() as u32;
+            let position = prefix_bit(from, k, i) as usize;
+            path.push((index, position));
+            match self.internals[index].0[position] {
+                // Nothing has the prefix.
+                0 => return true,
+                m if m & HIGH != 0 => {
+                    if has_prefix(m & !HIGH, i, from) {
+                        break m;
+                    }
+                    return true;
+                }
+                m if i == k - 1 => break m,
+                m => index = m as usize,
+            }
+        };
+        // The table of the top levels, the subtree counts and recorded paths would become stale.
+        self.top = None;
+        self.counts = None;
+        self.generation += 1;
+        // Detach it.
+        let (index, position) = path.pop().unwrap();
+        self.internals[index].0[position] = 0;
+        self.collapse(index, &path);
+        // Attach it along the destination path, which only has leaves of other prefixes.
+        let mut index = 0;
+        for i in 0..k {
+            let position = prefix_bit(to, k, i) as usize;
+            match self.internals[index].0[position] {
+                // A leaf can be stored as soon as there is room, but a subtree must stay at its depth.
+                0 if moving & HIGH != 0 || i == k - 1 => {
+                    self.internals[index].0[position] = moving;
+                    return true;
+                }
+                0 => {
+                    let new_index = self.allocate(Internal::default());
+                    self.internals[index].0[position] = new_index;
+                    index = new_index as usize;
+                }
+                // Push the leaf of another prefix down out of the way.
+                m if m & HIGH != 0 => {
+                    let mut new_internal = Internal::default();
+                    new_internal.0[lookup(m & !HIGH, i + 1) as usize] = m;
+                    let new_index = self.allocate(new_internal);
+                    self.internals[index].0[position] = new_index;
+                    index = new_index as usize;
+                }
+                m => index = m as usize,
+            }
+        }
+        unreachable!("the destination prefix is always reached")
+    }
+}
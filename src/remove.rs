@@ -0,0 +1,215 @@
+use crate::{BinTrie, Child, FastSet};
+use alloc::vec;
+use alloc::vec::Vec;
+
+impl BinTrie {
+    /// Removes the item with a particular key.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the key.
+    /// `F(item, n)` - A function that must be able to look up the nth bit
+    ///    from a previously inserted item.
+    ///
+    /// The `lookup` is used to make sure that the item found along the key really has
+    /// that key before removing it. Once the item is removed, any internal node left
+    /// holding only a single item is collapsed back into a leaf in its parent, so the
+    /// trie keeps the same shape it would have if the item had never been inserted.
+    ///
+    /// This changes the path to other items, so any `InsertHint` filled in before
+    /// removing starts over from the root the next time it is used. The internal nodes
+    /// that are collapsed are reused by later insertions, or can be dropped with `compact`.
+    ///
+    /// Returns `Some` of the removed item if there was one, otherwise `None`.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let mut trie = BinTrie::new_depth(32);
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// for n in 0..4 {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// assert_eq!(trie.remove(|l| lookup(2, l), lookup), Some(2));
+    /// assert_eq!(trie.remove(|l| lookup(2, l), lookup), None);
+    /// assert_eq!(trie.items().collect::<Vec<u32>>(), vec![0, 1, 3]);
+    /// let mut fresh = BinTrie::new_depth(32);
+    /// for &n in &[0, 1, 3] {
+    ///     fresh.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// assert!(trie.structurally_equal(&fresh));
+    /// ```
+    pub fn remove<K, F>(&mut self, mut key: K, mut lookup: F) -> Option<u32>
+    where
+        K: FnMut(u32) -> bool,
+        F: FnMut(u32, u32) -> bool,
+    {
+        self.assert_trusted();
+        // The internal nodes and positions taken to get to the item.
+        let mut path = vec![];
+        let mut index = 0;
+        let item = loop {
+            let level = path.len() as u32;
+            if level == self.depth {
+                return None;
+            }
+            let position = key(level) as usize;
+            path.push((index, position));
+            match Child::unpack(self.internals[index].0[position]) {
+                // Empty node encountered.
+                Child::Empty => return None,
+                // Leaf node encountered.
+                Child::Leaf(m) => break m,
+                // Internal node encountered.
+                Child::Internal(m) => index = m as usize,
+            }
+        };
+        // Make sure the item actually has the key.
+        let level = path.len() as u32;
+        if (level..self.depth).any(|l| lookup(item, l) != key(l)) {
+            return None;
+        }
+        // The table of the top levels and the subtree counts would become stale.
+        self.top = None;
+        self.counts = None;
+        self.forget_insertion(item);
+        // Another item in the bucket of the leaf takes its place if there is one.
+        let (index, position) = path.pop().unwrap();
+        let next = self.take_from_bucket(item);
+        self.internals[index].0[position] = next;
+        if next == 0 {
+            self.collapse(index, &path);
+        }
+        Some(item)
+    }
+
+    /// Collapses the internal node at `index` and then its ancestors if they hold too little.
+    ///
+    /// `path` contains the parent node and position of every ancestor of `index`.
+    pub(crate) fn collapse(&mut self, mut index: usize, path: &[(usize, usize)]) {
+        for &(parent, position) in path.iter().rev() {
+            if !self.collapse_into(index, parent, position) {
+                return;
+            }
+            index = parent;
+        }
+    }
+
+    /// Replaces the internal node at `index` in its parent if it holds too little.
+    ///
+    /// Returns whether the node was collapsed.
+    fn collapse_into(&mut self, index: usize, parent: usize, position: usize) -> bool {
+        let terminal = self.terminal(index as u32);
+        let children = self.internals[index].0.map(Child::unpack);
+        let replacement = match (children, terminal) {
+            // Nothing is left, so the node is removed.
+            ([Child::Empty, Child::Empty], None) => Child::Empty,
+            // Only the terminated item is left, so it becomes a leaf.
+            ([Child::Empty, Child::Empty], Some(item)) => {
+                self.terminals.remove(&(index as u32));
+                Child::Leaf(item)
+            }
+            // Only a single leaf is left, so it moves up.
+            ([Child::Empty, leaf @ Child::Leaf(_)], None)
+            | ([leaf @ Child::Leaf(_), Child::Empty], None) => leaf,
+            // The node still has enough beneath it.
+            _ => return false,
+        };
+        self.internals[index].0 = [0, 0];
+        self.internals[parent].0[position] = replacement.pack();
+        // The node can be reused by later insertions, so recorded paths are stale.
+        self.free.push(index as u32);
+        self.generation += 1;
+        true
+    }
+
+    /// Removes every item for which `predicate` returns `false`.
+    ///
+    /// Internal nodes left holding too little are collapsed like with `remove`, so the
+    /// trie keeps the same shape it would have if only the kept items had been
+    /// inserted. Items in buckets and terminated items are also checked. This visits
+    /// every node once, which is much faster than rebuilding the trie.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let mut trie = BinTrie::new_depth(32);
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// for n in 0..64 {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// trie.retain(|n| n % 3 == 0);
+    /// assert_eq!(trie.len(), 22);
+    /// let mut fresh = BinTrie::new_depth(32);
+    /// for n in (0..64).filter(|n| n % 3 == 0) {
+    ///     fresh.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// assert!(trie.structurally_equal(&fresh));
+    /// ```
+    pub fn retain<P>(&mut self, mut predicate: P)
+    where
+        P: FnMut(u32) -> bool,
+    {
+        self.assert_trusted();
+        // The table of the top levels and the subtree counts would become stale.
+        self.top = None;
+        self.counts = None;
+        let mut removed = FastSet::new();
+        // Each internal node, its parent and position, and whether its children are done.
+        let mut stack = vec![(0, 0, 0, false)];
+        while let Some((index, parent, position, done)) = stack.pop() {
+            if done {
+                if let Some(item) = self.terminal(index as u32) {
+                    if !predicate(item) {
+                        self.terminals.remove(&(index as u32));
+                        removed.insert(item);
+                    }
+                }
+                if index != 0 {
+                    self.collapse_into(index, parent, position);
+                }
+                continue;
+            }
+            stack.push((index, parent, position, true));
+            for child_position in 0..2 {
+                match Child::unpack(self.internals[index].0[child_position]) {
+                    Child::Empty => {}
+                    Child::Leaf(m) => {
+                        let leaf = self.retain_leaf(m, &mut predicate, &mut removed);
+                        self.internals[index].0[child_position] = leaf.pack();
+                    }
+                    Child::Internal(m) => stack.push((m as usize, index, child_position, false)),
+                }
+            }
+        }
+        self.len -= removed.len();
+        if let Some(order) = &mut self.insertion_order {
+            order.retain(|item| !removed.contains(item));
+        }
+    }
+
+    /// Filters a leaf and its bucket, returning what should take the place of the leaf.
+    fn retain_leaf<P>(&mut self, item: u32, predicate: &mut P, removed: &mut FastSet<u32>) -> Child
+    where
+        P: FnMut(u32) -> bool,
+    {
+        let bucket = match &mut self.buckets {
+            Some(buckets) => buckets.remove(&item).unwrap_or_default(),
+            None => vec![],
+        };
+        let mut kept = core::iter::once(item).chain(bucket).filter(|&n| {
+            let keep = predicate(n);
+            if !keep {
+                removed.insert(n);
+            }
+            keep
+        });
+        let leaf = match kept.next() {
+            Some(leaf) => leaf,
+            None => return Child::Empty,
+        };
+        let bucket: Vec<u32> = kept.collect();
+        if !bucket.is_empty() {
+            if let Some(buckets) = &mut self.buckets {
+                buckets.insert(leaf, bucket);
+            }
+        }
+        Child::Leaf(leaf)
+    }
+}
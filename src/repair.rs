@@ -0,0 +1,147 @@
+use crate::{BinTrie, FastSet, Internal, HIGH};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// What `BinTrie::repair` found wrong with a trie and what was lost fixing it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RepairReport {
+    /// Children that pointed past the end of the internal nodes and were made empty.
+    pub invalid_indices: usize,
+    /// Children that pointed to an internal node already in the trie, which would
+    /// form a cycle or share a subtree, and were made empty.
+    pub repeated_indices: usize,
+    /// Internal nodes that were dropped because nothing reached them or because
+    /// they were deeper than the depth of the trie.
+    pub dropped_nodes: usize,
+    /// Items that were dropped along with the nodes holding them.
+    pub lost_items: Vec<u32>,
+}
+
+impl RepairReport {
+    /// Checks if nothing was wrong with the trie.
+    pub fn is_clean(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl BinTrie {
+    /// Makes a possibly corrupt trie valid again, keeping as many items as possible.
+    ///
+    /// Every child index that is out of range or reaches an internal node a second
+    /// time is made empty, internal nodes that are unreachable or deeper than the depth
+    /// of the trie are dropped, and the remaining nodes are compacted like `compact`.
+    /// Items whose nodes were dropped are reported in the returned `RepairReport`.
+    ///
+    /// This is intended for tries recovered from damaged storage, where keeping most
+    /// of the items is preferable to keeping none of them.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let mut trie = BinTrie::new_depth(8);
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// for n in 0..16 {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// assert!(trie.repair().is_clean());
+    /// ```
+    pub fn repair(&mut self) -> RepairReport {
+        let mut report = RepairReport::default();
+        self.suspect.clear();
+        self.reached_by.clear();
+        // Dropped nodes and slots would leave the table, the counts and recorded paths out of date.
+        self.top = None;
+        self.counts = None;
+        self.generation += 1;
+        if self.internals.is_empty() {
+            self.internals.push(Internal::default());
+        }
+        let len = self.internals.len();
+        let mut reached = vec![false; len];
+        reached[0] = true;
+        // The kept internal nodes, starting with the root.
+        let mut order = vec![0];
+        // Each internal node along with its depth.
+        let mut stack = vec![(0, 0)];
+        while let Some((index, depth)) = stack.pop() {
+            for position in 0..2 {
+                let n = self.internals[index].0[position];
+                if n == 0 || n & HIGH != 0 {
+                    continue;
+                }
+                let child = n as usize;
+                if child >= len {
+                    report.invalid_indices += 1;
+                } else if reached[child] {
+                    report.repeated_indices += 1;
+                } else if depth + 1 >= self.depth {
+                    // Nothing beneath the last level can be reached, so everything there is lost.
+                    reached[child] = true;
+                    let mut lost = vec![child];
+                    while let Some(index) = lost.pop() {
+                        for n in self.internals[index].0 {
+                            if n & HIGH != 0 {
+                                report.lost_items.push(n & !HIGH);
+                                report.lost_items.extend_from_slice(self.bucket(n & !HIGH));
+                            } else if n != 0 && (n as usize) < len && !reached[n as usize] {
+                                reached[n as usize] = true;
+                                lost.push(n as usize);
+                            }
+                        }
+                    }
+                } else {
+                    reached[child] = true;
+                    order.push(n);
+                    stack.push((child, depth + 1));
+                    continue;
+                }
+                self.internals[index].0[position] = 0;
+            }
+        }
+        // Drop the terminated items of every node that is not kept, including the root.
+        let mut kept = vec![false; len];
+        for &index in &order[1..] {
+            kept[index as usize] = true;
+        }
+        let lost_items = &mut report.lost_items;
+        self.terminals.retain(|&index, &mut item| {
+            let keep = kept.get(index as usize).copied().unwrap_or(false);
+            if !keep {
+                lost_items.push(item);
+            }
+            keep
+        });
+        let unused = self.free.len();
+        report.dropped_nodes = (len - order.len()).saturating_sub(unused);
+        self.permute(&order);
+        // Only buckets of leaves that are still in the trie are kept.
+        let leaves: Vec<u32> = self
+            .internals
+            .iter()
+            .flat_map(|internal| internal.0)
+            .filter(|&n| n & HIGH != 0)
+            .map(|n| n & !HIGH)
+            .collect();
+        if let Some(buckets) = &mut self.buckets {
+            let leaves: FastSet<u32> = leaves.iter().copied().collect();
+            buckets.retain(|item, bucket| {
+                let keep = leaves.contains(item);
+                if !keep {
+                    report.lost_items.extend_from_slice(bucket);
+                }
+                keep
+            });
+        }
+        // Recount the items and forget any that were lost.
+        self.len = leaves.len() + self.terminals.len();
+        if let Some(buckets) = &self.buckets {
+            self.len += buckets.values().map(Vec::len).sum::<usize>();
+        }
+        if !report.lost_items.is_empty() {
+            let lost: FastSet<u32> = report.lost_items.iter().copied().collect();
+            if let Some(order) = &mut self.insertion_order {
+                order.retain(|item| !lost.contains(item));
+            }
+        }
+        report
+    }
+}
@@ -0,0 +1,182 @@
+use crate::explore::{accept_all, ExploreIter};
+use crate::range::RangeIter;
+use crate::validate::check_nodes;
+use crate::{ArchivedBinTrie, Child, FastSet, IntoHeuristic, InvalidTrieError, Nodes};
+use alloc::vec;
+use rkyv::bytecheck::Verify;
+use rkyv::rancor::{Fallible, Source};
+
+impl ArchivedBinTrie {
+    /// Perform a lookup for a particular item like `BinTrie::get`.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the key.
+    pub fn get<K>(&self, mut key: K) -> Option<u32>
+    where
+        K: FnMut(u32) -> bool,
+    {
+        let mut index = 0;
+        for i in 0..self.depth() {
+            match self.child(index, key(i) as usize) {
+                // Empty node encountered.
+                Child::Empty => return None,
+                // Leaf node encountered.
+                Child::Leaf(m) => return Some(m),
+                // Internal node encountered.
+                Child::Internal(m) => index = m,
+            }
+        }
+        None
+    }
+
+    /// Get an iterator over the items in the trie.
+    ///
+    /// Items in a bucket are yielded right after the item of their leaf.
+    pub fn items(&self) -> impl Iterator<Item = u32> + '_ {
+        RangeIter::all(self)
+    }
+
+    /// Iterates over the trie while using the `heuristic` to guide iteration, like `BinTrie::explore`.
+    pub fn explore<'a, H>(&'a self, heuristic: H) -> impl Iterator<Item = u32> + 'a
+    where
+        H: IntoHeuristic,
+        H::Heuristic: 'a,
+    {
+        ExploreIter::new(self, heuristic.into_heuristic(), vec![], accept_all)
+    }
+
+    /// Get the number of items in the trie.
+    pub fn len(&self) -> usize {
+        self.len.to_native() as usize
+    }
+
+    /// Check if the trie has no items.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get the maximum depth of the trie.
+    pub fn depth(&self) -> u32 {
+        self.depth.to_native()
+    }
+
+    /// Get the metadata that was set with `BinTrie::set_metadata`.
+    pub fn metadata(&self) -> &[u8] {
+        &self.metadata
+    }
+
+    /// Checks everything that `BinTrie` relies on, so an archive can't make lookups go out of bounds.
+    fn check_invariants(&self) -> Result<(), InvalidTrieError> {
+        let internals = &self.internals;
+        let (reached, leaves) = check_nodes(internals.len(), self.depth(), |index| {
+            let [left, right] = &internals[index].0;
+            [left.to_native(), right.to_native()]
+        })?;
+        for index in self.terminals.keys() {
+            let index = index.to_native();
+            if index == 0 || !reached.get(index as usize).copied().unwrap_or(false) {
+                return Err(InvalidTrieError::InvalidTerminal(index));
+            }
+        }
+        let mut free = FastSet::new();
+        for index in self.free.iter() {
+            let index = index.to_native();
+            let unused = (index as usize) < internals.len()
+                && !reached[index as usize]
+                && internals[index as usize].0 == [0, 0]
+                && free.insert(index);
+            if !unused {
+                return Err(InvalidTrieError::InvalidFreeNode(index));
+            }
+        }
+        let mut items = leaves.len() + self.terminals.len();
+        if let Some(buckets) = self.buckets.as_ref() {
+            let leaves: FastSet<u32> = leaves.iter().copied().collect();
+            for (item, bucket) in buckets.iter() {
+                if !leaves.contains(&item.to_native()) {
+                    return Err(InvalidTrieError::InvalidBucket(item.to_native()));
+                }
+                items += bucket.len();
+            }
+        }
+        let order_matches = self
+            .insertion_order
+            .as_ref()
+            .is_none_or(|order| order.len() == items);
+        if !order_matches || self.len() != items {
+            return Err(InvalidTrieError::InvalidInsertionOrder);
+        }
+        Ok(())
+    }
+}
+
+impl Nodes for &ArchivedBinTrie {
+    type Index = u32;
+
+    #[inline(always)]
+    fn depth(&self) -> u32 {
+        self.depth.to_native()
+    }
+
+    #[inline(always)]
+    fn root(&self) -> u32 {
+        0
+    }
+
+    #[inline(always)]
+    fn child(&self, index: u32, position: usize) -> Child {
+        Child::unpack(self.internals[index as usize].0[position].to_native())
+    }
+
+    #[inline(always)]
+    unsafe fn child_unchecked(&self, index: u32, position: usize) -> Child {
+        Child::unpack(
+            self.internals
+                .get_unchecked(index as usize)
+                .0
+                .get_unchecked(position)
+                .to_native(),
+        )
+    }
+
+    #[inline(always)]
+    fn terminal(&self, index: u32) -> Option<u32> {
+        if self.terminals.is_empty() {
+            return None;
+        }
+        self.terminals
+            .get(&index.into())
+            .map(|item| item.to_native())
+    }
+
+    #[inline(always)]
+    fn child_checked(&self, index: u32, position: usize) -> Child {
+        Child::unpack(
+            self.internals
+                .get(index as usize)
+                .map_or(0, |internal| internal.0[position].to_native()),
+        )
+    }
+
+    #[inline(always)]
+    fn bucket_item(&self, item: u32, position: usize) -> Option<u32> {
+        let bucket = self.buckets.as_ref()?.get(&item.into())?;
+        bucket.get(position).map(|item| item.to_native())
+    }
+
+    #[inline(always)]
+    fn is_verified(&self) -> bool {
+        // Archives are verified when they are accessed.
+        true
+    }
+}
+
+// Safety: The trie is checked after bytecheck has made sure every field can be read.
+unsafe impl<C> Verify<C> for ArchivedBinTrie
+where
+    C: Fallible + ?Sized,
+    C::Error: Source,
+{
+    fn verify(&self, _context: &mut C) -> Result<(), C::Error> {
+        self.check_invariants().map_err(C::Error::new)
+    }
+}
@@ -0,0 +1,154 @@
+use crate::explore::Shifted;
+use crate::{BinTrie, Heuristic, IntoHeuristic};
+use alloc::collections::VecDeque;
+
+/// A sliding window of tries, where each trie holds the items of one time bucket.
+///
+/// Items are inserted into the newest bucket and queries search every bucket.
+/// Calling `rotate` starts a new bucket, and once there are more buckets than the
+/// window allows, the oldest bucket is expired in `O(1)` by dropping its whole trie.
+/// This is handy for deduplicating items that were seen within a recent window.
+///
+/// ```
+/// # use bintrie::RotatingTrie;
+/// let mut window = RotatingTrie::new_depth(8, 2);
+/// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+/// window.insert(1, |l| lookup(1, l), lookup);
+/// window.rotate();
+/// window.insert(2, |l| lookup(2, l), lookup);
+/// assert_eq!(window.get(|l| lookup(1, l), lookup), Some(1));
+/// assert_eq!(window.get(|l| lookup(2, l), lookup), Some(2));
+/// // The bucket with `1` falls out of the window.
+/// let expired = window.rotate().unwrap();
+/// assert_eq!(expired.items().collect::<Vec<u32>>(), vec![1]);
+/// assert_eq!(window.get(|l| lookup(1, l), lookup), None);
+/// assert_eq!(window.items().collect::<Vec<u32>>(), vec![2]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct RotatingTrie {
+    /// The buckets from oldest to newest, which always has at least one bucket.
+    buckets: VecDeque<BinTrie>,
+    window: usize,
+    depth: u32,
+}
+
+impl RotatingTrie {
+    /// Makes a window of `window` buckets, which each have a maximum `depth` of `8192`.
+    pub fn new(window: usize) -> Self {
+        Self::new_depth(8192, window)
+    }
+
+    /// Makes a window of `window` buckets, which each have a given maximum `depth`.
+    pub fn new_depth(depth: u32, window: usize) -> Self {
+        assert!(window != 0, "the window must have at least one bucket");
+        let mut buckets = VecDeque::with_capacity(window);
+        buckets.push_back(BinTrie::new_depth(depth));
+        Self {
+            buckets,
+            window,
+            depth,
+        }
+    }
+
+    /// Inserts an item into the newest bucket.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the key.
+    /// `F(item, n)` - A function that must be able to look up the nth bit
+    ///    from a previously inserted item.
+    ///
+    /// Only items in the newest bucket can be replaced, so the same key may be in
+    /// several buckets. Use `get` first to skip items that are already in the window.
+    ///
+    /// Returns `Some` of a replaced item if one was replaced, otherwise None.
+    pub fn insert<K, F>(&mut self, item: u32, key: K, lookup: F) -> Option<u32>
+    where
+        K: FnMut(u32) -> bool,
+        F: FnMut(u32, u32) -> bool,
+    {
+        self.current_mut().insert(item, key, lookup)
+    }
+
+    /// Finds an item with exactly the same key in any bucket, newest first.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the key.
+    /// `F(item, n)` - A function that must be able to look up the nth bit
+    ///    from a previously inserted item.
+    ///
+    /// Unlike `BinTrie::get`, the `lookup` is used to check the rest of the key of
+    /// the item found in each bucket, so an item that only shares a prefix with the
+    /// key in a newer bucket doesn't hide the exact match in an older one.
+    pub fn get<K, F>(&self, mut key: K, mut lookup: F) -> Option<u32>
+    where
+        K: FnMut(u32) -> bool,
+        F: FnMut(u32, u32) -> bool,
+    {
+        for trie in self.buckets.iter().rev() {
+            if let Some(item) = trie.get(&mut key) {
+                if (0..self.depth).all(|l| lookup(item, l) == key(l)) {
+                    return Some(item);
+                }
+            }
+        }
+        None
+    }
+
+    /// Explores every bucket with the `heuristic`, newest first.
+    pub fn explore<'a, H>(&'a self, heuristic: H) -> impl Iterator<Item = u32> + 'a
+    where
+        H: IntoHeuristic,
+        H::Heuristic: 'a,
+    {
+        let mut heuristic = heuristic.into_heuristic();
+        heuristic.begin();
+        // Every bucket continues the same exploration rather than beginning a new one.
+        self.buckets.iter().rev().flat_map(move |trie| {
+            trie.explore(Shifted {
+                heuristic: heuristic.clone(),
+                levels: 0,
+            })
+        })
+    }
+
+    /// Starts a new bucket, which receives all later insertions.
+    ///
+    /// Returns the trie of the oldest bucket if it no longer fits in the window.
+    pub fn rotate(&mut self) -> Option<BinTrie> {
+        let expired = if self.buckets.len() == self.window {
+            self.buckets.pop_front()
+        } else {
+            None
+        };
+        self.buckets.push_back(BinTrie::new_depth(self.depth));
+        expired
+    }
+
+    /// Get an iterator over the items in every bucket, oldest first.
+    pub fn items(&self) -> impl Iterator<Item = u32> + '_ {
+        self.buckets.iter().flat_map(BinTrie::items)
+    }
+
+    /// Get the number of items in all of the buckets.
+    pub fn len(&self) -> usize {
+        self.buckets.iter().map(BinTrie::len).sum()
+    }
+
+    /// Check if none of the buckets have any items.
+    pub fn is_empty(&self) -> bool {
+        self.buckets.iter().all(BinTrie::is_empty)
+    }
+
+    /// Get the trie of each bucket, from oldest to newest.
+    pub fn buckets(&self) -> impl ExactSizeIterator<Item = &BinTrie> + DoubleEndedIterator {
+        self.buckets.iter()
+    }
+
+    /// Get the trie of the newest bucket, which insertions go into.
+    pub fn current(&self) -> &BinTrie {
+        self.buckets.back().expect("there is always a bucket")
+    }
+
+    /// Get the trie of the newest bucket mutably.
+    pub fn current_mut(&mut self) -> &mut BinTrie {
+        self.buckets.back_mut().expect("there is always a bucket")
+    }
+}
@@ -0,0 +1,51 @@
+use crate::{BinTrie, IntoHeuristic};
+use alloc::vec::Vec;
+
+impl BinTrie {
+    /// Takes a uniformly random sample of `k` of the items that `explore` would yield.
+    ///
+    /// `R()` - A function that provides uniformly random `u64` numbers.
+    ///
+    /// The sample is taken in a single pass with reservoir sampling, so only `k`
+    /// items are ever kept, no matter how many items the exploration finds. If it
+    /// finds fewer than `k` items, all of them are returned.
+    ///
+    /// ```
+    /// # use bintrie::{BinTrie, FilterHeuristic};
+    /// let mut trie = BinTrie::new_depth(16);
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// for n in 0..1000 {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// // A tiny xorshift generator.
+    /// let mut state = 0x2545_F491_4F6C_DD1Du64;
+    /// let rng = move || {
+    ///     state ^= state << 13;
+    ///     state ^= state >> 7;
+    ///     state ^= state << 17;
+    ///     state
+    /// };
+    /// let sample = trie.explore_sample(FilterHeuristic(|_| true), 10, rng);
+    /// assert_eq!(sample.len(), 10);
+    /// assert!(sample.iter().all(|&n| n < 1000));
+    /// ```
+    pub fn explore_sample<H, R>(&self, heuristic: H, k: usize, mut rng: R) -> Vec<u32>
+    where
+        H: IntoHeuristic,
+        R: FnMut() -> u64,
+    {
+        let mut sample = Vec::with_capacity(k);
+        for (seen, item) in self.explore(heuristic).enumerate() {
+            if seen < k {
+                sample.push(item);
+            } else {
+                // Pick a uniform position in `0..=seen` and keep the item if it is in the sample.
+                let position = ((u128::from(rng()) * (seen as u128 + 1)) >> 64) as usize;
+                if position < k {
+                    sample[position] = item;
+                }
+            }
+        }
+        sample
+    }
+}
@@ -0,0 +1,165 @@
+use crate::{BinTrie, Child, Nodes, ScoredHeuristic, Side};
+use alloc::collections::BinaryHeap;
+use core::cmp::{Ordering, Reverse};
+
+/// A branch or item waiting to be explored by `ScoredIter`.
+enum Branch<H> {
+    Item(u32),
+    /// An internal node with its level and the heuristic for beneath it.
+    Node(u32, u32, H),
+}
+
+/// A `Branch` ordered by its total score, then by the order it was found in.
+struct Scored<H> {
+    score: Reverse<(u64, u64)>,
+    branch: Branch<H>,
+}
+
+impl<H> PartialEq for Scored<H> {
+    fn eq(&self, other: &Self) -> bool {
+        self.score == other.score
+    }
+}
+
+impl<H> Eq for Scored<H> {}
+
+impl<H> PartialOrd for Scored<H> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<H> Ord for Scored<H> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.score.cmp(&other.score)
+    }
+}
+
+/// The iterator returned by `BinTrie::explore_scored`.
+struct ScoredIter<'a, H> {
+    trie: &'a BinTrie,
+    heap: BinaryHeap<Scored<H>>,
+    /// The number of branches found so far, which breaks ties in the order they were found.
+    found: u64,
+    checked: bool,
+}
+
+impl<'a, H> ScoredIter<'a, H>
+where
+    H: ScoredHeuristic,
+{
+    fn push(&mut self, score: u64, branch: Branch<H>) {
+        self.heap.push(Scored {
+            score: Reverse((score, self.found)),
+            branch,
+        });
+        self.found += 1;
+    }
+}
+
+impl<'a, H> Iterator for ScoredIter<'a, H>
+where
+    H: ScoredHeuristic,
+{
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        loop {
+            let Scored { score, branch } = self.heap.pop()?;
+            let score = (score.0).0;
+            let (index, level, heuristic) = match branch {
+                Branch::Item(item) => return Some(item),
+                Branch::Node(index, level, heuristic) => (index, level, heuristic),
+            };
+            for side in Side::BOTH {
+                let n = if self.checked {
+                    Child::unpack(self.trie.child_checked(index as usize, side.index()))
+                } else {
+                    self.trie.child(index, side.index())
+                };
+                if n == Child::Empty {
+                    continue;
+                }
+                let branch_score = match heuristic.score(side, level) {
+                    Some(branch_score) => score + u64::from(branch_score),
+                    None => continue,
+                };
+                match n {
+                    Child::Empty => {}
+                    Child::Leaf(item) => {
+                        self.push(branch_score, Branch::Item(item));
+                        for &item in self.trie.bucket(item) {
+                            self.push(branch_score, Branch::Item(item));
+                        }
+                    }
+                    // Internal node that is too deep, which may be a cycle in a suspect trie.
+                    Child::Internal(_) if self.checked && level + 1 >= self.trie.depth => {}
+                    Child::Internal(n) => {
+                        if let Some(item) = self.trie.terminal(n) {
+                            self.push(branch_score, Branch::Item(item));
+                        }
+                        let mut heuristic = heuristic.clone();
+                        heuristic.enter(side, level);
+                        self.push(branch_score, Branch::Node(n, level + 1, heuristic));
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl BinTrie {
+    /// Explores the trie best-first, always continuing with the lowest scoring branch.
+    ///
+    /// The `heuristic` scores every side it enters, and a branch's score is the total
+    /// of the scores along its path. Rather than descending depth-first like `explore`,
+    /// every branch found so far waits in one queue, so a cheap branch anywhere in the
+    /// trie is explored before an expensive one beneath the current node. Items are
+    /// yielded in the order of their scores, with ties in the order they were found.
+    ///
+    /// This keeps every waiting branch in memory, which is more than `explore` needs.
+    ///
+    /// ```
+    /// # use bintrie::{BinTrie, ScoredHeuristic, Side};
+    /// /// Scores a mismatch with `0b0101` on bit `n` as `n + 1`, up to a total of `3`.
+    /// #[derive(Clone)]
+    /// struct Weighted {
+    ///     budget: u32,
+    /// }
+    ///
+    /// impl ScoredHeuristic for Weighted {
+    ///     fn score(&self, side: Side, level: u32) -> Option<u32> {
+    ///         let matches = bool::from(side) == (0b0101 >> level & 1 == 1);
+    ///         let score = if matches { 0 } else { level + 1 };
+    ///         Some(score).filter(|&score| score <= self.budget)
+    ///     }
+    ///
+    ///     fn enter(&mut self, side: Side, level: u32) {
+    ///         self.budget -= self.score(side, level).unwrap();
+    ///     }
+    /// }
+    ///
+    /// let mut trie = BinTrie::new_depth(4);
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// for n in 0..16 {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// let found = trie.explore_scored(Weighted { budget: 3 }).collect::<Vec<u32>>();
+    /// assert_eq!(found, vec![0b0101, 0b0100, 0b0111, 0b0001, 0b0110]);
+    /// ```
+    pub fn explore_scored<'a, H>(&'a self, heuristic: H) -> impl Iterator<Item = u32> + 'a
+    where
+        H: ScoredHeuristic + 'a,
+    {
+        let mut iter = ScoredIter {
+            trie: self,
+            heap: BinaryHeap::new(),
+            found: 0,
+            checked: !self.is_verified(),
+        };
+        if !self.internals.is_empty() {
+            iter.push(0, Branch::Node(0, 0, heuristic));
+        }
+        iter
+    }
+}
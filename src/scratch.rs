@@ -0,0 +1,109 @@
+use crate::knn::Waiting;
+use crate::{CompiledHeuristic, ExploreFrame, Heuristic};
+use alloc::collections::BinaryHeap;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Reverse;
+
+/// Buffers that can be reused across queries to avoid allocating for every query.
+///
+/// Pass the same scratch to `BinTrie::explore_with` or to any of the query methods
+/// ending in `_in`, like `knn_in`, `within_distance_in` and `within_adaptive_in`,
+/// and the buffers it allocated for previous queries will be reused. Those that return a slice keep their results in the scratch as well, so
+/// they are only valid until the next query.
+///
+/// Only `explore_with` and `within_distance_in` keep the stack of the heuristic `H`.
+///
+/// ```
+/// # use bintrie::{BinTrie, QueryScratch};
+/// let mut trie = BinTrie::new_depth(8);
+/// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+/// for n in 0..64 {
+///     trie.insert(n, |l| lookup(n, l), lookup);
+/// }
+/// let mut scratch: QueryScratch = QueryScratch::new();
+/// for target in 0..4 {
+///     let nearest = trie.knn_in(|l| lookup(target, l), 1, lookup, &mut scratch);
+///     assert_eq!(nearest, &[(target, 0)]);
+///     let close = trie.within_adaptive_in(|l| lookup(target, l), 1, 8, lookup, &mut scratch);
+///     assert_eq!(close, &[(target, 0)]);
+///     let exact = trie.within_distance_in(|l| lookup(target, l), 0, lookup, &mut scratch);
+///     assert_eq!(exact.collect::<Vec<_>>(), vec![(target, 0)]);
+/// }
+/// ```
+pub struct QueryScratch<H = CompiledHeuristic>
+where
+    H: Heuristic,
+{
+    pub(crate) frames: Vec<ExploreFrame<H>>,
+    /// The best-first queue of `knn_in`, and the distances of the nearest items found so far.
+    pub(crate) queue: BinaryHeap<Reverse<(u32, Waiting)>>,
+    pub(crate) best: BinaryHeap<u32>,
+    /// The candidate nodes and items of `within_adaptive_in` bucketed by distance, and
+    /// the nodes it searches depth-first.
+    pub(crate) nodes: Vec<Vec<(u32, u32)>>,
+    pub(crate) items: Vec<Vec<u32>>,
+    pub(crate) stack: Vec<(u32, u32, u32)>,
+    /// The items and distances found by the last query.
+    pub(crate) results: Vec<(u32, u32)>,
+}
+
+impl<H> QueryScratch<H>
+where
+    H: Heuristic,
+{
+    /// Makes an empty scratch which allocates on first use.
+    pub fn new() -> Self {
+        Self {
+            frames: vec![],
+            queue: BinaryHeap::new(),
+            best: BinaryHeap::new(),
+            nodes: vec![],
+            items: vec![],
+            stack: vec![],
+            results: vec![],
+        }
+    }
+}
+
+impl<H> Default for QueryScratch<H>
+where
+    H: Heuristic,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Buffers that `BinTrie::get_batch_in` reuses across batches of lookups.
+///
+/// Batches of lookups don't use a heuristic, so unlike `QueryScratch` this is not
+/// generic over one.
+///
+/// ```
+/// # use bintrie::{BatchScratch, BinTrie};
+/// let mut trie = BinTrie::new_depth(8);
+/// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+/// for n in 0..64 {
+///     trie.insert(n, |l| lookup(n, l), lookup);
+/// }
+/// let mut scratch = BatchScratch::new();
+/// for target in 0..4 {
+///     let keys = [|l| lookup(target, l)];
+///     assert_eq!(trie.get_batch_in(&keys, &mut scratch), &[Some(target)]);
+/// }
+/// ```
+#[derive(Default)]
+pub struct BatchScratch {
+    /// The lookups in flight, as the position of their key, their internal node and its level.
+    pub(crate) lanes: Vec<(usize, usize, u32)>,
+    /// What each lookup found.
+    pub(crate) found: Vec<Option<u32>>,
+}
+
+impl BatchScratch {
+    /// Makes an empty scratch which allocates on first use.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
@@ -0,0 +1,97 @@
+use crate::{BinTrie, FastMap, Internal, LoadMode};
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// The serialized form of a `BinTrie`, borrowed from it.
+#[derive(Serialize)]
+struct TrieFields<'a> {
+    depth: u32,
+    internals: &'a [Internal],
+    terminals: &'a BTreeMap<u32, u32>,
+    free: &'a [u32],
+    buckets: &'a Option<FastMap<u32, Vec<u32>>>,
+    insertion_order: &'a Option<Vec<u32>>,
+    metadata: &'a [u8],
+}
+
+/// The serialized form of a `BinTrie`, before it has been validated.
+#[derive(Deserialize)]
+struct BinTrieRepr {
+    depth: u32,
+    internals: Vec<Internal>,
+    terminals: BTreeMap<u32, u32>,
+    free: Vec<u32>,
+    buckets: Option<FastMap<u32, Vec<u32>>>,
+    insertion_order: Option<Vec<u32>>,
+    metadata: Vec<u8>,
+}
+
+impl Serialize for BinTrie {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        TrieFields {
+            depth: self.depth,
+            internals: &self.internals,
+            terminals: &self.terminals,
+            free: &self.free,
+            buckets: &self.buckets,
+            insertion_order: &self.insertion_order,
+            metadata: &self.metadata,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for BinTrie {
+    /// Deserializes a trie, making sure that it is valid so that lookups can't go out of bounds.
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        BinTrie::deserialize_with(deserializer, LoadMode::Strict)
+    }
+}
+
+impl BinTrie {
+    /// Deserializes a trie, treating a trie that breaks the invariants of a trie according to the `mode`.
+    ///
+    /// ```
+    /// # use bintrie::{BinTrie, LoadMode};
+    /// let json = r#"{"depth":1,"internals":[[5,0],[0,0]],"terminals":{},"free":[],
+    ///     "buckets":null,"insertion_order":null,"metadata":[]}"#;
+    /// assert!(serde_json::from_str::<BinTrie>(json).is_err());
+    /// let mut de = serde_json::Deserializer::from_str(json);
+    /// let mut trie = BinTrie::deserialize_with(&mut de, LoadMode::Lenient).unwrap();
+    /// assert_eq!(trie.suspect_nodes(), &[0]);
+    /// trie.repair();
+    /// assert!(trie.is_empty());
+    /// ```
+    pub fn deserialize_with<'de, D>(deserializer: D, mode: LoadMode) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let repr = BinTrieRepr::deserialize(deserializer)?;
+        let trie = BinTrie {
+            internals: repr.internals,
+            depth: repr.depth,
+            terminals: repr.terminals,
+            insertion_order: repr.insertion_order,
+            metadata: repr.metadata,
+            top: None,
+            counts: None,
+            len: 0,
+            free: repr.free,
+            buckets: repr.buckets,
+            suspect: vec![],
+            reached_by: vec![],
+            generation: 0,
+            accesses: None,
+        };
+        trie.load(mode).map_err(D::Error::custom)
+    }
+}
@@ -0,0 +1,362 @@
+use crate::{BinTrie, HIGH};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// A difference between two tries found by `BinTrie::diff`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum DiffEntry {
+    /// The item is only in the other trie.
+    Added(u32),
+    /// The item is only in this trie.
+    Removed(u32),
+    /// The key of the `old` item of this trie holds the `new` item in the other trie.
+    Replaced {
+        /// The item of this trie.
+        old: u32,
+        /// The item of the other trie.
+        new: u32,
+    },
+}
+
+/// Where an item of one trie was found when walking it along with another trie.
+enum Found {
+    /// The item and the item of the other trie with the same key.
+    Shared(u32, u32),
+    /// The item has a key that is not in the other trie.
+    Only(u32),
+    /// Our leaf and the leaf of the other trie with the same key, which both stand
+    /// for their whole bucket.
+    Leaves(u32, u32),
+}
+
+/// Walks two tries together, finding out which items of `ours` have keys in `theirs`.
+struct Walk<'a, F> {
+    ours: &'a BinTrie,
+    theirs: &'a BinTrie,
+    lookup: F,
+    /// Children of both tries at the same place, along with the number of key bits
+    /// that lead to them. Our child is never empty, but theirs may be.
+    stack: Vec<(u32, u32, u32)>,
+    /// Items that were found and not yet returned, in reverse order.
+    found: Vec<Found>,
+}
+
+impl<'a, F> Walk<'a, F>
+where
+    F: FnMut(u32, u32) -> bool,
+{
+    fn new(ours: &'a BinTrie, theirs: &'a BinTrie, lookup: F) -> Self {
+        assert_eq!(ours.depth, theirs.depth);
+        let mut walk = Self {
+            ours,
+            theirs,
+            lookup,
+            stack: vec![],
+            found: vec![],
+        };
+        walk.enter(0, theirs.children_checked(0), theirs.terminal(0), 0);
+        walk
+    }
+
+    /// Finds the terminated item of our internal node and queues its children.
+    ///
+    /// `other` holds the children of the other trie at the same place, and
+    /// `terminal` is its terminated item there.
+    fn enter(&mut self, index: u32, other: [u32; 2], terminal: Option<u32>, level: u32) {
+        let children = self.ours.children_checked(index as usize);
+        for position in (0..2).rev() {
+            if children[position] != 0 {
+                self.stack
+                    .push((children[position], other[position], level + 1));
+            }
+        }
+        if let Some(item) = self.ours.terminal(index) {
+            let found = match terminal {
+                Some(other) => Found::Shared(item, other),
+                None => Found::Only(item),
+            };
+            self.found.push(found);
+        }
+    }
+
+    /// Queues the items of our leaf, which have the key of `other` if it is `Some`.
+    fn leaf(&mut self, item: u32, other: Option<u32>) {
+        match other {
+            Some(other) => self.found.push(Found::Leaves(item, other)),
+            None => {
+                let items = leaf_items(self.ours, item);
+                let found = self.found.len();
+                self.found.extend(items.map(Found::Only));
+                self.found[found..].reverse();
+            }
+        }
+    }
+}
+
+impl<F> Iterator for Walk<'_, F>
+where
+    F: FnMut(u32, u32) -> bool,
+{
+    type Item = Found;
+
+    fn next(&mut self) -> Option<Found> {
+        loop {
+            if let Some(found) = self.found.pop() {
+                return Some(found);
+            }
+            let (ours, theirs, level) = self.stack.pop()?;
+            match (ours, theirs) {
+                // Our leaf is alone.
+                (n, 0) if n & HIGH != 0 => self.leaf(n & !HIGH, None),
+                // The same item has the same key, so nothing has to be compared.
+                (n, m) if n & HIGH != 0 && n == m => self.leaf(n & !HIGH, Some(m & !HIGH)),
+                // Both are leaves, so the rest of their keys have to be compared.
+                (n, m) if n & HIGH != 0 && m & HIGH != 0 => {
+                    let (item, other) = (n & !HIGH, m & !HIGH);
+                    let lookup = &mut self.lookup;
+                    let same =
+                        (level..self.ours.depth).all(|l| lookup(item, l) == lookup(other, l));
+                    self.leaf(item, if same { Some(other) } else { None });
+                }
+                // Our leaf can only match their items on the side of its key.
+                (n, m) if n & HIGH != 0 => {
+                    let side = (self.lookup)(n & !HIGH, level) as usize;
+                    let other = self.theirs.child_checked(m as usize, side);
+                    self.stack.push((n, other, level + 1));
+                }
+                // Only our internal node is here.
+                (n, 0) => self.enter(n, [0, 0], None, level),
+                // Their leaf can only match our items on the side of its key.
+                (n, m) if m & HIGH != 0 => {
+                    let mut other = [0, 0];
+                    other[(self.lookup)(m & !HIGH, level) as usize] = m;
+                    self.enter(n, other, None, level);
+                }
+                // Both are internal nodes.
+                (n, m) => {
+                    let other = self.theirs.children_checked(m as usize);
+                    self.enter(n, other, self.theirs.terminal(m), level);
+                }
+            }
+        }
+    }
+}
+
+/// The item of a leaf followed by the rest of its bucket.
+fn leaf_items(trie: &BinTrie, item: u32) -> impl Iterator<Item = u32> + '_ {
+    core::iter::once(item).chain(trie.bucket(item).iter().copied())
+}
+
+/// The items of the leaf `item` of `ours` that are not in the leaf `other` of `theirs`.
+///
+/// Buckets are compared as multisets, so an item that is in `ours` more often than
+/// in `theirs` is returned for every extra time.
+fn unmatched(ours: &BinTrie, item: u32, theirs: &BinTrie, other: u32) -> Vec<u32> {
+    let mut left = leaf_items(theirs, other).collect::<Vec<u32>>();
+    leaf_items(ours, item)
+        .filter(|item| match left.iter().position(|other| other == item) {
+            Some(position) => {
+                left.swap_remove(position);
+                false
+            }
+            None => true,
+        })
+        .collect()
+}
+
+impl BinTrie {
+    /// Iterates over the items of this trie whose keys are also in `other`.
+    ///
+    /// Both tries must have the same depth and be built with the same key function.
+    /// They are walked together, so parts of this trie that are missing from `other`
+    /// are skipped without looking up any keys. Each item is yielded along with the
+    /// item of `other` that has the same key, in the order of `items`.
+    ///
+    /// `F(item, n)` - A function that must be able to look up the nth bit
+    ///    from an item of either trie.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let lookup = |n: u32, l: u32| n % 100 >> l & 1 == 1;
+    /// let mut a = BinTrie::new_depth(8);
+    /// let mut b = BinTrie::new_depth(8);
+    /// for n in 0..8 {
+    ///     a.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// for n in (104..112).step_by(2) {
+    ///     b.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// let shared = a.intersection_items(&b, lookup).collect::<Vec<_>>();
+    /// assert_eq!(shared, vec![(4, 104), (6, 106)]);
+    /// ```
+    pub fn intersection_items<'a, F>(
+        &'a self,
+        other: &'a BinTrie,
+        lookup: F,
+    ) -> impl Iterator<Item = (u32, u32)> + 'a
+    where
+        F: FnMut(u32, u32) -> bool + 'a,
+    {
+        Walk::new(self, other, lookup).flat_map(move |found| {
+            let (item, bucket, other) = match found {
+                Found::Shared(item, other) => (Some(item), &[][..], other),
+                Found::Leaves(item, other) => (Some(item), self.bucket(item), other),
+                Found::Only(_) => (None, &[][..], 0),
+            };
+            item.into_iter()
+                .chain(bucket.iter().copied())
+                .map(move |item| (item, other))
+        })
+    }
+
+    /// Iterates over the items of this trie whose keys are not in `other`.
+    ///
+    /// This walks both tries together like `intersection_items`, and whole subtrees
+    /// that `other` has nothing in are yielded without looking up any keys.
+    ///
+    /// `F(item, n)` - A function that must be able to look up the nth bit
+    ///    from an item of either trie.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let lookup = |n: u32, l: u32| n % 100 >> l & 1 == 1;
+    /// let mut a = BinTrie::new_depth(8);
+    /// let mut b = BinTrie::new_depth(8);
+    /// for n in 0..8 {
+    ///     a.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// for n in (104..112).step_by(2) {
+    ///     b.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// let mut only = a.difference_items(&b, lookup).collect::<Vec<_>>();
+    /// only.sort();
+    /// assert_eq!(only, vec![0, 1, 2, 3, 5, 7]);
+    /// ```
+    pub fn difference_items<'a, F>(
+        &'a self,
+        other: &'a BinTrie,
+        lookup: F,
+    ) -> impl Iterator<Item = u32> + 'a
+    where
+        F: FnMut(u32, u32) -> bool + 'a,
+    {
+        Walk::new(self, other, lookup).filter_map(|found| match found {
+            Found::Shared(..) | Found::Leaves(..) => None,
+            Found::Only(item) => Some(item),
+        })
+    }
+
+    /// Iterates over the differences between this trie and a later snapshot of it.
+    ///
+    /// Both tries must have the same depth and be built with the same key function.
+    /// Items of this trie that are missing from `other` or were replaced are found
+    /// first, in the order of `items`, followed by the items that were added, in the
+    /// order of `other.items()`. The tries are walked together like
+    /// `intersection_items`, so subtrees only in one of them are reported without
+    /// looking up any keys, and leaves holding the same item in both are skipped
+    /// without comparing their keys.
+    ///
+    /// Buckets with the same key are compared as multisets. Items that are only in
+    /// the bucket of this trie are paired up with items only in the bucket of `other`
+    /// as replaced, and the rest are removed or added.
+    ///
+    /// Identical subtrees are not skipped, since tries don't share nodes, so this
+    /// visits every node of both tries twice and takes time linear in their size even
+    /// when only a few items changed.
+    ///
+    /// `F(item, n)` - A function that must be able to look up the nth bit
+    ///    from an item of either trie.
+    ///
+    /// ```
+    /// # use bintrie::{BinTrie, DiffEntry};
+    /// let keys = [0u32, 1, 2, 3, 2];
+    /// let lookup = |n: u32, l: u32| keys[n as usize] >> l & 1 == 1;
+    /// let mut before = BinTrie::new_depth(4);
+    /// for n in 0..3 {
+    ///     before.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// let mut after = before.clone();
+    /// after.remove(|l| lookup(0, l), lookup);
+    /// after.insert(3, |l| lookup(3, l), lookup);
+    /// after.insert(4, |l| lookup(4, l), lookup);
+    /// let diff = before.diff(&after, lookup).collect::<Vec<_>>();
+    /// assert_eq!(
+    ///     diff,
+    ///     vec![
+    ///         DiffEntry::Removed(0),
+    ///         DiffEntry::Replaced { old: 2, new: 4 },
+    ///         DiffEntry::Added(3),
+    ///     ]
+    /// );
+    ///
+    /// let keys = [0u32, 1, 1, 1];
+    /// let lookup = |n: u32, l: u32| keys[n as usize] >> l & 1 == 1;
+    /// let mut before = BinTrie::new_depth(4).with_buckets();
+    /// for n in 0..3 {
+    ///     before.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// let mut after = before.clone();
+    /// after.insert(3, |l| lookup(3, l), lookup);
+    /// assert_eq!(before.diff(&after, lookup).collect::<Vec<_>>(), vec![DiffEntry::Added(3)]);
+    /// assert_eq!(after.diff(&before, lookup).collect::<Vec<_>>(), vec![DiffEntry::Removed(3)]);
+    /// after.remove(|l| lookup(1, l), lookup);
+    /// assert_eq!(
+    ///     before.diff(&after, lookup).collect::<Vec<_>>(),
+    ///     vec![DiffEntry::Replaced { old: 1, new: 3 }],
+    /// );
+    /// ```
+    pub fn diff<'a, F>(
+        &'a self,
+        other: &'a BinTrie,
+        lookup: F,
+    ) -> impl Iterator<Item = DiffEntry> + 'a
+    where
+        F: FnMut(u32, u32) -> bool + Clone + 'a,
+    {
+        let changed = Walk::new(self, other, lookup.clone()).flat_map(move |found| {
+            let (entry, rest) = match found {
+                Found::Shared(old, new) | Found::Leaves(old, new)
+                    if self.bucket(old).is_empty() && other.bucket(new).is_empty() =>
+                {
+                    let entry = if old == new {
+                        None
+                    } else {
+                        Some(DiffEntry::Replaced { old, new })
+                    };
+                    (entry, vec![])
+                }
+                Found::Leaves(old, new) => {
+                    let removed = unmatched(self, old, other, new);
+                    let added = unmatched(other, new, self, old);
+                    let rest = removed
+                        .iter()
+                        .enumerate()
+                        .map(|(n, &old)| match added.get(n) {
+                            Some(&new) => DiffEntry::Replaced { old, new },
+                            None => DiffEntry::Removed(old),
+                        })
+                        .collect();
+                    (None, rest)
+                }
+                Found::Shared(old, new) => (Some(DiffEntry::Replaced { old, new }), vec![]),
+                Found::Only(item) => (Some(DiffEntry::Removed(item)), vec![]),
+            };
+            entry.into_iter().chain(rest)
+        });
+        let added = Walk::new(other, self, lookup).flat_map(move |found| {
+            let (item, rest) = match found {
+                Found::Shared(..) => (None, vec![]),
+                // Added items that were paired up with removed ones were already replaced.
+                Found::Leaves(new, old) => {
+                    let replaced = unmatched(self, old, other, new).len();
+                    let mut added = unmatched(other, new, self, old);
+                    added.drain(..replaced.min(added.len()));
+                    (None, added)
+                }
+                Found::Only(item) => (Some(item), vec![]),
+            };
+            item.into_iter().chain(rest).map(DiffEntry::Added)
+        });
+        changed.chain(added)
+    }
+}
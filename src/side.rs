@@ -0,0 +1,62 @@
+/// One of the two children of an internal node.
+///
+/// `Left` corresponds to a `false` (`0`) key bit and `Right` corresponds
+/// to a `true` (`1`) key bit. Conversions to and from `bool` are provided
+/// so that key functions can continue to work with raw bits.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+impl Side {
+    /// Both sides in key order.
+    pub const BOTH: [Side; 2] = [Side::Left, Side::Right];
+
+    /// The index of this side within an internal node.
+    ///
+    /// ```
+    /// # use bintrie::Side;
+    /// assert_eq!(Side::Left.index(), 0);
+    /// assert_eq!(Side::Right.index(), 1);
+    /// ```
+    #[inline(always)]
+    pub fn index(self) -> usize {
+        match self {
+            Side::Left => 0,
+            Side::Right => 1,
+        }
+    }
+
+    /// The other side.
+    ///
+    /// ```
+    /// # use bintrie::Side;
+    /// assert_eq!(Side::Left.opposite(), Side::Right);
+    /// ```
+    #[inline(always)]
+    pub fn opposite(self) -> Self {
+        match self {
+            Side::Left => Side::Right,
+            Side::Right => Side::Left,
+        }
+    }
+}
+
+impl From<bool> for Side {
+    #[inline(always)]
+    fn from(bit: bool) -> Self {
+        if bit {
+            Side::Right
+        } else {
+            Side::Left
+        }
+    }
+}
+
+impl From<Side> for bool {
+    #[inline(always)]
+    fn from(side: Side) -> Self {
+        side == Side::Right
+    }
+}
@@ -0,0 +1,96 @@
+use crate::explore::{accept_all, ExploreIter, Location};
+use crate::{BinTrie, Heuristic, IntoHeuristic, Side};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Wraps a heuristic to track if the path taken so far exactly matches a key.
+#[derive(Clone)]
+struct Tracked<H, K> {
+    heuristic: H,
+    key: K,
+    level: u32,
+    exact: bool,
+}
+
+impl<H, K> Heuristic for Tracked<H, K>
+where
+    H: Heuristic,
+    K: Fn(u32) -> bool + Clone,
+{
+    type Iter = H::Iter;
+
+    #[inline(always)]
+    fn enter(&mut self, side: Side, level: u32) {
+        self.exact &= Side::from((self.key)(level)) == side;
+        self.level = level + 1;
+        self.heuristic.enter(side, level);
+    }
+
+    #[inline(always)]
+    fn iter(&self, level: u32) -> Self::Iter {
+        self.heuristic.iter(level)
+    }
+
+    #[inline(always)]
+    fn begin(&mut self) {
+        self.heuristic.begin();
+    }
+}
+
+impl BinTrie {
+    /// Explores the trie and separates the items found on the path of `key` from the rest.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the key.
+    ///
+    /// This runs a single exploration with the `heuristic` and returns two `Vec`s.
+    /// The first has the items that were reached by following `key` exactly, and the
+    /// second has the items that were reached by the heuristic straying from `key`.
+    /// Both are in the order they were found.
+    ///
+    /// ```
+    /// # use bintrie::{BinTrie, CompiledHeuristic};
+    /// let mut trie = BinTrie::new_depth(4);
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// for n in 0..16 {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// let key = |l| lookup(0b0101, l);
+    /// let (exact, mut probed) = trie.explore_split(key, CompiledHeuristic::hamming(key, 4, 1));
+    /// probed.sort();
+    /// assert_eq!(exact, vec![0b0101]);
+    /// assert_eq!(probed, vec![0b0001, 0b0100, 0b0111, 0b1101]);
+    /// ```
+    pub fn explore_split<K, H>(&self, key: K, heuristic: H) -> (Vec<u32>, Vec<u32>)
+    where
+        K: Fn(u32) -> bool + Clone,
+        H: IntoHeuristic,
+    {
+        let tracked = Tracked {
+            heuristic: heuristic.into_heuristic(),
+            key: key.clone(),
+            level: 0,
+            exact: true,
+        };
+        let mut iter = ExploreIter::new(self, tracked, vec![], accept_all);
+        let mut exact = vec![];
+        let mut probed = vec![];
+        while let Some(item) = iter.next() {
+            let location = iter.leaves.location;
+            let tracked = iter
+                .current_heuristic()
+                .expect("a leaf was found so its node must be on the stack");
+            // Leaves are one more branch away from the node they were found in.
+            let on_path = tracked.exact
+                && match location {
+                    Some(Location::Leaf(_, side)) => side == Side::from(key(tracked.level)),
+                    _ => true,
+                };
+            if on_path {
+                exact.push(item);
+            } else {
+                probed.push(item);
+            }
+        }
+        (exact, probed)
+    }
+}
@@ -0,0 +1,145 @@
+use crate::{BinTrie, Internal, HIGH};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::mem::size_of;
+
+/// The size and shape of a trie, as returned by `BinTrie::stats`.
+///
+/// Depths count the root as depth `0`, so a leaf in a child of the root is at depth `1`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TrieStats {
+    /// The number of internal nodes reachable from the root.
+    pub internal_nodes: usize,
+    /// The number of internal nodes kept for reuse after removals.
+    pub free_nodes: usize,
+    /// The number of leaves, which hold one item each plus any bucket.
+    pub leaves: usize,
+    /// The number of items whose keys end at an internal node.
+    pub terminated_items: usize,
+    /// The number of items in the trie.
+    pub items: usize,
+    /// The bytes allocated on the heap for the trie.
+    ///
+    /// This counts the capacity of every buffer, but only estimates the maps of
+    /// terminated items and buckets from their sizes, without the allocator overhead.
+    pub heap_bytes: usize,
+    /// The depth of the deepest leaf.
+    pub max_leaf_depth: u32,
+    /// The mean depth of the leaves, which is the number of internal nodes a lookup
+    /// visits on average to find an item.
+    pub mean_leaf_depth: f64,
+    /// The number of internal nodes at each depth, starting with the root.
+    pub nodes_per_level: Vec<usize>,
+    /// The number of leaves at each depth, starting with the root, which has none.
+    pub leaves_per_level: Vec<usize>,
+}
+
+impl TrieStats {
+    /// The fraction of the `2^level` places at a depth that have a node or a leaf.
+    ///
+    /// A level that is close to full is a good fit for `build_top_table`, while
+    /// levels that stay nearly empty point to keys sharing long prefixes.
+    pub fn occupancy(&self, level: u32) -> f64 {
+        let at = |levels: &[usize]| levels.get(level as usize).copied().unwrap_or(0);
+        let used = at(&self.nodes_per_level) + at(&self.leaves_per_level);
+        // Halving once per level also works without `std` and can't overflow.
+        (0..level).fold(used as f64, |fraction, _| fraction / 2.0)
+    }
+}
+
+impl BinTrie {
+    /// Measures the size of the trie and how its nodes and leaves are spread over levels.
+    ///
+    /// This walks every internal node once. Use it to choose a `depth` or to find key
+    /// distributions that make the trie deep and sparse.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let mut trie = BinTrie::new_depth(8);
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// for n in 0..4 {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// let stats = trie.stats();
+    /// assert_eq!(stats.internal_nodes, 3);
+    /// assert_eq!(stats.leaves, 4);
+    /// assert_eq!(stats.nodes_per_level, vec![1, 2]);
+    /// assert_eq!(stats.leaves_per_level, vec![0, 0, 4]);
+    /// assert_eq!(stats.max_leaf_depth, 2);
+    /// assert_eq!(stats.mean_leaf_depth, 2.0);
+    /// assert_eq!(stats.occupancy(2), 1.0);
+    /// assert!(stats.heap_bytes >= 3 * 8);
+    /// ```
+    pub fn stats(&self) -> TrieStats {
+        let mut stats = TrieStats {
+            free_nodes: self.free.len(),
+            terminated_items: self.terminals.len(),
+            items: self.len,
+            heap_bytes: self.heap_bytes(),
+            ..TrieStats::default()
+        };
+        let mut leaf_depths = 0;
+        let mut stack = vec![(0u32, 0u32)];
+        while let Some((index, depth)) = stack.pop() {
+            // Only follow the first slot that reaches each node of a suspect trie, and stop at the depth.
+            if depth >= self.depth {
+                continue;
+            }
+            let children = self.children_checked(index as usize);
+            count(&mut stats.nodes_per_level, depth);
+            for &n in &children {
+                match n {
+                    0 => {}
+                    n if n & HIGH != 0 => {
+                        count(&mut stats.leaves_per_level, depth + 1);
+                        stats.max_leaf_depth = stats.max_leaf_depth.max(depth + 1);
+                        leaf_depths += u64::from(depth + 1);
+                    }
+                    n => stack.push((n, depth + 1)),
+                }
+            }
+        }
+        stats.internal_nodes = stats.nodes_per_level.iter().sum();
+        stats.leaves = stats.leaves_per_level.iter().sum();
+        if stats.leaves != 0 {
+            stats.mean_leaf_depth = leaf_depths as f64 / stats.leaves as f64;
+        }
+        stats
+    }
+
+    /// Adds up the capacity of every buffer the trie owns, estimating the maps.
+    fn heap_bytes(&self) -> usize {
+        let u32s = |capacity: usize| capacity * size_of::<u32>();
+        let mut bytes = self.internals.capacity() * size_of::<Internal>()
+            + self.terminals.len() * size_of::<(u32, u32)>()
+            + self.metadata.capacity()
+            + u32s(self.free.capacity())
+            + u32s(self.suspect.capacity())
+            + self.reached_by.capacity() * size_of::<u64>();
+        if let Some(order) = &self.insertion_order {
+            bytes += u32s(order.capacity());
+        }
+        if let Some(top) = &self.top {
+            bytes += u32s(top.slots.capacity());
+        }
+        if let Some(counts) = &self.counts {
+            bytes += u32s(counts.capacity());
+        }
+        if let Some(buckets) = &self.buckets {
+            bytes += buckets
+                .values()
+                .map(|bucket| size_of::<(u32, Vec<u32>)>() + u32s(bucket.capacity()))
+                .sum::<usize>();
+        }
+        bytes
+    }
+}
+
+/// Counts one more node or leaf at `depth`.
+fn count(levels: &mut Vec<usize>, depth: u32) {
+    let depth = depth as usize;
+    if levels.len() <= depth {
+        levels.resize(depth + 1, 0);
+    }
+    levels[depth] += 1;
+}
@@ -0,0 +1,262 @@
+use crate::{BinTrie, BinTrieError, Internal, HIGH};
+
+impl BinTrie {
+    /// Inserts an item whose key may be shorter than the depth of the trie.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the key, or `None`
+    ///    if the key has fewer than `n + 1` bits.
+    /// `F(item, n)` - A function that must be able to look up the nth bit
+    ///    from a previously inserted item, or `None` if its key has ended.
+    ///
+    /// A key that ends before the depth of the trie is terminated at the internal
+    /// node where it ends, so it can never collide with a longer key that shares
+    /// its bits. Keys must have at least one bit. Terminated items are yielded by
+    /// `items`, `explore`, and the range scans just before the items beneath them.
+    ///
+    /// Tries that contain terminated keys should only be modified with this method,
+    /// since `insert` is unable to tell when the key of an existing item ends.
+    ///
+    /// Returns `Some` of a replaced item if an item with the same key was replaced.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let mut trie = BinTrie::new_depth(8);
+    /// let keys: [&[bool]; 3] = [&[true], &[true, false], &[true, false, true]];
+    /// let lookup = |n: u32, l: u32| keys[n as usize].get(l as usize).copied();
+    /// for n in 0..3 {
+    ///     trie.insert_terminated(n, |l| lookup(n, l), lookup);
+    /// }
+    /// for n in 0..3 {
+    ///     assert_eq!(trie.get_terminated(|l| lookup(n, l)), Some(n));
+    /// }
+    /// assert_eq!(trie.items().collect::<Vec<u32>>(), vec![0, 1, 2]);
+    /// ```
+    pub fn insert_terminated<K, F>(&mut self, item: u32, key: K, lookup: F) -> Option<u32>
+    where
+        K: FnMut(u32) -> Option<bool>,
+        F: FnMut(u32, u32) -> Option<bool>,
+    {
+        self.try_insert_terminated(item, key, lookup)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Inserts an item like `insert_terminated`, but returns an error rather than panicking.
+    ///
+    /// Along with the errors of `try_insert`, `BinTrieError::DepthExhausted` is returned
+    /// without changing the trie if the key has more bits than the depth of the trie.
+    ///
+    /// ```
+    /// # use bintrie::{BinTrie, BinTrieError};
+    /// let mut trie = BinTrie::new_depth(2);
+    /// let key = [true, false, true];
+    /// assert_eq!(
+    ///     trie.try_insert_terminated(0, |l| key.get(l as usize).copied(), |_, _| None),
+    ///     Err(BinTrieError::DepthExhausted),
+    /// );
+    /// assert!(trie.is_empty());
+    /// ```
+    pub fn try_insert_terminated<K, F>(
+        &mut self,
+        item: u32,
+        mut key: K,
+        mut lookup: F,
+    ) -> Result<Option<u32>, BinTrieError>
+    where
+        K: FnMut(u32) -> Option<bool>,
+        F: FnMut(u32, u32) -> Option<bool>,
+    {
+        // Always check that the high bit is not set in the item.
+        if item & HIGH != 0 {
+            return Err(BinTrieError::ItemTooLarge(item));
+        }
+        if self.is_suspect() {
+            return Err(BinTrieError::SuspectTrie);
+        }
+        // Make sure the key fits before changing anything.
+        if key(self.depth).is_some() {
+            return Err(BinTrieError::DepthExhausted);
+        }
+        // The table of the top levels and the subtree counts would become stale.
+        self.top = None;
+        self.counts = None;
+        let mut index = 0;
+        for i in 0..self.depth {
+            let position = match key(i) {
+                Some(bit) => bit as usize,
+                None => {
+                    // The key ends here, but the root can't hold a terminated key.
+                    assert!(i != 0, "keys must have at least one bit");
+                    let replaced = self.terminals.insert(index as u32, item);
+                    self.record_insertion(item, replaced);
+                    return Ok(replaced);
+                }
+            };
+            match self.internals[index].0[position] {
+                // Empty node encountered.
+                0 => {
+                    self.internals[index].0[position] = item | HIGH;
+                    self.record_insertion(item, None);
+                    return Ok(None);
+                }
+                // Leaf node encountered at the last level is replaced.
+                m if m & HIGH != 0 && i == self.depth - 1 => {
+                    self.internals[index].0[position] = item | HIGH;
+                    self.record_insertion(item, Some(m & !HIGH));
+                    return Ok(Some(m & !HIGH));
+                }
+                // Leaf node encountered.
+                m if m & HIGH != 0 => {
+                    let new_index = self.try_allocate(Internal::default())?;
+                    // Either move the existing leaf down or terminate it at the new node.
+                    match lookup(m & !HIGH, i + 1) {
+                        Some(bit) => self.internals[new_index as usize].0[bit as usize] = m,
+                        None => {
+                            self.terminals.insert(new_index, m & !HIGH);
+                        }
+                    }
+                    self.internals[index].0[position] = new_index;
+                    index = new_index as usize;
+                }
+                // Internal node encountered.
+                m => index = m as usize,
+            }
+        }
+        unreachable!("the last level always places the item")
+    }
+
+    /// Inserts an item for a prefix made of the first `len` bits of `key`.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the key, which is
+    ///    only asked for bits before `len`.
+    /// `F(item, n)` - A function that must be able to look up the nth bit
+    ///    from a previously inserted item, or `None` if its prefix has ended.
+    ///
+    /// This is `insert_terminated` for keys with a known length, such as the routes of
+    /// a routing table. The item covers every key that starts with the prefix, so it
+    /// is found for any of them by `longest_prefix_match` unless a longer prefix also
+    /// covers the key. `len` must be at least `1` and at most the depth of the trie.
+    ///
+    /// Returns `Some` of a replaced item if an item with the same prefix was replaced.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let routes: [(u32, u32); 2] = [(0xC0A8_0000, 16), (0xC0A8_0100, 24)];
+    /// let bit = |address: u32, l: u32| address >> (31 - l) & 1 == 1;
+    /// let lookup = |n: u32, l: u32| {
+    ///     let (address, len) = routes[n as usize];
+    ///     (l < len).then(|| bit(address, l))
+    /// };
+    /// let mut trie = BinTrie::new_depth(32);
+    /// for (n, &(address, len)) in routes.iter().enumerate() {
+    ///     trie.insert_prefix(n as u32, |l| bit(address, l), len, lookup);
+    /// }
+    /// let route = |address: u32| trie.longest_prefix_match(|l| bit(address, l), lookup);
+    /// assert_eq!(route(0xC0A8_0101), Some(1));
+    /// assert_eq!(route(0xC0A8_FF01), Some(0));
+    /// assert_eq!(route(0xC0A9_0000), None);
+    /// ```
+    pub fn insert_prefix<K, F>(&mut self, item: u32, mut key: K, len: u32, lookup: F) -> Option<u32>
+    where
+        K: FnMut(u32) -> bool,
+        F: FnMut(u32, u32) -> Option<bool>,
+    {
+        self.assert_trusted();
+        assert!(
+            len > 0 && len <= self.depth,
+            "prefixes must have between 1 and depth bits"
+        );
+        self.insert_terminated(item, |l| if l < len { Some(key(l)) } else { None }, lookup)
+    }
+
+    /// Perform a lookup for an item whose key may be shorter than the depth of the trie.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the key, or `None`
+    ///    if the key has fewer than `n + 1` bits.
+    ///
+    /// See `insert_terminated` for an example.
+    pub fn get_terminated<K>(&self, mut key: K) -> Option<u32>
+    where
+        K: FnMut(u32) -> Option<bool>,
+    {
+        let mut index = 0;
+        for i in 0..self.depth {
+            let position = match key(i) {
+                Some(bit) => bit as usize,
+                None => return self.terminal(index as u32),
+            };
+            match self.internals[index].0[position] {
+                // Empty node encountered.
+                0 => return None,
+                // Leaf node encountered.
+                m if m & HIGH != 0 => return Some(m & !HIGH),
+                // Internal node encountered.
+                m => index = m as usize,
+            }
+        }
+        None
+    }
+
+    /// Finds the item with the longest key that is a prefix of `key`.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the key.
+    /// `F(item, n)` - A function that must be able to look up the nth bit
+    ///    from a previously inserted item, or `None` if its key has ended.
+    ///
+    /// This is the longest prefix match of a routing table, where every item is a
+    /// route whose key is its prefix, inserted with `insert_terminated`. The trie is
+    /// descended along `key`, remembering the deepest terminated item passed, until
+    /// the path ends. A leaf at the end of the path is the match if the rest of its
+    /// key matches, and otherwise the deepest terminated item is.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// // Routes for 10.0.0.0/8, 10.1.0.0/16 and 10.1.2.0/24.
+    /// let routes: [(u32, u32); 3] = [(0x0A00_0000, 8), (0x0A01_0000, 16), (0x0A01_0200, 24)];
+    /// let lookup = |n: u32, l: u32| {
+    ///     let (address, len) = routes[n as usize];
+    ///     (l < len).then(|| address >> (31 - l) & 1 == 1)
+    /// };
+    /// let mut trie = BinTrie::new_depth(32);
+    /// for n in 0..3 {
+    ///     trie.insert_terminated(n, |l| lookup(n, l), lookup);
+    /// }
+    /// let route = |address: u32| trie.longest_prefix_match(|l| address >> (31 - l) & 1 == 1, lookup);
+    /// assert_eq!(route(0x0A01_0203), Some(2));
+    /// assert_eq!(route(0x0A01_0303), Some(1));
+    /// assert_eq!(route(0x0A02_0000), Some(0));
+    /// assert_eq!(route(0x0B00_0000), None);
+    /// ```
+    pub fn longest_prefix_match<K, F>(&self, mut key: K, mut lookup: F) -> Option<u32>
+    where
+        K: FnMut(u32) -> bool,
+        F: FnMut(u32, u32) -> Option<bool>,
+    {
+        let mut longest = None;
+        let mut index = 0;
+        for i in 0..self.depth {
+            match self.internals[index].0[key(i) as usize] {
+                // Empty node encountered.
+                0 => break,
+                // Leaf node encountered, which only matches if the rest of its key does.
+                m if m & HIGH != 0 => {
+                    let item = m & !HIGH;
+                    let matches = (i + 1..self.depth)
+                        .map_while(|l| lookup(item, l).map(|bit| bit == key(l)))
+                        .all(|matches| matches);
+                    if matches {
+                        longest = Some(item);
+                    }
+                    break;
+                }
+                // Internal node encountered.
+                m => {
+                    if let Some(item) = self.terminal(m) {
+                        longest = Some(item);
+                    }
+                    index = m as usize;
+                }
+            }
+        }
+        longest
+    }
+}
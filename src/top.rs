@@ -0,0 +1,81 @@
+use crate::{BinTrie, HIGH};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// A direct lookup table that replaces the top levels of a trie.
+#[derive(Clone, Debug)]
+pub(crate) struct TopTable {
+    /// The number of levels the table replaces.
+    pub(crate) bits: u32,
+    /// What is found after following each prefix of `bits` bits from the root.
+    ///
+    /// Each slot is encoded the same way as a child of an internal node.
+    pub(crate) slots: Vec<u32>,
+}
+
+impl TopTable {
+    /// Finds the slot for the first `bits` bits of `key`.
+    #[inline(always)]
+    pub(crate) fn slot<K>(&self, key: &mut K) -> u32
+    where
+        K: FnMut(u32) -> bool,
+    {
+        let prefix = (0..self.bits).fold(0, |prefix, l| prefix << 1 | key(l) as usize);
+        self.slots[prefix]
+    }
+}
+
+impl BinTrie {
+    /// Replaces the first `bits` levels of lookups with a direct table of `2^bits` entries.
+    ///
+    /// With the table, `get` finds the subtree for the first `bits` bits of the key
+    /// with a single table access rather than following `bits` internal nodes, which
+    /// is a large win for dense tries. The table costs `4 * 2^bits` bytes and `bits`
+    /// can be at most `24`.
+    ///
+    /// The table is a snapshot of the trie, so any modification of the items drops
+    /// it and it must be built again once the modifications are done. Moving the
+    /// nodes with `compact` or `optimize_layout` keeps it.
+    ///
+    /// ```
+    /// # use bintrie::BinTrie;
+    /// let mut trie = BinTrie::new_depth(16);
+    /// let lookup = |n: u32, l: u32| n >> (15 - l) & 1 == 1;
+    /// for n in (0..1000).map(|n| n * 61) {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// let before: Vec<_> = (0..1 << 16).map(|n| trie.get(|l| lookup(n, l))).collect();
+    /// trie.build_top_table(8);
+    /// assert!(trie.has_top_table());
+    /// let after: Vec<_> = (0..1 << 16).map(|n| trie.get(|l| lookup(n, l))).collect();
+    /// assert_eq!(before, after);
+    /// assert_eq!(trie.get(|l| lookup(61, l)), Some(61));
+    /// trie.insert(1, |l| lookup(1, l), lookup);
+    /// assert!(!trie.has_top_table());
+    /// ```
+    pub fn build_top_table(&mut self, bits: u32) {
+        self.assert_trusted();
+        assert!(bits <= 24 && bits < self.depth);
+        let mut slots = vec![0; 1 << bits];
+        // Each internal node along with its depth and the prefix leading to it.
+        let mut stack = vec![(0, 0, 0)];
+        while let Some((index, depth, prefix)) = stack.pop() {
+            for (position, &n) in self.internals[index].0.iter().enumerate() {
+                let prefix = prefix << 1 | position;
+                if n != 0 && n & HIGH == 0 && depth + 1 < bits {
+                    stack.push((n as usize, depth + 1, prefix));
+                } else {
+                    // Everything with this prefix ends up at the same place.
+                    let shift = bits - depth - 1;
+                    slots[prefix << shift..(prefix + 1) << shift].fill(n);
+                }
+            }
+        }
+        self.top = Some(TopTable { bits, slots });
+    }
+
+    /// Checks if a table built by `build_top_table` is being used.
+    pub fn has_top_table(&self) -> bool {
+        self.top.is_some()
+    }
+}
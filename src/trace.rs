@@ -0,0 +1,123 @@
+use crate::explore::{accept_all, ExploreIter, Observer, OwnedExplore};
+use crate::{BinTrie, Child, Heuristic, Side};
+use alloc::collections::VecDeque;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// A step taken while exploring a trie, as reported by `BinTrie::explore_trace`.
+///
+/// Every event carries the `depth` of the branch it describes, which is also the
+/// index of the key bit that chooses between the two sides at that branch.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum VisitEvent {
+    /// The internal node on `side` of the branch at `depth` was entered.
+    Enter { depth: u32, side: Side },
+    /// The leaf `item` on `side` of the branch at `depth` was yielded.
+    ///
+    /// Every item in the bucket of the leaf is reported with its own event.
+    Leaf { item: u32, depth: u32, side: Side },
+    /// The `item` whose key ends at the internal node just entered was yielded.
+    ///
+    /// The `depth` is that of the branch at the node, which is the length of the key.
+    Terminal { item: u32, depth: u32 },
+    /// The heuristic did not choose the occupied `side` of the branch at `depth`.
+    Prune { depth: u32, side: Side },
+}
+
+/// Turns the steps of an exploration into `VisitEvent`s.
+struct Recorder {
+    /// A mask of the sides chosen so far at every node on the stack.
+    visited: Vec<u8>,
+    /// Events waiting to be yielded, in order.
+    events: VecDeque<VisitEvent>,
+}
+
+impl Observer for Recorder {
+    fn choose(&mut self, side: Side) {
+        if let Some(visited) = self.visited.last_mut() {
+            *visited |= 1 << side.index();
+        }
+    }
+
+    fn enter(&mut self, level: u32, side: Side) {
+        self.visited.push(0);
+        self.events
+            .push_back(VisitEvent::Enter { depth: level, side });
+    }
+
+    fn exhaust<I, C>(&mut self, level: u32, children: C)
+    where
+        C: FnOnce() -> [Child<I>; 2],
+    {
+        let visited = self.visited.pop().unwrap_or(0);
+        let children = children();
+        // Report every occupied side that was never chosen.
+        for &side in Side::BOTH.iter() {
+            let occupied = !matches!(children[side.index()], Child::Empty);
+            if visited & 1 << side.index() == 0 && occupied {
+                self.events
+                    .push_back(VisitEvent::Prune { depth: level, side });
+            }
+        }
+    }
+
+    fn leaf(&mut self, item: u32, level: u32, side: Side) {
+        self.events.push_back(VisitEvent::Leaf {
+            item,
+            depth: level,
+            side,
+        });
+    }
+
+    fn terminal(&mut self, item: u32, level: u32) {
+        self.events
+            .push_back(VisitEvent::Terminal { item, depth: level });
+    }
+}
+
+/// An exploration that yields the steps it takes rather than the items.
+pub(crate) struct TraceIter<'a, H>
+where
+    H: Heuristic,
+{
+    explore: OwnedExplore<'a, H, fn(u32) -> bool, Recorder>,
+    /// Whether the exploration has run out.
+    done: bool,
+}
+
+impl<'a, H> TraceIter<'a, H>
+where
+    H: Heuristic,
+{
+    pub(crate) fn new(trie: &'a BinTrie, heuristic: H) -> Self {
+        let recorder = Recorder {
+            visited: vec![0],
+            events: VecDeque::new(),
+        };
+        Self {
+            explore: ExploreIter::new(trie, heuristic, vec![], accept_all as fn(u32) -> bool)
+                .observe(recorder),
+            done: false,
+        }
+    }
+}
+
+impl<'a, H> Iterator for TraceIter<'a, H>
+where
+    H: Heuristic,
+{
+    type Item = VisitEvent;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.explore.observer.events.pop_front() {
+                return Some(event);
+            }
+            if self.done {
+                return None;
+            }
+            // Every step up to the next item is recorded along the way.
+            self.done = self.explore.next().is_none();
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use crate::{BinTrie, Collision, IntoHeuristic};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// A trie whose items are full 64-bit IDs, such as pointers or document IDs.
+///
+/// The items of a `BinTrie` are limited to 31 bits, since the high bit marks
+/// leaves. Here the leaves of the underlying `BinTrie` hold indices into a
+/// vector of the items instead, so any `u64` can be stored without a separate
+/// table that maps the items to smaller IDs.
+///
+/// ```
+/// # use bintrie::BinTrie64;
+/// let mut trie = BinTrie64::new_depth(8);
+/// let lookup = |n: u64, l: u32| n >> (63 - l) & 1 == 1;
+/// let ids = [0x0100_0000_0000_0001, 0x8000_0000_dead_beef, u64::MAX];
+/// for &id in &ids {
+///     trie.insert(id, |l| lookup(id, l), lookup);
+/// }
+/// assert_eq!(trie.get(|l| lookup(u64::MAX, l)), Some(u64::MAX));
+/// assert_eq!(trie.items().collect::<Vec<_>>(), ids);
+/// ```
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "Trie64Repr"))]
+pub struct BinTrie64 {
+    /// Stores an index into `items` in each leaf.
+    trie: BinTrie,
+    items: Vec<u64>,
+}
+
+impl BinTrie64 {
+    /// Makes a new trie with a maximum `depth` of `8192`.
+    pub fn new() -> Self {
+        Self::new_depth(8192)
+    }
+
+    /// Makes a new trie with a given maximum `depth`.
+    pub fn new_depth(depth: u32) -> Self {
+        Self {
+            trie: BinTrie::new_depth(depth),
+            items: vec![],
+        }
+    }
+
+    /// The maximum depth of the trie.
+    pub fn depth(&self) -> u32 {
+        self.trie.depth()
+    }
+
+    /// Inserts an item.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the key.
+    /// `F(item, n)` - A function that must be able to look up the nth bit
+    ///    from a previously inserted item.
+    ///
+    /// Returns `Some` of a replaced item if one was replaced, otherwise None.
+    pub fn insert<K, F>(&mut self, item: u64, key: K, mut lookup: F) -> Option<u64>
+    where
+        K: FnMut(u32) -> bool,
+        F: FnMut(u64, u32) -> bool,
+    {
+        let index = self.items.len() as u32;
+        let items = &self.items;
+        let mut replaced = None;
+        // On a collision, the existing index is kept and its item is overwritten.
+        self.trie.insert_merge(
+            index,
+            key,
+            |index, n| lookup(items[index as usize], n),
+            |Collision { existing, .. }| {
+                replaced = Some(existing as usize);
+                existing
+            },
+        );
+        if let Some(existing) = replaced {
+            Some(core::mem::replace(&mut self.items[existing], item))
+        } else {
+            self.items.push(item);
+            None
+        }
+    }
+
+    /// Perform a lookup for a particular item.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the key.
+    pub fn get<K>(&self, key: K) -> Option<u64>
+    where
+        K: FnMut(u32) -> bool,
+    {
+        Some(self.items[self.trie.get(key)? as usize])
+    }
+
+    /// Get an iterator over the items added to the trie.
+    pub fn items(&self) -> impl ExactSizeIterator<Item = u64> + '_ {
+        self.trie
+            .items()
+            .map(move |index| self.items[index as usize])
+    }
+
+    /// Explores the trie using a heuristic, like `BinTrie::explore`.
+    pub fn explore<'a, H>(&'a self, heuristic: H) -> impl Iterator<Item = u64> + 'a
+    where
+        H: IntoHeuristic,
+        H::Heuristic: 'a,
+    {
+        self.trie
+            .explore(heuristic)
+            .map(move |index| self.items[index as usize])
+    }
+
+    /// The number of items in the trie.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Checks if the trie has no items.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+impl Default for BinTrie64 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The serialized form of a `BinTrie64`, before it has been validated.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct Trie64Repr {
+    trie: BinTrie,
+    items: Vec<u64>,
+}
+
+#[cfg(feature = "serde")]
+impl core::convert::TryFrom<Trie64Repr> for BinTrie64 {
+    type Error = crate::InvalidTrieError;
+
+    /// Makes sure that every leaf refers to an item.
+    fn try_from(repr: Trie64Repr) -> Result<Self, Self::Error> {
+        let Trie64Repr { trie, items } = repr;
+        if let Some(index) = trie.items().find(|&index| index as usize >= items.len()) {
+            return Err(crate::InvalidTrieError::InvalidEntry(index));
+        }
+        Ok(Self { trie, items })
+    }
+}
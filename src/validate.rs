@@ -0,0 +1,345 @@
+use crate::{BinTrie, BinTrieError, Child, FastSet, InvalidTrieError};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::mem;
+
+/// The slot that reaches an internal node which is not in the trie.
+pub(crate) const UNREACHED: u64 = u64::MAX;
+
+/// Identifies the child at `position` of the internal node at `index`.
+pub(crate) fn slot(index: usize, position: usize) -> u64 {
+    2 * index as u64 + position as u64
+}
+
+/// How a trie loaded from outside of the program is treated when it breaks the invariants of a trie.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LoadMode {
+    /// Reject the trie with an error.
+    Strict,
+    /// Accept the trie, but mark it as suspect along with the internal nodes where the
+    /// problems were found, so that as much of a partially corrupt trie as possible is
+    /// available instead of failing to load it at all.
+    ///
+    /// Until `BinTrie::validate` or `BinTrie::repair` succeeds, `get`, `items`, `explore`,
+    /// and the range scans of a suspect trie check every index and stop at the depth of
+    /// the trie, which is slower. The other queries also check every index, and every
+    /// method that modifies the trie returns `BinTrieError::SuspectTrie` or panics.
+    Lenient,
+}
+
+/// Checks that `len` internal nodes form a tree of at most `depth` levels beneath the root.
+///
+/// `C(index)` - A function that provides the children of the internal node at `index`.
+///
+/// Returns which internal nodes are in the tree along with the items of every leaf.
+pub(crate) fn check_nodes<C>(
+    len: usize,
+    depth: u32,
+    mut children: C,
+) -> Result<(Vec<bool>, Vec<u32>), InvalidTrieError>
+where
+    C: FnMut(usize) -> [u32; 2],
+{
+    if depth == 0 {
+        return Err(InvalidTrieError::ZeroDepth);
+    }
+    if len == 0 {
+        return Err(InvalidTrieError::MissingRoot);
+    }
+    let mut reached = vec![false; len];
+    reached[0] = true;
+    let mut leaves = vec![];
+    // Each internal node along with its depth.
+    let mut stack = vec![(0, 0)];
+    while let Some((index, level)) = stack.pop() {
+        for n in children(index) {
+            if let Child::Leaf(item) = Child::unpack(n) {
+                leaves.push(item);
+            } else if n != 0 {
+                if n as usize >= len {
+                    return Err(InvalidTrieError::IndexOutOfRange(n));
+                }
+                if reached[n as usize] {
+                    return Err(InvalidTrieError::RepeatedIndex(n));
+                }
+                if level + 1 >= depth {
+                    return Err(InvalidTrieError::TooDeep(n));
+                }
+                reached[n as usize] = true;
+                stack.push((n as usize, level + 1));
+            }
+        }
+    }
+    Ok((reached, leaves))
+}
+
+/// Finds the internal nodes with children that break the invariants of a trie of
+/// `len` internal nodes, along with the child slot that first reaches each internal node.
+///
+/// `C(index)` - A function that provides the children of the internal node at `index`.
+///
+/// Problems that don't belong to a child, such as an invalid free list, mark the root.
+pub(crate) fn find_suspects<C>(len: usize, depth: u32, mut children: C) -> (Vec<u32>, Vec<u64>)
+where
+    C: FnMut(usize) -> [u32; 2],
+{
+    if len == 0 {
+        return (vec![0], vec![]);
+    }
+    let mut reached = vec![false; len];
+    reached[0] = true;
+    let mut reached_by = vec![UNREACHED; len];
+    let mut suspect = vec![];
+    // Each internal node along with its depth.
+    let mut stack = vec![(0, 0)];
+    while let Some((index, level)) = stack.pop() {
+        let mut valid = true;
+        for (position, &n) in children(index).iter().enumerate() {
+            let n = match Child::unpack(n) {
+                Child::Internal(n) => n,
+                Child::Empty | Child::Leaf(_) => continue,
+            };
+            if n as usize >= len || reached[n as usize] || level + 1 >= depth {
+                valid = false;
+            } else {
+                reached[n as usize] = true;
+                reached_by[n as usize] = slot(index, position);
+                stack.push((n as usize, level + 1));
+            }
+        }
+        if !valid {
+            suspect.push(index as u32);
+        }
+    }
+    if suspect.is_empty() {
+        suspect.push(0);
+    }
+    suspect.sort_unstable();
+    (suspect, reached_by)
+}
+
+/// Treats the child `n` at `position` of the internal node at `index` as empty unless
+/// it is the slot that first reaches it, as found by `find_suspects`.
+///
+/// Without any `reached_by`, the trie is not suspect and `n` is always kept.
+pub(crate) fn first_reach(reached_by: &[u64], index: usize, position: usize, n: u32) -> u32 {
+    if reached_by.is_empty() {
+        return n;
+    }
+    match Child::unpack(n) {
+        Child::Internal(m) if reached_by.get(m as usize) != Some(&slot(index, position)) => 0,
+        _ => n,
+    }
+}
+
+impl BinTrie {
+    /// Checks that the trie upholds every invariant that the rest of the trie relies on.
+    ///
+    /// This is needed before using a trie from outside of the program, since lookups
+    /// index the internal nodes without bounds checks. Returns the number of items.
+    pub(crate) fn check_invariants(&self) -> Result<usize, InvalidTrieError> {
+        let (reached, leaves) = check_nodes(self.internals.len(), self.depth, |index| {
+            self.internals[index].0
+        })?;
+        let len = self.internals.len();
+        for &index in self.terminals.keys() {
+            if index == 0 || !reached.get(index as usize).copied().unwrap_or(false) {
+                return Err(InvalidTrieError::InvalidTerminal(index));
+            }
+        }
+        let mut free = FastSet::new();
+        for &index in &self.free {
+            let unused = (index as usize) < len
+                && !reached[index as usize]
+                && self.internals[index as usize].0 == [0, 0]
+                && free.insert(index);
+            if !unused {
+                return Err(InvalidTrieError::InvalidFreeNode(index));
+            }
+        }
+        let mut items = leaves.len() + self.terminals.len();
+        if let Some(buckets) = &self.buckets {
+            let leaves: FastSet<u32> = leaves.iter().copied().collect();
+            for (&item, bucket) in buckets {
+                if !leaves.contains(&item) {
+                    return Err(InvalidTrieError::InvalidBucket(item));
+                }
+                items += bucket.len();
+            }
+        }
+        if let Some(order) = &self.insertion_order {
+            if order.len() != items {
+                return Err(InvalidTrieError::InvalidInsertionOrder);
+            }
+        }
+        Ok(items)
+    }
+
+    /// Loads a trie from outside of the program according to the `mode`.
+    #[cfg(any(feature = "std", feature = "serde"))]
+    pub(crate) fn load(mut self, mode: LoadMode) -> Result<BinTrie, InvalidTrieError> {
+        match self.check_invariants() {
+            Ok(len) => self.len = len,
+            Err(error) if mode == LoadMode::Strict => return Err(error),
+            Err(_) => {
+                let (suspect, reached_by) = self.find_suspects();
+                self.suspect = suspect;
+                self.reached_by = reached_by;
+                // Count whatever a checked traversal can reach.
+                self.len = crate::Iter::new(&self).count();
+            }
+        }
+        Ok(self)
+    }
+
+    /// Finds the internal nodes with children that break the invariants of the trie,
+    /// along with the child slot that first reaches each internal node.
+    #[cfg(any(feature = "std", feature = "serde"))]
+    fn find_suspects(&self) -> (Vec<u32>, Vec<u64>) {
+        find_suspects(self.internals.len(), self.depth, |index| {
+            self.internals[index].0
+        })
+    }
+
+    /// Checks that the trie upholds every invariant of a trie, so that it can be queried.
+    ///
+    /// A trie that was loaded with `LoadMode::Lenient` is no longer suspect once this
+    /// succeeds. Tries made by this crate are always valid.
+    ///
+    /// ```
+    /// # #[cfg(feature = "std")]
+    /// # {
+    /// # use bintrie::{BinTrie, LoadMode};
+    /// let mut trie = BinTrie::new_depth(8);
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// for n in 0..16 {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// let mut bytes = vec![];
+    /// trie.write_to(&mut bytes).unwrap();
+    /// // Corrupt the depth in the header so that the nodes are too deep.
+    /// bytes[5] = 2;
+    /// assert!(BinTrie::read_from(&bytes[..]).is_err());
+    /// let mut loaded = BinTrie::read_from_with(&bytes[..], LoadMode::Lenient).unwrap();
+    /// assert!(loaded.is_suspect());
+    /// // Queries still work, but check every index until the trie is repaired.
+    /// let reachable = loaded.items().count();
+    /// assert!(loaded.validate().is_err());
+    /// let report = loaded.repair();
+    /// assert!(!loaded.is_suspect());
+    /// assert_eq!(loaded.len(), reachable);
+    /// assert_eq!(loaded.len() + report.lost_items.len(), 16);
+    /// # }
+    /// ```
+    pub fn validate(&mut self) -> Result<(), InvalidTrieError> {
+        self.len = self.check_invariants()?;
+        self.suspect.clear();
+        self.reached_by.clear();
+        Ok(())
+    }
+
+    /// Checks if the trie was loaded leniently and broke the invariants of a trie.
+    ///
+    /// Tries that are not suspect are verified, so queries use the fast unchecked paths.
+    /// A suspect trie can't be modified until it is validated or repaired.
+    ///
+    /// ```
+    /// # #[cfg(feature = "std")]
+    /// # {
+    /// # use bintrie::{BinTrie, BinTrieError, LoadMode};
+    /// // The root points to an internal node that does not exist.
+    /// let bytes = b"BTRI\x01\x08\x01\x14\0\0\0\0\0\0";
+    /// let mut trie = BinTrie::read_from_with(&bytes[..], LoadMode::Lenient).unwrap();
+    /// assert!(trie.is_suspect());
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// assert_eq!(
+    ///     trie.try_insert(3, |l| lookup(3, l), lookup),
+    ///     Err(BinTrieError::SuspectTrie),
+    /// );
+    /// assert_eq!(trie.knn(|l| lookup(3, l), 1, lookup), vec![]);
+    /// trie.repair();
+    /// assert_eq!(trie.try_insert(3, |l| lookup(3, l), lookup), Ok(None));
+    /// # }
+    /// ```
+    pub fn is_suspect(&self) -> bool {
+        !self.suspect.is_empty()
+    }
+
+    /// Panics if the trie is suspect, since modifying it would follow its unchecked indices.
+    pub(crate) fn assert_trusted(&self) {
+        if self.is_suspect() {
+            panic!("{}", BinTrieError::SuspectTrie);
+        }
+    }
+
+    /// Perform a lookup like `get`, but checking every index of a suspect trie.
+    pub(crate) fn get_checked<K>(&self, mut key: K) -> Option<u32>
+    where
+        K: FnMut(u32) -> bool,
+    {
+        let mut index = 0;
+        for i in 0..self.depth {
+            match Child::unpack(self.child_checked(index, key(i) as usize)) {
+                // Empty node encountered.
+                Child::Empty => return None,
+                // Leaf node encountered.
+                Child::Leaf(m) => return Some(m),
+                // Internal node encountered.
+                Child::Internal(m) => index = m as usize,
+            }
+        }
+        None
+    }
+
+    /// Gets a child like `Nodes::child_checked`, but only follows the first slot that
+    /// reaches each internal node of a suspect trie.
+    ///
+    /// The other slots may form cycles or share subtrees, and would make a traversal
+    /// take exponential time, so they are treated as empty like `repair` makes them.
+    pub(crate) fn child_checked(&self, index: usize, position: usize) -> u32 {
+        let n = self
+            .internals
+            .get(index)
+            .map_or(0, |internal| internal.0[position]);
+        first_reach(&self.reached_by, index, position, n)
+    }
+
+    /// Gets both children of an internal node like `child_checked`.
+    pub(crate) fn children_checked(&self, index: usize) -> [u32; 2] {
+        [self.child_checked(index, 0), self.child_checked(index, 1)]
+    }
+
+    /// Gets the child in a `slot` of the internal nodes like `child_checked`.
+    ///
+    /// Slots that are not in the internal nodes are empty.
+    pub(crate) fn slot_checked(&self, slot: &u32) -> u32 {
+        let offset = (slot as *const u32 as usize).wrapping_sub(self.internals.as_ptr() as usize)
+            / mem::size_of::<u32>();
+        self.child_checked(offset / 2, offset % 2)
+    }
+
+    /// Gets the sorted internal nodes where a lenient load found problems.
+    ///
+    /// The subtrees beneath these nodes are the ones that may be lost by `repair`.
+    /// Until then, a child that reaches an internal node a second time is treated
+    /// as empty, so queries visit every internal node at most once.
+    ///
+    /// ```
+    /// # #[cfg(feature = "std")]
+    /// # {
+    /// # use bintrie::{BinTrie, LoadMode};
+    /// // Both children of every internal node point to the next one, which would
+    /// // otherwise make for 2^40 paths.
+    /// let mut bytes = b"BTRI\x01\x40\x29".to_vec();
+    /// bytes.extend_from_slice(&[4; 80]);
+    /// bytes.extend_from_slice(&[0b11, 0b101, 0, 0, 0, 0, 0]);
+    /// let trie = BinTrie::read_from_with(&bytes[..], LoadMode::Lenient).unwrap();
+    /// assert_eq!(trie.suspect_nodes().len(), 40);
+    /// assert_eq!(trie.items().collect::<Vec<u32>>(), vec![1, 2]);
+    /// assert_eq!(trie.stats().internal_nodes, 41);
+    /// # }
+    /// ```
+    pub fn suspect_nodes(&self) -> &[u32] {
+        &self.suspect
+    }
+}
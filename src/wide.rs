@@ -0,0 +1,309 @@
+use crate::explore::{accept_all, ExploreIter};
+use crate::{Child, FilterHeuristic, IntoHeuristic, Nodes, HIGH};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// A trie whose internal nodes consume `BITS` key bits at once.
+///
+/// Every internal node has `2^BITS` children, so a key of `depth` bits takes
+/// `depth / BITS` steps to look up instead of `depth`. This trades memory for
+/// fewer pointer chases, and works best for dense tries, like ones keyed by
+/// hashes. `BITS` must be between `1` and `8`; `WideTrie<1>` is laid out like
+/// a `BinTrie`.
+///
+/// The children of a node are in key order, so `items` returns the items in
+/// the same order as `BinTrie::items`. Exploring uses the same `Heuristic`s
+/// as `BinTrie`, which are still asked about every key bit individually.
+///
+/// ```
+/// # use bintrie::{BinTrie, WideTrie};
+/// let lookup = |n: u32, l: u32| n.wrapping_mul(0x9E37_79B9) >> (31 - l) & 1 == 1;
+/// let mut wide = WideTrie::<4>::new_depth(32);
+/// let mut trie = BinTrie::new_depth(32);
+/// for n in 0..1000 {
+///     wide.insert(n, |l| lookup(n, l), lookup);
+///     trie.insert(n, |l| lookup(n, l), lookup);
+/// }
+/// assert_eq!(wide.levels(), 8);
+/// assert_eq!(wide.len(), 1000);
+/// assert_eq!(wide.items().collect::<Vec<_>>(), trie.items().collect::<Vec<_>>());
+/// for n in 0..1000 {
+///     assert_eq!(wide.get(|l| lookup(n, l)), Some(n));
+/// }
+/// ```
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WideTrie<const BITS: u32> {
+    /// The children of every internal node, `2^BITS` at a time.
+    /// The root node is always at index `0`.
+    internals: Vec<u32>,
+    /// The maximum depth to stop at.
+    depth: u32,
+    /// The number of items in the trie.
+    len: usize,
+}
+
+impl<const BITS: u32> Default for WideTrie<BITS> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const BITS: u32> WideTrie<BITS> {
+    /// The number of children of an internal node.
+    const FANOUT: usize = 1 << BITS;
+
+    /// Makes a new trie with a maximum `depth` of `8192`.
+    pub fn new() -> Self {
+        Self::new_depth(8192)
+    }
+
+    /// Makes a new trie with a given maximum `depth` in bits.
+    pub fn new_depth(depth: u32) -> Self {
+        assert!(depth > 0);
+        assert!((1..=8).contains(&BITS), "BITS must be between 1 and 8");
+        Self {
+            internals: vec![0; Self::FANOUT],
+            depth,
+            len: 0,
+        }
+    }
+
+    /// The maximum depth of a key in bits.
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    /// The number of levels of internal nodes, which is the most steps a lookup takes.
+    pub fn levels(&self) -> u32 {
+        self.depth.div_ceil(BITS)
+    }
+
+    /// The number of items in the trie.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Checks if the trie has no items.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The number of internal nodes, including the root.
+    pub fn node_count(&self) -> usize {
+        self.internals.len() / Self::FANOUT
+    }
+
+    /// The child of a node at a given level which a key goes to.
+    ///
+    /// Key bits past the depth of the trie are treated as `false`.
+    #[inline(always)]
+    fn position<K>(&self, mut key: K, level: u32) -> usize
+    where
+        K: FnMut(u32) -> bool,
+    {
+        let start = level * BITS;
+        (start..start + BITS).fold(0, |position, l| {
+            position << 1 | (l < self.depth && key(l)) as usize
+        })
+    }
+
+    /// Inserts an item.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the key.
+    /// `F(item, n)` - A function that must be able to look up the nth bit
+    ///    from a previously inserted item.
+    ///
+    /// Returns `Some` of a replaced leaf if a leaf was replaced, otherwise None.
+    pub fn insert<K, F>(&mut self, item: u32, mut key: K, mut lookup: F) -> Option<u32>
+    where
+        K: FnMut(u32) -> bool,
+        F: FnMut(u32, u32) -> bool,
+    {
+        // Always check that the high bit is not set in the item.
+        assert!(item & HIGH == 0);
+        let mut index = 0;
+        for level in 0..self.levels() - 1 {
+            let slot = index * Self::FANOUT + self.position(&mut key, level);
+            match self.internals[slot] {
+                // Empty node encountered.
+                0 => {
+                    self.internals[slot] = item | HIGH;
+                    self.len += 1;
+                    return None;
+                }
+                // Leaf node encountered.
+                m if m & HIGH != 0 => {
+                    let new_index = self.node_count() as u32;
+                    // Panic if we go too high to fit in our indices.
+                    assert!(new_index & HIGH == 0);
+                    let position = self.position(|l| lookup(m & !HIGH, l), level + 1);
+                    self.internals
+                        .resize(self.internals.len() + Self::FANOUT, 0);
+                    self.internals[new_index as usize * Self::FANOUT + position] = m;
+                    self.internals[slot] = new_index;
+                    index = new_index as usize;
+                }
+                // Internal node encountered.
+                m => index = m as usize,
+            }
+        }
+        let slot = index * Self::FANOUT + self.position(&mut key, self.levels() - 1);
+        match core::mem::replace(&mut self.internals[slot], item | HIGH) {
+            0 => {
+                self.len += 1;
+                None
+            }
+            m => Some(m & !HIGH),
+        }
+    }
+
+    /// Perform a lookup for a particular item.
+    ///
+    /// `K(n)` - A function that provides the `n`th bit for the key.
+    pub fn get<K>(&self, mut key: K) -> Option<u32>
+    where
+        K: FnMut(u32) -> bool,
+    {
+        let mut index = 0;
+        for level in 0..self.levels() {
+            match self.internals[index * Self::FANOUT + self.position(&mut key, level)] {
+                // Empty node encountered.
+                0 => return None,
+                // Leaf node encountered.
+                m if m & HIGH != 0 => return Some(m & !HIGH),
+                // Internal node encountered.
+                m => index = m as usize,
+            }
+        }
+        None
+    }
+
+    /// Get an iterator over the items added to the trie.
+    pub fn items(&self) -> impl Iterator<Item = u32> + '_ {
+        self.explore(FilterHeuristic(|_| true))
+    }
+
+    /// Explores the trie using a heuristic, like `BinTrie::explore`.
+    ///
+    /// The heuristic chooses the sides of every key bit within a node in turn,
+    /// and the children of the node are visited in the order it chooses them.
+    ///
+    /// ```
+    /// # use bintrie::{FilterHeuristic, HammingRadius, Side, WideTrie};
+    /// let mut trie = WideTrie::<2>::new_depth(4);
+    /// let lookup = |n: u32, l: u32| n >> (3 - l) & 1 == 1;
+    /// for n in 0..16 {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// let found: Vec<u32> = trie.explore(FilterHeuristic(|s| s == Side::Right)).collect();
+    /// assert_eq!(found, vec![0b1111]);
+    /// // The last level of internal nodes only uses one of its 3 key bits.
+    /// let mut trie = WideTrie::<3>::new_depth(4);
+    /// let lookup = |n: u32, l: u32| n >> l & 1 == 1;
+    /// for n in 0..16 {
+    ///     trie.insert(n, |l| lookup(n, l), lookup);
+    /// }
+    /// let mut found: Vec<u32> = trie.explore(HammingRadius::new(0b0101, 1)).collect();
+    /// found.sort();
+    /// assert_eq!(found, vec![0b0001, 0b0100, 0b0101, 0b0111, 0b1101]);
+    /// ```
+    pub fn explore<'a, H>(&'a self, heuristic: H) -> impl Iterator<Item = u32> + 'a
+    where
+        H: IntoHeuristic,
+        H::Heuristic: 'a,
+    {
+        ExploreIter::new(self, heuristic.into_heuristic(), vec![], accept_all)
+    }
+
+    /// Decodes the child in a `slot` of an internal node whose first key bit is `start`.
+    #[inline(always)]
+    fn slot_child(&self, slot: usize, start: u32) -> Child<Branch> {
+        match self.internals[slot] {
+            0 => Child::Empty,
+            m if m & HIGH != 0 => Child::Leaf(m & !HIGH),
+            m => Child::Internal(Branch {
+                node: m,
+                start: start + BITS,
+                path: 1,
+            }),
+        }
+    }
+}
+
+/// A branch of the binary trie of `BITS` levels that an internal node of a
+/// `WideTrie` stands for, which lets it be explored one key bit at a time.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) struct Branch {
+    /// The internal node the branch is in.
+    node: u32,
+    /// The first key bit of the internal node.
+    start: u32,
+    /// The sides chosen so far within the internal node, after a leading `1`.
+    path: u32,
+}
+
+impl<const BITS: u32> Nodes for &WideTrie<BITS> {
+    type Index = Branch;
+
+    #[inline(always)]
+    fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    #[inline(always)]
+    fn root(&self) -> Branch {
+        Branch {
+            node: 0,
+            start: 0,
+            path: 1,
+        }
+    }
+
+    #[inline(always)]
+    fn child(&self, index: Branch, position: usize) -> Child<Branch> {
+        let path = index.path << 1 | position as u32;
+        let bits = 31 - path.leading_zeros();
+        if bits == BITS || index.start + bits >= self.depth {
+            // Key bits past the depth are always `false`, like in `position`.
+            let position = (path & !(1 << bits)) << (BITS - bits);
+            let slot = index.node as usize * WideTrie::<BITS>::FANOUT + position as usize;
+            self.slot_child(slot, index.start)
+        } else {
+            Child::Internal(Branch { path, ..index })
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn child_unchecked(&self, index: Branch, position: usize) -> Child<Branch> {
+        // Deserialized tries are not validated, so the bounds are still checked.
+        self.child(index, position)
+    }
+
+    #[inline(always)]
+    fn terminal(&self, _index: Branch) -> Option<u32> {
+        // Keys can't be terminated early.
+        None
+    }
+
+    #[inline(always)]
+    fn child_checked(&self, index: Branch, position: usize) -> Child<Branch> {
+        if (index.node as usize) < self.node_count() {
+            self.child(index, position)
+        } else {
+            Child::Empty
+        }
+    }
+
+    #[inline(always)]
+    fn bucket_item(&self, _item: u32, _position: usize) -> Option<u32> {
+        // Every leaf holds a single item.
+        None
+    }
+
+    #[inline(always)]
+    fn is_verified(&self) -> bool {
+        // A child that is out of range panics in `child_unchecked` instead.
+        true
+    }
+}